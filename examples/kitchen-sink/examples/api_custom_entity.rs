@@ -7,7 +7,7 @@ use lazybe::macros::{Entity, EntityEndpoint};
 use lazybe::openapi::{CreateRouterDoc, DeleteRouterDoc, GetRouterDoc, ListRouterDoc};
 use lazybe::router::{CreateRouter, DeleteRouter, GetRouter, ListRouter, RouteConfig};
 use serde::{Deserialize, Serialize};
-use sqlx::{Executor, Sqlite, SqlitePool};
+use sqlx::{Acquire, Executor, Sqlite, SqlitePool};
 use utoipa::ToSchema;
 use utoipa::openapi::{Info, OpenApiBuilder, Server};
 use utoipa_redoc::{Redoc, Servable};
@@ -77,19 +77,22 @@ pub struct BookBatch {
 }
 
 impl CreateEntity<Sqlite> for BookBatch {
-    async fn create<Ctx>(
+    async fn create<'a, Ctx>(
         ctx: &Ctx,
-        tx: &mut sqlx::Transaction<'_, Sqlite>,
+        conn: impl Acquire<'a, Database = Sqlite> + Send + 'a,
         input: Self::Create,
     ) -> Result<Self, sqlx::Error>
     where
         Ctx: DbCtx<Sqlite> + Sync,
     {
+        // all books are created or none of them
+        let mut tx = conn.begin().await?;
         let mut books = Vec::with_capacity(input.len());
         for create_book in input {
-            let book = ctx.create::<Book>(tx, create_book).await?;
+            let book = ctx.create::<Book>(&mut tx, create_book).await?;
             books.push(book);
         }
+        tx.commit().await?;
         Ok(BookBatch { books })
     }
 }
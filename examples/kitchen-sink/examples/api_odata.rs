@@ -0,0 +1,99 @@
+use chrono::NaiveDate;
+use lazybe::axum::Router;
+use lazybe::db::sqlite::SqliteDbCtx;
+use lazybe::macros::Entity;
+use lazybe::openapi::{CreateRouterDoc, DeleteRouterDoc, GetRouterDoc, ListRouterDoc, UpdateRouterDoc};
+use lazybe::router::{CreateRouter, DeleteRouter, GetRouter, ListRouter, RouteConfig, UpdateRouter};
+use serde::{Deserialize, Serialize};
+use sqlx::{Executor, Sqlite, SqlitePool};
+use utoipa::ToSchema;
+use utoipa::openapi::{Info, OpenApiBuilder, Server};
+use utoipa_redoc::{Redoc, Servable};
+
+#[derive(Clone)]
+struct AppState {
+    ctx: SqliteDbCtx,
+    pool: SqlitePool,
+}
+
+impl RouteConfig for AppState {
+    type Ctx = SqliteDbCtx;
+    type Db = Sqlite;
+
+    fn db_ctx(&self) -> (Self::Ctx, SqlitePool) {
+        (self.ctx.clone(), self.pool.clone())
+    }
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    tracing_subscriber::fmt::init();
+
+    let ctx = SqliteDbCtx;
+    let pool = SqlitePool::connect("sqlite::memory:").await?;
+    migrate(&pool).await?;
+
+    let openapi = OpenApiBuilder::new()
+        .info(Info::new("Todo Example", "0.1.0"))
+        .servers(Some([Server::new("http://localhost:8080")]))
+        .build()
+        .merge_from(Book::get_endpoint_doc(None))
+        .merge_from(Book::list_endpoint_doc(None))
+        .merge_from(Book::create_endpoint_doc(None))
+        .merge_from(Book::update_endpoint_doc(None))
+        .merge_from(Book::replace_endpoint_doc(None))
+        .merge_from(Book::delete_endpoint_doc(None));
+
+    let app = Router::new()
+        .merge(Redoc::with_url("/", openapi))
+        .merge(Book::get_endpoint())
+        .merge(Book::list_endpoint())
+        .merge(Book::create_endpoint())
+        .merge(Book::update_endpoint())
+        .merge(Book::replace_endpoint())
+        .merge(Book::delete_endpoint())
+        .with_state(AppState { ctx, pool });
+
+    let listener = tokio::net::TcpListener::bind("0.0.0.0:8080").await?;
+    tracing::info!("Server is listening on 0.0.0.0:8080");
+    lazybe::axum::serve(listener, app).await?;
+    Ok(())
+}
+
+/// Try these queries:
+/// - `/books?$filter=author eq 'Frank Herbert' and publication_date ge 1970-01-01`
+/// - `/books?$filter=contains(title,'Dune') or pages gt 500&$orderby=pages desc`
+/// - `/books?$select=id,title&$top=2&$count=true`
+#[derive(Debug, Clone, Serialize, Deserialize, Entity, ToSchema)]
+#[lazybe(table = "book", endpoint = "/books", collection_api = "odata", derive_to_schema)]
+pub struct Book {
+    #[lazybe(primary_key)]
+    pub id: u32,
+    pub title: String,
+    pub author: String,
+    pub pages: Option<u32>,
+    pub publication_date: NaiveDate,
+}
+
+async fn migrate(pool: &SqlitePool) -> anyhow::Result<()> {
+    pool.execute(
+        r#"
+CREATE TABLE IF NOT EXISTS book (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    title TEXT NOT NULL,
+    author TEXT NOT NULL,
+    pages INTEGER,
+    publication_date DATE NOT NULL
+);
+
+INSERT INTO book (title, author, pages, publication_date) VALUES
+    ('Dune', 'Frank Herbert', 412, '1965-08-01'),
+    ('Dune Messiah', 'Frank Herbert', 256, '1969-10-15'),
+    ('Children of Dune', 'Frank Herbert', 444, '1976-04-01'),
+    ('The Left Hand of Darkness', 'Ursula K. Le Guin', NULL, '1969-03-01'),
+    ('The Dispossessed', 'Ursula K. Le Guin', 387, '1974-05-01');
+        "#,
+    )
+    .await?;
+    Ok(())
+}
@@ -1,6 +1,8 @@
 use chrono::NaiveDate;
-use lazybe::axum::Router;
+use lazybe::axum::response::{IntoResponse, Response};
+use lazybe::axum::{Json, Router};
 use lazybe::db::sqlite::SqliteDbCtx;
+use lazybe::fields::{Fields, Partial};
 use lazybe::filter::Filter;
 use lazybe::macros::Entity;
 use lazybe::openapi::{CreateRouterDoc, DeleteRouterDoc, GetRouterDoc, ListRouterDoc, UpdateRouterDoc};
@@ -83,6 +85,15 @@ pub struct PaginatedBook {
     pub data: Vec<Book>,
 }
 
+/// The same envelope as `PaginatedBook` with the fields selected by `?fields=...`
+#[derive(Debug, Serialize)]
+pub struct PartialPaginatedBook {
+    pub page: u32,
+    pub page_size: u32,
+    pub count: u32,
+    pub data: Vec<Partial<Book>>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct BookQuery {
     page: Option<u32>,
@@ -103,6 +114,21 @@ impl EntityCollectionApi for Book {
         }
     }
 
+    fn partial_page_response(
+        page: Page<Partial<Self>>,
+        _input: &Self::Query,
+        _fields: &Fields<Self>,
+        _path: &str,
+    ) -> Response {
+        let resp = PartialPaginatedBook {
+            page: page.page,
+            page_size: page.page_size,
+            count: page.total_records,
+            data: page.data,
+        };
+        Json(resp).into_response()
+    }
+
     fn page_input(input: &Self::Query) -> Option<PaginationInput> {
         Some(PaginationInput {
             page: input.page.unwrap_or(0),
@@ -2,6 +2,8 @@ use chrono::NaiveDate;
 use lazybe::db::DbOps;
 use lazybe::db::sqlite::SqliteDbCtx;
 use lazybe::macros::Entity;
+use lazybe::schema::TableSchema;
+use sea_query::SqliteQueryBuilder;
 use sqlx::{Executor, SqlitePool};
 
 #[tokio::main]
@@ -49,16 +51,7 @@ pub struct Book {
 }
 
 async fn migrate(pool: &SqlitePool) -> anyhow::Result<()> {
-    pool.execute(
-        r#"
-CREATE TABLE IF NOT EXISTS book (
-    id INTEGER PRIMARY KEY AUTOINCREMENT,
-    title TEXT NOT NULL,
-    author TEXT NOT NULL,
-    publication_date DATE NOT NULL
-);
-        "#,
-    )
-    .await?;
+    let create_table = Book::create_table().if_not_exists().to_string(SqliteQueryBuilder);
+    pool.execute(create_table.as_str()).await?;
     Ok(())
 }
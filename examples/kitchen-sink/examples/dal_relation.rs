@@ -0,0 +1,195 @@
+use lazybe::db::DbOps;
+use lazybe::db::sqlite::SqliteDbCtx;
+use lazybe::filter::Filter;
+use lazybe::macros::Entity;
+use lazybe::sort::Sort;
+use sqlx::{Executor, SqlitePool};
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let ctx = SqliteDbCtx;
+    let pool = SqlitePool::connect("sqlite::memory:").await?;
+    migrate(&pool).await?;
+    seed(&ctx, &pool).await?;
+
+    let mut tx = pool.begin().await?;
+
+    let books_by_british_authors = ctx
+        .list::<Book>(
+            &mut tx,
+            Filter::all([BookFilter::author().country().eq("United Kingdom".to_string())]),
+            Sort::new([BookSort::title().asc()]),
+            None,
+        )
+        .await?
+        .data
+        .into_iter()
+        .map(|i| i.title)
+        .collect::<Vec<_>>();
+
+    let books_by_author_name = ctx
+        .list::<Book>(
+            &mut tx,
+            Filter::empty(),
+            Sort::new([BookSort::author().name().asc(), BookSort::title().asc()]),
+            None,
+        )
+        .await?
+        .data
+        .into_iter()
+        .map(|i| i.title)
+        .collect::<Vec<_>>();
+
+    let books_from_penguin = ctx
+        .list::<Book>(
+            &mut tx,
+            Filter::all([BookFilter::author().publisher().name().eq("Penguin".to_string())]),
+            Sort::new([BookSort::author().name().desc(), BookSort::title().asc()]),
+            None,
+        )
+        .await?
+        .data
+        .into_iter()
+        .map(|i| i.title)
+        .collect::<Vec<_>>();
+
+    let books_by_publisher_name = ctx
+        .list::<Book>(
+            &mut tx,
+            Filter::empty(),
+            Sort::new([
+                BookSort::author().publisher().name().lower().asc().nulls_last(),
+                BookSort::title().lower().asc(),
+            ]),
+            None,
+        )
+        .await?
+        .data
+        .into_iter()
+        .map(|i| i.title)
+        .collect::<Vec<_>>();
+
+    println!("Books by British authors: {:#?}", books_by_british_authors);
+    println!("Books sorted by author name: {:#?}", books_by_author_name);
+    println!("Books by authors published by Penguin: {:#?}", books_from_penguin);
+    println!("Books sorted by publisher name: {:#?}", books_by_publisher_name);
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+#[derive(Debug, Clone, Entity)]
+#[lazybe(table = "publisher")]
+pub struct Publisher {
+    #[lazybe(primary_key)]
+    pub id: u32,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Entity)]
+#[lazybe(table = "author")]
+pub struct Author {
+    #[lazybe(primary_key)]
+    pub id: u32,
+    pub name: String,
+    pub country: String,
+    #[lazybe(references = "Publisher")]
+    pub publisher_id: Option<u32>,
+}
+
+#[derive(Debug, Clone, Entity)]
+#[lazybe(table = "book")]
+pub struct Book {
+    #[lazybe(primary_key)]
+    pub id: u32,
+    pub title: String,
+    #[lazybe(references = "Author")]
+    pub author_id: u32,
+}
+
+async fn migrate(pool: &SqlitePool) -> anyhow::Result<()> {
+    pool.execute(
+        r#"
+CREATE TABLE IF NOT EXISTS publisher (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS author (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL,
+    country TEXT NOT NULL,
+    publisher_id INTEGER REFERENCES publisher (id)
+);
+
+CREATE TABLE IF NOT EXISTS book (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    title TEXT NOT NULL,
+    author_id INTEGER NOT NULL REFERENCES author (id)
+);
+        "#,
+    )
+    .await?;
+    Ok(())
+}
+
+async fn seed(ctx: &SqliteDbCtx, pool: &SqlitePool) -> anyhow::Result<()> {
+    let mut tx = pool.begin().await?;
+
+    let penguin = ctx
+        .create::<Publisher>(
+            &mut tx,
+            CreatePublisher {
+                name: "Penguin".to_string(),
+            },
+        )
+        .await?;
+
+    let author_defs = [
+        (
+            "George Orwell",
+            "United Kingdom",
+            Some(penguin.id),
+            vec!["1984", "Animal Farm"],
+        ),
+        (
+            "Haruki Murakami",
+            "Japan",
+            None,
+            vec!["Norwegian Wood", "Kafka on the Shore"],
+        ),
+        (
+            "Jane Austen",
+            "United Kingdom",
+            Some(penguin.id),
+            vec!["Emma", "Persuasion"],
+        ),
+    ];
+
+    for (name, country, publisher_id, titles) in author_defs {
+        let author = ctx
+            .create::<Author>(
+                &mut tx,
+                CreateAuthor {
+                    name: name.to_string(),
+                    country: country.to_string(),
+                    publisher_id,
+                },
+            )
+            .await?;
+        for title in titles {
+            ctx.create::<Book>(
+                &mut tx,
+                CreateBook {
+                    title: title.to_string(),
+                    author_id: author.id,
+                },
+            )
+            .await?;
+        }
+    }
+
+    tx.commit().await?;
+    Ok(())
+}
@@ -0,0 +1,133 @@
+use lazybe::db::DbOps;
+use lazybe::db::sqlite::SqliteDbCtx;
+use lazybe::filter::Filter;
+use lazybe::macros::Entity;
+use lazybe::sort::Sort;
+use sqlx::{Executor, SqlitePool};
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let ctx = SqliteDbCtx;
+    let pool = SqlitePool::connect("sqlite::memory:").await?;
+    migrate(&pool).await?;
+    seed(&ctx, &pool).await?;
+
+    let mut tx = pool.begin().await?;
+
+    let books_about_dragons = ctx
+        .list::<Book>(&mut tx, BookFilter::search("dragon"), Sort::empty(), None)
+        .await?
+        .data
+        .into_iter()
+        .map(|i| i.title)
+        .collect::<Vec<_>>();
+
+    let books_by_relevance = ctx
+        .list::<Book>(
+            &mut tx,
+            Filter::search("wizard school"),
+            Sort::new([BookSort::search_rank("wizard school").desc(), BookSort::id().asc()]),
+            None,
+        )
+        .await?
+        .data
+        .into_iter()
+        .map(|i| i.title)
+        .collect::<Vec<_>>();
+
+    let short_books_about_dragons = ctx
+        .list::<Book>(
+            &mut tx,
+            Filter::all([BookFilter::search("dragon"), BookFilter::pages().lt(300).into()]),
+            Sort::empty(),
+            None,
+        )
+        .await?
+        .data
+        .into_iter()
+        .map(|i| i.title)
+        .collect::<Vec<_>>();
+
+    println!("Books about dragons: {:#?}", books_about_dragons);
+    println!("Books about wizard school by relevance: {:#?}", books_by_relevance);
+    println!("Short books about dragons: {:#?}", short_books_about_dragons);
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+#[derive(Debug, Clone, Entity)]
+#[lazybe(table = "book")]
+pub struct Book {
+    #[lazybe(primary_key)]
+    pub id: u32,
+    #[lazybe(searchable)]
+    pub title: String,
+    #[lazybe(searchable)]
+    pub description: Option<String>,
+    pub pages: u32,
+}
+
+async fn migrate(pool: &SqlitePool) -> anyhow::Result<()> {
+    pool.execute(
+        r#"
+CREATE TABLE IF NOT EXISTS book (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    title TEXT NOT NULL,
+    description TEXT,
+    pages INTEGER NOT NULL
+);
+
+CREATE VIRTUAL TABLE IF NOT EXISTS book_fts USING fts5(id UNINDEXED, title, description);
+
+CREATE TRIGGER IF NOT EXISTS book_fts_insert AFTER INSERT ON book BEGIN
+    INSERT INTO book_fts (id, title, description) VALUES (new.id, new.title, new.description);
+END;
+
+CREATE TRIGGER IF NOT EXISTS book_fts_update AFTER UPDATE ON book BEGIN
+    UPDATE book_fts SET title = new.title, description = new.description WHERE id = new.id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS book_fts_delete AFTER DELETE ON book BEGIN
+    DELETE FROM book_fts WHERE id = old.id;
+END;
+        "#,
+    )
+    .await?;
+    Ok(())
+}
+
+async fn seed(ctx: &SqliteDbCtx, pool: &SqlitePool) -> anyhow::Result<()> {
+    let book_defs = [
+        ("The Hobbit", Some("A hobbit, a wizard and a dragon"), 310),
+        ("Eragon", Some("A farm boy finds a dragon egg"), 509),
+        (
+            "Harry Potter and the Philosopher's Stone",
+            Some("A boy goes to a wizard school"),
+            223,
+        ),
+        ("The Paper Bag Princess", Some("A princess outsmarts a dragon"), 32),
+        (
+            "A Wizard of Earthsea",
+            Some("A young wizard at the school on Roke"),
+            183,
+        ),
+        ("Dune", None, 412),
+    ];
+
+    let mut tx = pool.begin().await?;
+    for (title, description, pages) in book_defs {
+        ctx.create::<Book>(
+            &mut tx,
+            CreateBook {
+                title: title.to_string(),
+                description: description.map(|s| s.to_string()),
+                pages,
+            },
+        )
+        .await?;
+    }
+    tx.commit().await?;
+    Ok(())
+}
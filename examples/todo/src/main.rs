@@ -3,10 +3,9 @@ use lazybe::axum::extract::State;
 use lazybe::axum::http::StatusCode;
 use lazybe::axum::routing::get;
 use lazybe::db::sqlite::SqliteDbCtx;
-use lazybe::openapi::{CreateRouterDoc, DeleteRouterDoc, GetRouterDoc, ListRouterDoc, UpdateRouterDoc};
-use lazybe::router::{CreateRouter, DeleteRouter, GetRouter, ListRouter, RouteConfig, UpdateRouter};
+use lazybe::registry;
+use lazybe::router::RouteConfig;
 use sqlx::{Executor, Pool, Sqlite, SqlitePool};
-use todo::Todo;
 use utoipa::openapi::{Info, OpenApiBuilder, Server};
 use utoipa_redoc::{Redoc, Servable};
 
@@ -35,24 +34,13 @@ async fn main() -> anyhow::Result<()> {
     let pool = SqlitePool::connect("sqlite::memory:").await?;
     reset_db(&pool).await?;
 
-    let mut app = Router::new();
-    let mut openapi = OpenApiBuilder::new()
+    // the endpoints of every entity registered for `AppState`
+    let app: Router<AppState> = registry::router();
+    let openapi = OpenApiBuilder::new()
         .info(Info::new("Todo Example", "0.1.0"))
         .servers(Some([Server::new("http://localhost:8080")]))
-        .build();
-
-    let endpoint_defs = [
-        (Todo::get_endpoint(), Todo::get_endpoint_doc(None)),
-        (Todo::list_endpoint(), Todo::list_endpoint_doc(None)),
-        (Todo::create_endpoint(), Todo::create_endpoint_doc(None)),
-        (Todo::update_endpoint(), Todo::update_endpoint_doc(None)),
-        (Todo::replace_endpoint(), Todo::replace_endpoint_doc(None)),
-        (Todo::delete_endpoint(), Todo::delete_endpoint_doc(None)),
-    ];
-    for (endpoint_router, endpoint_doc) in endpoint_defs {
-        app = app.merge(endpoint_router);
-        openapi = openapi.merge_from(endpoint_doc);
-    }
+        .build()
+        .merge_from(registry::openapi::<AppState>());
 
     let app_router = app
         .merge(Redoc::with_url("/", openapi))
@@ -7,7 +7,7 @@ use utoipa::ToSchema;
 pub struct TodoId(u64);
 
 #[derive(Debug, Clone, Serialize, Deserialize, Entity, ToSchema)]
-#[lazybe(table = "todo", endpoint = "/todos", derive_to_schema)]
+#[lazybe(table = "todo", endpoint = "/todos", derive_to_schema, register = "crate::AppState")]
 pub struct Todo {
     #[lazybe(primary_key)]
     pub id: TodoId,
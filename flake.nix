@@ -98,6 +98,12 @@
                   password = "postgres";
                   dbName = "postgres";
                 };
+                localMySqlDb = {
+                  port = 3306;
+                  username = "mysql";
+                  password = "mysql";
+                  dbName = "lazybe";
+                };
               in
               {
                 format = pkgs.writeShellScriptBin "format" ''
@@ -120,6 +126,21 @@
                 dbDown = pkgs.writeShellScriptBin "dbDown" ''
                   ${pkgs.docker}/bin/docker stop ${localDb.dbName}
                 '';
+
+                mysqlDbUp = pkgs.writeShellScriptBin "mysqlDbUp" ''
+                  ${pkgs.docker}/bin/docker run \
+                    -d --rm \
+                    --name ${localMySqlDb.dbName}-mysql \
+                    -e MARIADB_DATABASE=${localMySqlDb.dbName} \
+                    -e MARIADB_USER=${localMySqlDb.username} \
+                    -e MARIADB_PASSWORD=${localMySqlDb.password} \
+                    -e MARIADB_RANDOM_ROOT_PASSWORD=1 \
+                    -p ${toString localMySqlDb.port}:3306 mariadb:11
+                '';
+
+                mysqlDbDown = pkgs.writeShellScriptBin "mysqlDbDown" ''
+                  ${pkgs.docker}/bin/docker stop ${localMySqlDb.dbName}-mysql
+                '';
               };
           in
           pkgs.mkShell {
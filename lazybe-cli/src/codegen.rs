@@ -0,0 +1,419 @@
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+use convert_case::{Case, Casing};
+
+use crate::introspect::{Backend, Column, Table};
+
+const KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern", "false", "fn",
+    "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref", "return", "self", "Self",
+    "static", "struct", "super", "trait", "true", "type", "unsafe", "use", "where", "while", "abstract", "become",
+    "box", "do", "final", "gen", "macro", "override", "priv", "try", "typeof", "unsized", "virtual", "yield",
+];
+
+/// A field of a generated entity
+struct Field {
+    name: String,
+    ty: String,
+    attrs: Vec<String>,
+    fixme: Option<String>,
+}
+
+/// An enum generated from a check constraint
+struct EnumDef {
+    name: String,
+    variants: Vec<String>,
+}
+
+/// Generate the Rust source of entities for the tables
+pub fn generate(backend: Backend, tables: &[Table], enums: bool) -> String {
+    let mut items = Vec::new();
+    let mut uses = BTreeSet::new();
+    for table in tables {
+        items.push(entity(backend, table, enums, &mut uses));
+    }
+
+    let mut source = String::from("// Generated by `lazybe scaffold`. Review the guessed types and attributes.\n\n");
+    let chrono_items = ["DateTime", "NaiveDate", "NaiveDateTime", "NaiveTime", "Utc"]
+        .into_iter()
+        .filter(|i| uses.contains(*i))
+        .collect::<Vec<_>>();
+    if !chrono_items.is_empty() {
+        writeln!(source, "use chrono::{{{}}};", chrono_items.join(", ")).unwrap();
+    }
+    let macros = match uses.contains("Enum") {
+        true => "{Entity, Enum}",
+        false => "Entity",
+    };
+    writeln!(source, "use lazybe::macros::{};", macros).unwrap();
+    if uses.contains("Uuid") {
+        writeln!(source, "use lazybe::uuid::Uuid;").unwrap();
+    }
+    writeln!(source, "use serde::{{Deserialize, Serialize}};").unwrap();
+    for item in items {
+        source.push('\n');
+        source.push_str(&item);
+    }
+    source
+}
+
+fn entity(backend: Backend, table: &Table, enums: bool, uses: &mut BTreeSet<&'static str>) -> String {
+    let entity = singular(&table.name).to_case(Case::Pascal);
+    let primary_keys = table.columns.iter().filter(|c| c.primary_key).count();
+    let mut enum_defs = Vec::new();
+    let mut generators = Vec::new();
+    let mut fields = Vec::new();
+
+    for column in &table.columns {
+        let enum_values = Some(column)
+            .filter(|c| enums && rust_type(backend, &c.sql_type).is_some_and(|(ty, _)| ty == "String"))
+            .and_then(|c| enum_values(&table.checks, &c.name, &table.columns));
+        let (mut ty, json) = match enum_values {
+            Some(variants) => {
+                let name = format!("{}{}", entity, column.name.to_case(Case::Pascal));
+                enum_defs.push(EnumDef {
+                    name: name.clone(),
+                    variants,
+                });
+                uses.insert("Enum");
+                (name, false)
+            }
+            None => match rust_type(backend, &column.sql_type) {
+                Some((ty, json)) => (ty.to_string(), json),
+                None => ("String".to_string(), false),
+            },
+        };
+        uses.extend(
+            ["DateTime", "NaiveDate", "NaiveDateTime", "NaiveTime", "Utc", "Uuid"]
+                .iter()
+                .filter(|i| ty.contains(*i)),
+        );
+
+        let mut attrs = Vec::new();
+        if column.primary_key && primary_keys == 1 {
+            match column.has_default {
+                true => attrs.push("primary_key".to_string()),
+                false => {
+                    let generator = format!("new_{}_id", singular(&table.name).to_case(Case::Snake));
+                    attrs.push(format!("primary_key, generate_with = \"{}\"", generator));
+                    generators.push((generator, ty.clone()));
+                }
+            }
+        }
+        // a nullable JSON column is decoded as `Option<serde_json::Value>` since `#[lazybe(json)]` cannot decode null
+        if json && !column.nullable {
+            attrs.push("json".to_string());
+        }
+        if ty.starts_with("DateTime")
+            && !column.nullable
+            && ["created_at", "updated_at"].contains(&column.name.as_str())
+        {
+            attrs.push(column.name.clone());
+        }
+
+        let fixme = if !is_ident(&column.name) {
+            Some(format!("FIXME: column `{}` is not a valid field name", column.name))
+        } else if rust_type(backend, &column.sql_type).is_none() {
+            Some(format!("FIXME: unknown column type `{}`", column.sql_type))
+        } else {
+            None
+        };
+        if column.nullable {
+            ty = format!("Option<{}>", ty);
+        }
+        fields.push(Field {
+            name: column.name.clone(),
+            ty,
+            attrs,
+            fixme,
+        });
+    }
+
+    let mut source = String::new();
+    for enum_def in &enum_defs {
+        if enum_def.variants.iter().any(|v| v.to_case(Case::Pascal) != *v) {
+            // the variants are the values stored in the database
+            writeln!(source, "#[allow(non_camel_case_types)]").unwrap();
+        }
+        writeln!(
+            source,
+            "#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Enum)]\npub enum {} {{",
+            enum_def.name
+        )
+        .unwrap();
+        for variant in &enum_def.variants {
+            writeln!(source, "    {},", variant).unwrap();
+        }
+        writeln!(source, "}}\n").unwrap();
+    }
+
+    if primary_keys != 1 {
+        writeln!(source, "// FIXME: an entity requires a primary key of exactly 1 column").unwrap();
+    }
+    writeln!(
+        source,
+        "#[derive(Debug, Clone, Serialize, Deserialize, Entity)]\n#[lazybe(table = \"{}\")]\npub struct {} {{",
+        table.name, entity
+    )
+    .unwrap();
+    for field in fields {
+        if let Some(fixme) = field.fixme {
+            writeln!(source, "    // {}", fixme).unwrap();
+        }
+        if !field.attrs.is_empty() {
+            writeln!(source, "    #[lazybe({})]", field.attrs.join(", ")).unwrap();
+        }
+        writeln!(source, "    pub {}: {},", field.name, field.ty).unwrap();
+    }
+    writeln!(source, "}}").unwrap();
+
+    for (generator, ty) in generators {
+        // the generated source must not compile until the generator is written
+        let body = match ty.as_str() {
+            "Uuid" => "Uuid::new_v4()".to_string(),
+            _ => format!(
+                "compile_error!(\"FIXME: generate the primary key of `{}` which has no default in the database\")",
+                table.name
+            ),
+        };
+        writeln!(
+            source,
+            "\nfn {}(_: &Create{}) -> {} {{\n    {}\n}}",
+            generator, entity, ty, body
+        )
+        .unwrap();
+    }
+    source
+}
+
+/// The Rust type of a column type and whether it is a JSON column, or `None` if the type is unknown
+fn rust_type(backend: Backend, sql_type: &str) -> Option<(&'static str, bool)> {
+    let sql_type = sql_type.trim().to_lowercase();
+    let name = sql_type.split(['(', ' ']).next().unwrap_or_default();
+    let ty = match (backend, name) {
+        (_, "bool" | "boolean") => "bool",
+        (_, "smallint" | "int2" | "tinyint") => "i16",
+        (_, "int" | "integer" | "int4" | "mediumint") => "i32",
+        (_, "bigint" | "int8") => "i64",
+        (Backend::Postgres, "real" | "float4") => "f32",
+        (Backend::Sqlite, "float") => "f32",
+        (_, "real" | "float" | "float8" | "double") => "f64",
+        (_, "text" | "varchar" | "char" | "character" | "bpchar" | "clob" | "citext" | "name") => "String",
+        (_, "blob" | "bytea") => "Vec<u8>",
+        (_, "json" | "jsonb") => return Some(("serde_json::Value", true)),
+        (_, "jsonb_text" | "json_text") => return Some(("serde_json::Value", true)),
+        (_, "uuid" | "uuid_text") => "Uuid",
+        (_, "date") => "NaiveDate",
+        (_, "time" | "timetz") => "NaiveTime",
+        (_, "timestamptz" | "timestamp_with_timezone_text") => "DateTime<Utc>",
+        (Backend::Postgres, "timestamp") if sql_type.contains("with time zone") => "DateTime<Utc>",
+        (Backend::Postgres, "timestamp") => "NaiveDateTime",
+        (Backend::Sqlite, "timestamp" | "datetime" | "timestamp_text" | "datetime_text") => "DateTime<Utc>",
+        // after the `*_text` types of sqlx
+        (_, n) if n.contains("char") || n.contains("text") => "String",
+        _ => return None,
+    };
+    Some((ty, false))
+}
+
+/// The values of a check constraint restricting the column to a list,
+/// e.g. `status IN ('draft', 'published')` or `status = ANY (ARRAY['draft'::text, 'published'::text])`
+fn enum_values(checks: &[String], column: &str, columns: &[Column]) -> Option<Vec<String>> {
+    checks.iter().find_map(|check| {
+        let upper = check.to_uppercase();
+        let is_list = upper.contains(" IN ") || upper.contains(" IN(") || upper.contains("ANY");
+        let references = |name: &str| references_column(check, name);
+        if !is_list || !references(column) || columns.iter().any(|c| c.name != column && references(&c.name)) {
+            return None;
+        }
+        let values = string_literals(check);
+        Some(values).filter(|v| !v.is_empty() && v.iter().all(|v| is_ident(v)))
+    })
+}
+
+/// Whether the column name appears in the expression outside of string literals
+fn references_column(expr: &str, column: &str) -> bool {
+    let mut in_string = false;
+    let mut word = String::new();
+    for c in expr.chars().chain([' ']) {
+        match c {
+            '\'' => {
+                in_string = !in_string;
+                word.clear();
+            }
+            c if !in_string && (c.is_alphanumeric() || c == '_') => word.push(c),
+            _ => {
+                if !in_string && word == column {
+                    return true;
+                }
+                word.clear();
+            }
+        }
+    }
+    false
+}
+
+fn string_literals(expr: &str) -> Vec<String> {
+    let mut literals = Vec::new();
+    let mut chars = expr.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\'' {
+            continue;
+        }
+        let mut literal = String::new();
+        while let Some(c) = chars.next() {
+            match c {
+                '\'' if chars.peek() == Some(&'\'') => {
+                    literal.push('\'');
+                    chars.next();
+                }
+                '\'' => break,
+                c => literal.push(c),
+            }
+        }
+        literals.push(literal);
+    }
+    literals
+}
+
+fn is_ident(name: &str) -> bool {
+    let mut chars = name.chars();
+    let valid = chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+    valid && name != "_" && !KEYWORDS.contains(&name)
+}
+
+/// A naive singular form of a table name, e.g. `books` to `book` and `categories` to `category`
+fn singular(table: &str) -> String {
+    if let Some(stem) = table.strip_suffix("ies") {
+        return format!("{}y", stem);
+    }
+    match table.strip_suffix('s') {
+        Some(stem) if !stem.ends_with('s') && !stem.is_empty() => stem.to_string(),
+        _ => table.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn column(name: &str, sql_type: &str) -> Column {
+        Column {
+            name: name.to_string(),
+            sql_type: sql_type.to_string(),
+            nullable: false,
+            primary_key: false,
+            has_default: false,
+        }
+    }
+
+    #[test]
+    fn rust_types() {
+        let cases = [
+            (Backend::Sqlite, "INTEGER", Some(("i32", false))),
+            (Backend::Sqlite, "bigint", Some(("i64", false))),
+            (Backend::Sqlite, "FLOAT", Some(("f32", false))),
+            (Backend::Postgres, "float4", Some(("f32", false))),
+            (Backend::Postgres, "float8", Some(("f64", false))),
+            (Backend::Sqlite, "VARCHAR(64)", Some(("String", false))),
+            (Backend::Sqlite, "NVARCHAR", Some(("String", false))),
+            (Backend::Postgres, "bytea", Some(("Vec<u8>", false))),
+            (Backend::Postgres, "jsonb", Some(("serde_json::Value", true))),
+            (Backend::Sqlite, "jsonb_text", Some(("serde_json::Value", true))),
+            (Backend::Postgres, "uuid", Some(("Uuid", false))),
+            (Backend::Postgres, "timestamptz", Some(("DateTime<Utc>", false))),
+            (Backend::Postgres, "timestamp", Some(("NaiveDateTime", false))),
+            (
+                Backend::Postgres,
+                "timestamp with time zone",
+                Some(("DateTime<Utc>", false)),
+            ),
+            (Backend::Sqlite, "datetime", Some(("DateTime<Utc>", false))),
+            (Backend::Sqlite, "uuid_text", Some(("Uuid", false))),
+            (
+                Backend::Sqlite,
+                "timestamp_with_timezone_text",
+                Some(("DateTime<Utc>", false)),
+            ),
+            (Backend::Postgres, "numeric", None),
+            (Backend::Postgres, "interval", None),
+        ];
+        for (backend, sql_type, expected) in cases {
+            assert_eq!(rust_type(backend, sql_type), expected, "{}", sql_type);
+        }
+    }
+
+    #[test]
+    fn enum_values_of_checks() {
+        let columns = [column("status", "text"), column("kind", "text")];
+        let checks = ["kind <> ''".to_string(), "status IN ('draft', 'published')".to_string()];
+        assert_eq!(
+            enum_values(&checks, "status", &columns),
+            Some(vec!["draft".to_string(), "published".to_string()])
+        );
+        assert_eq!(enum_values(&checks, "kind", &columns), None);
+
+        let checks = ["(status = ANY (ARRAY['draft'::text, 'published'::text]))".to_string()];
+        assert_eq!(
+            enum_values(&checks, "status", &columns),
+            Some(vec!["draft".to_string(), "published".to_string()])
+        );
+
+        // a value that is not an identifier, or a check of several columns
+        let checks = ["status IN ('draft', 'in review')".to_string()];
+        assert_eq!(enum_values(&checks, "status", &columns), None);
+        let checks = ["status IN ('draft') OR kind IN ('draft')".to_string()];
+        assert_eq!(enum_values(&checks, "status", &columns), None);
+        // the column name inside a literal is not a reference
+        let checks = ["kind IN ('status', 'other')".to_string()];
+        assert_eq!(enum_values(&checks, "status", &columns), None);
+    }
+
+    #[test]
+    fn string_literals_of_expr() {
+        assert_eq!(string_literals("a IN ('x', 'y')"), ["x", "y"]);
+        assert_eq!(string_literals("a = 'it''s'"), ["it's"]);
+        assert_eq!(string_literals("a = ''"), [""]);
+        assert!(string_literals("a > 0").is_empty());
+    }
+
+    #[test]
+    fn identifiers() {
+        assert!(is_ident("title"));
+        assert!(is_ident("_title2"));
+        assert!(!is_ident("_"));
+        assert!(!is_ident("2title"));
+        assert!(!is_ident("first name"));
+        assert!(!is_ident("type"));
+        assert!(!is_ident(""));
+    }
+
+    #[test]
+    fn singulars() {
+        assert_eq!(singular("books"), "book");
+        assert_eq!(singular("categories"), "category");
+        assert_eq!(singular("address"), "address");
+        assert_eq!(singular("status"), "statu");
+        assert_eq!(singular("s"), "s");
+        assert_eq!(singular("person"), "person");
+    }
+
+    #[test]
+    fn id_generator() {
+        let table = Table {
+            name: "books".to_string(),
+            columns: vec![Column {
+                primary_key: true,
+                ..column("code", "text")
+            }],
+            checks: Vec::new(),
+        };
+        let source = generate(Backend::Postgres, &[table], false);
+        assert!(source.contains("#[lazybe(primary_key, generate_with = \"new_book_id\")]"));
+        assert!(source.contains(
+            "fn new_book_id(_: &CreateBook) -> String {\n    compile_error!(\"FIXME: generate the primary key of `books` which has no default in the database\")\n}"
+        ));
+    }
+}
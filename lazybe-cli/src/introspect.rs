@@ -0,0 +1,194 @@
+use sqlx::{PgPool, SqlitePool};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    Sqlite,
+    Postgres,
+}
+
+#[derive(Debug, Clone)]
+pub struct Table {
+    pub name: String,
+    pub columns: Vec<Column>,
+    /// The expressions of the check constraints on the table
+    pub checks: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Column {
+    pub name: String,
+    pub sql_type: String,
+    pub nullable: bool,
+    pub primary_key: bool,
+    /// Whether the database provides a value when the column is omitted on insert
+    pub has_default: bool,
+}
+
+pub async fn sqlite_tables(pool: &SqlitePool, only: &[String]) -> anyhow::Result<Vec<Table>> {
+    let tables: Vec<(String, String)> = sqlx::query_as(
+        "SELECT name, sql FROM sqlite_master WHERE type = 'table' \
+         AND name NOT LIKE 'sqlite\\_%' ESCAPE '\\' AND name NOT LIKE '\\_sqlx\\_%' ESCAPE '\\' ORDER BY name",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut result = Vec::new();
+    for (name, sql) in tables.into_iter().filter(|(name, _)| is_selected(name, only)) {
+        let rows: Vec<(String, String, bool, i64, Option<String>)> =
+            sqlx::query_as("SELECT name, type, \"notnull\", pk, dflt_value FROM pragma_table_info(?) ORDER BY cid")
+                .bind(&name)
+                .fetch_all(pool)
+                .await?;
+        let single_pk = rows.iter().filter(|(_, _, _, pk, _)| *pk > 0).count() == 1;
+        let columns = rows
+            .into_iter()
+            .map(|(name, sql_type, not_null, pk, default)| {
+                // an INTEGER primary key is an alias of the rowid which is never null and generated on insert
+                let rowid = single_pk && pk > 0 && sql_type.eq_ignore_ascii_case("integer");
+                Column {
+                    name,
+                    sql_type,
+                    // other primary keys are nullable unless declared NOT NULL, which is a long-standing SQLite quirk
+                    nullable: !not_null && pk == 0,
+                    primary_key: pk > 0,
+                    has_default: default.is_some() || rowid,
+                }
+            })
+            .collect();
+        result.push(Table {
+            name,
+            columns,
+            checks: check_constraints(&sql),
+        });
+    }
+    Ok(result)
+}
+
+pub async fn postgres_tables(pool: &PgPool, schema: Option<&str>, only: &[String]) -> anyhow::Result<Vec<Table>> {
+    let schema: String = match schema {
+        Some(schema) => schema.to_string(),
+        None => {
+            sqlx::query_scalar("SELECT current_schema()::text")
+                .fetch_one(pool)
+                .await?
+        }
+    };
+    let tables: Vec<String> = sqlx::query_scalar(
+        "SELECT table_name::text FROM information_schema.tables \
+         WHERE table_schema = $1 AND table_type = 'BASE TABLE' AND table_name NOT LIKE '\\_sqlx\\_%' \
+         ORDER BY table_name",
+    )
+    .bind(&schema)
+    .fetch_all(pool)
+    .await?;
+
+    let mut result = Vec::new();
+    for name in tables.into_iter().filter(|name| is_selected(name, only)) {
+        let relation = format!("{}.{}", quote_ident(&schema), quote_ident(&name));
+        let primary_keys: Vec<String> = sqlx::query_scalar(
+            "SELECT a.attname::text FROM pg_index i \
+             JOIN pg_attribute a ON a.attrelid = i.indrelid AND a.attnum = ANY(i.indkey) \
+             WHERE i.indrelid = $1::regclass AND i.indisprimary",
+        )
+        .bind(&relation)
+        .fetch_all(pool)
+        .await?;
+        let rows: Vec<(String, String, bool, bool)> = sqlx::query_as(
+            "SELECT column_name::text, udt_name::text, is_nullable = 'YES', \
+             column_default IS NOT NULL OR is_identity = 'YES' OR is_generated = 'ALWAYS' \
+             FROM information_schema.columns WHERE table_schema = $1 AND table_name = $2 ORDER BY ordinal_position",
+        )
+        .bind(&schema)
+        .bind(&name)
+        .fetch_all(pool)
+        .await?;
+        let checks: Vec<String> = sqlx::query_scalar(
+            "SELECT pg_get_constraintdef(oid) FROM pg_constraint WHERE conrelid = $1::regclass AND contype = 'c'",
+        )
+        .bind(&relation)
+        .fetch_all(pool)
+        .await?;
+        let columns = rows
+            .into_iter()
+            .map(|(name, sql_type, nullable, has_default)| Column {
+                primary_key: primary_keys.contains(&name),
+                name,
+                sql_type,
+                nullable,
+                has_default,
+            })
+            .collect();
+        result.push(Table { name, columns, checks });
+    }
+    Ok(result)
+}
+
+fn is_selected(table: &str, only: &[String]) -> bool {
+    only.is_empty() || only.iter().any(|t| t == table)
+}
+
+fn quote_ident(ident: &str) -> String {
+    format!("\"{}\"", ident.replace('"', "\"\""))
+}
+
+/// The expressions of `CHECK (...)` in a `CREATE TABLE` statement
+fn check_constraints(create_table: &str) -> Vec<String> {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    let mut checks = Vec::new();
+    let mut in_string = false;
+    let mut prev = ' ';
+    for (i, c) in create_table.char_indices() {
+        let keyword = create_table[i..].get(.."CHECK".len());
+        if c == '\'' {
+            in_string = !in_string;
+        } else if !in_string && !is_word(prev) && keyword.is_some_and(|k| k.eq_ignore_ascii_case("CHECK")) {
+            let rest = &create_table[i + "CHECK".len()..];
+            if let Some(expr) = rest.trim_start().strip_prefix('(').and_then(parenthesized) {
+                checks.push(expr.to_string());
+            }
+        }
+        prev = c;
+    }
+    checks
+}
+
+/// The expression up to the closing parenthesis, skipping nested parentheses and string literals
+fn parenthesized(expr: &str) -> Option<&str> {
+    let mut depth = 0;
+    let mut in_string = false;
+    for (i, c) in expr.char_indices() {
+        match c {
+            '\'' => in_string = !in_string,
+            '(' if !in_string => depth += 1,
+            ')' if !in_string && depth == 0 => return Some(&expr[..i]),
+            ')' if !in_string => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_constraints_of_create_table() {
+        let sql = "CREATE TABLE book (\
+                   id INTEGER PRIMARY KEY, \
+                   status TEXT NOT NULL CHECK (status IN ('draft', 'published')), \
+                   pages INTEGER CHECK(pages > 0 AND (pages < 10000)), \
+                   note TEXT DEFAULT 'CHECK (x)', \
+                   checksum TEXT, \
+                   CONSTRAINT title_check CHECK (length(title) > 0 OR title = ')'))";
+        assert_eq!(
+            check_constraints(sql),
+            [
+                "status IN ('draft', 'published')",
+                "pages > 0 AND (pages < 10000)",
+                "length(title) > 0 OR title = ')'",
+            ]
+        );
+        assert!(check_constraints("CREATE TABLE book (id INTEGER PRIMARY KEY)").is_empty());
+    }
+}
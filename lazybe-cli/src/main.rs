@@ -0,0 +1,76 @@
+use std::path::PathBuf;
+
+use clap::{Args, Parser, Subcommand};
+use introspect::Backend;
+use sqlx::{PgPool, SqlitePool};
+
+mod codegen;
+mod introspect;
+
+#[derive(Parser)]
+#[command(name = "lazybe", version, about = "Tools for building applications with lazybe")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Generate Entity structs from the tables of an existing database
+    Scaffold(ScaffoldArgs),
+}
+
+#[derive(Args)]
+struct ScaffoldArgs {
+    /// The URL of a SQLite or Postgres database (e.g. `sqlite://app.db` or `postgres://localhost/app`)
+    database_url: String,
+    /// Only generate the entities of these tables. Defaults to all tables.
+    #[arg(long = "table", value_name = "TABLE")]
+    tables: Vec<String>,
+    /// The Postgres schema of the tables. Defaults to the current schema.
+    #[arg(long)]
+    schema: Option<String>,
+    /// Generate an `Enum` for each text column that is restricted to a list of values by a check constraint
+    #[arg(long)]
+    enums: bool,
+    /// Write the generated source to a file instead of stdout
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+    match cli.command {
+        Command::Scaffold(args) => scaffold(args).await,
+    }
+}
+
+async fn scaffold(args: ScaffoldArgs) -> anyhow::Result<()> {
+    let url = args.database_url.as_str();
+    let (backend, tables) = if url.starts_with("sqlite:") {
+        let pool = SqlitePool::connect(url).await?;
+        (Backend::Sqlite, introspect::sqlite_tables(&pool, &args.tables).await?)
+    } else if url.starts_with("postgres:") || url.starts_with("postgresql:") {
+        let pool = PgPool::connect(url).await?;
+        let tables = introspect::postgres_tables(&pool, args.schema.as_deref(), &args.tables).await?;
+        (Backend::Postgres, tables)
+    } else {
+        anyhow::bail!("Unsupported database URL, expected `sqlite:` or `postgres:`");
+    };
+
+    if let Some(missing) = args
+        .tables
+        .iter()
+        .find(|t| !tables.iter().any(|table| &&table.name == t))
+    {
+        anyhow::bail!("Table `{}` does not exist", missing);
+    }
+
+    let source = codegen::generate(backend, &tables, args.enums);
+    match args.output {
+        Some(path) => std::fs::write(path, source)?,
+        None => print!("{}", source),
+    }
+    Ok(())
+}
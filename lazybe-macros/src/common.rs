@@ -1,12 +1,14 @@
 use darling::FromMeta;
 use proc_macro2::TokenStream;
 use quote::quote;
-use syn::Ident;
+use syn::{Ident, Type, Visibility};
 
 #[derive(Clone, FromMeta)]
 pub enum CollectionApi {
     Default,
     Manual,
+    #[darling(rename = "odata")]
+    OData,
 }
 
 impl Default for CollectionApi {
@@ -27,9 +29,34 @@ impl Default for ValidationHook {
     }
 }
 
-pub fn entity_route_trait_impl(entity: &Ident, base_url: &str) -> TokenStream {
+#[derive(Clone, FromMeta)]
+pub enum IsolationLevel {
+    ReadUncommitted,
+    ReadCommitted,
+    RepeatableRead,
+    Serializable,
+}
+
+pub fn entity_route_trait_impl(
+    entity: &Ident,
+    base_url: &str,
+    isolation_level: Option<&IsolationLevel>,
+) -> TokenStream {
     let get_path = format!("{}/{{id}}", base_url);
     let list_path = base_url.to_string();
+    let isolation_level_fn = isolation_level.map(|isolation_level| {
+        let variant = match isolation_level {
+            IsolationLevel::ReadUncommitted => quote! { ReadUncommitted },
+            IsolationLevel::ReadCommitted => quote! { ReadCommitted },
+            IsolationLevel::RepeatableRead => quote! { RepeatableRead },
+            IsolationLevel::Serializable => quote! { Serializable },
+        };
+        quote! {
+            fn isolation_level() -> Option<lazybe::db::IsolationLevel> {
+                Some(lazybe::db::IsolationLevel::#variant)
+            }
+        }
+    });
     quote! {
         impl lazybe::router::Routable for #entity {
             fn entity_path() -> &'static str {
@@ -38,14 +65,26 @@ pub fn entity_route_trait_impl(entity: &Ident, base_url: &str) -> TokenStream {
             fn entity_collection_path() -> &'static str {
                 #list_path
             }
+            #isolation_level_fn
         }
     }
 }
 
+/// Register the entity in the global registry for the axum state.
+/// With `crud`, the entity must implement the router of every CRUD operation.
+pub fn entity_register_impl(entity: &Ident, state: &Type, crud: bool) -> TokenStream {
+    match crud {
+        true => quote! { lazybe::__register_entity!(#entity, #state, crud); },
+        false => quote! { lazybe::__register_entity!(#entity, #state); },
+    }
+}
+
 pub fn entity_collection_api_trait_impl(
     entity: &Ident,
+    entity_vis: &Visibility,
     collection_api: &CollectionApi,
     default_sort: Option<(&Ident, &Ident)>,
+    search_query: Option<(&Ident, bool)>,
 ) -> TokenStream {
     let sort_expr = match default_sort {
         Some((pk_ident, sort_entity)) => quote! {
@@ -53,17 +92,122 @@ pub fn entity_collection_api_trait_impl(
         },
         None => quote! { lazybe::sort::Sort::empty() },
     };
-    match collection_api {
-        CollectionApi::Manual => TokenStream::new(),
-        CollectionApi::Default => quote! {
+    match (collection_api, search_query) {
+        (CollectionApi::Manual, _) => TokenStream::new(),
+        (CollectionApi::OData, _) => quote! {
+            impl lazybe::router::EntityCollectionApi for #entity {
+                type Resp = lazybe::odata::ODataPage<Self>;
+                type Query = lazybe::odata::ODataQuery<Self>;
+
+                fn page_response(page: lazybe::page::Page<Self>) -> Self::Resp {
+                    lazybe::odata::ODataPage::new(page.data)
+                }
+
+                fn page_response_with_query(
+                    page: lazybe::page::Page<Self>,
+                    input: &Self::Query,
+                    path: &str,
+                ) -> Self::Resp {
+                    input.response(page, path)
+                }
+
+                fn partial_page_response(
+                    page: lazybe::page::Page<lazybe::fields::Partial<Self>>,
+                    input: &Self::Query,
+                    fields: &lazybe::fields::Fields<Self>,
+                    path: &str,
+                ) -> lazybe::axum::response::Response {
+                    let resp = input.partial_response(page, fields, path);
+                    lazybe::axum::response::IntoResponse::into_response(lazybe::axum::Json(resp))
+                }
+
+                fn page_input(input: &Self::Query) -> Option<lazybe::page::PaginationInput> {
+                    input.page_input()
+                }
+
+                fn filter_input(input: &Self::Query) -> lazybe::filter::Filter<Self> {
+                    input.filter()
+                }
+
+                fn sort_input(input: &Self::Query) -> lazybe::sort::Sort<Self> {
+                    input.sort().unwrap_or_else(|| #sort_expr)
+                }
+            }
+        },
+        (CollectionApi::Default, Some((search_query, derive_to_schema))) => {
+            let derive_to_schema = Some(quote! { #[derive(utoipa::ToSchema)] }).filter(|_| derive_to_schema);
+            let search_sort_expr = match default_sort {
+                Some((pk_ident, sort_entity)) => quote! {
+                    lazybe::sort::Sort::new([
+                        lazybe::sort::SortCol::search_rank(q).desc(),
+                        #sort_entity::#pk_ident().asc(),
+                    ])
+                },
+                None => quote! { lazybe::sort::Sort::new([lazybe::sort::SortCol::search_rank(q).desc()]) },
+            };
+            quote! {
+                #[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+                #derive_to_schema
+                #entity_vis struct #search_query {
+                    /// Full-text search term
+                    pub q: Option<String>,
+                }
+
+                impl lazybe::router::EntityCollectionApi for #entity {
+                    type Resp = Vec<Self>;
+                    type Query = #search_query;
+
+                    fn page_response(page: lazybe::page::Page<Self>) -> Self::Resp {
+                        page.data
+                    }
+
+                    fn partial_page_response(
+                        page: lazybe::page::Page<lazybe::fields::Partial<Self>>,
+                        _input: &Self::Query,
+                        _fields: &lazybe::fields::Fields<Self>,
+                        _path: &str,
+                    ) -> lazybe::axum::response::Response {
+                        lazybe::axum::response::IntoResponse::into_response(lazybe::axum::Json(page.data))
+                    }
+
+                    fn page_input(_input: &Self::Query) -> Option<lazybe::page::PaginationInput> {
+                        None
+                    }
+
+                    fn filter_input(input: &Self::Query) -> lazybe::filter::Filter<Self> {
+                        match input.q.as_ref() {
+                            Some(q) => lazybe::filter::Filter::search(q.clone()),
+                            None => lazybe::filter::Filter::empty(),
+                        }
+                    }
+
+                    fn sort_input(input: &Self::Query) -> lazybe::sort::Sort<Self> {
+                        match input.q.as_ref().filter(|q| !q.trim().is_empty()) {
+                            Some(q) => #search_sort_expr,
+                            None => #sort_expr,
+                        }
+                    }
+                }
+            }
+        }
+        (CollectionApi::Default, None) => quote! {
             impl lazybe::router::EntityCollectionApi for #entity {
                 type Resp = Vec<Self>;
-                type Query = ();
+                type Query = lazybe::router::EmptyQuery;
 
                 fn page_response(page: lazybe::page::Page<Self>) -> Self::Resp {
                     page.data
                 }
 
+                fn partial_page_response(
+                    page: lazybe::page::Page<lazybe::fields::Partial<Self>>,
+                    _input: &Self::Query,
+                    _fields: &lazybe::fields::Fields<Self>,
+                    _path: &str,
+                ) -> lazybe::axum::response::Response {
+                    lazybe::axum::response::IntoResponse::into_response(lazybe::axum::Json(page.data))
+                }
+
                 fn page_input(_input: &Self::Query) -> Option<lazybe::page::PaginationInput> {
                     None
                 }
@@ -88,3 +232,14 @@ pub fn entity_validation_hook_trait_impl(entity: &Ident, validation: &Validation
         }
     }
 }
+
+/// The column type of a Rust type by `SqlType`, or by its name if it does not implement `SqlType`
+pub fn infer_column_type(ty: &Type) -> TokenStream {
+    quote! {
+        {
+            #[allow(unused_imports)]
+            use lazybe::schema::__private::{NoSqlType as _, ViaSqlType as _};
+            (&&lazybe::schema::__private::Probe::<#ty>::new()).sql_type()
+        }
+    }
+}
@@ -2,9 +2,11 @@ use convert_case::{Case, Casing};
 use darling::{FromDeriveInput, FromField};
 use proc_macro2::TokenStream;
 use quote::{format_ident, quote};
-use syn::{Data, DataStruct, DeriveInput, Fields, FieldsNamed, Ident, Type, Visibility};
+use syn::{
+    Data, DataStruct, DeriveInput, Fields, FieldsNamed, GenericArgument, Ident, Path, PathArguments, Type, Visibility,
+};
 
-use crate::common::{self, CollectionApi, ValidationHook};
+use crate::common::{self, CollectionApi, IsolationLevel, ValidationHook};
 
 #[derive(Clone, FromDeriveInput)]
 #[darling(attributes(lazybe))]
@@ -17,7 +19,15 @@ struct EntityAttr {
     #[darling(default)]
     validation: ValidationHook,
     #[darling(default)]
+    isolation: Option<IsolationLevel>,
+    #[darling(default)]
     derive_to_schema: bool,
+    #[darling(default)]
+    register: Option<Type>,
+    #[darling(default)]
+    history: bool,
+    #[darling(default)]
+    outbox: bool,
 }
 
 #[derive(Clone, FromField)]
@@ -32,7 +42,23 @@ struct EntityFieldAttr {
     #[darling(default)]
     updated_at: bool,
     #[darling(default)]
+    created_by: bool,
+    #[darling(default)]
+    updated_by: bool,
+    #[darling(default)]
+    tenant: bool,
+    #[darling(default)]
     json: bool,
+    #[darling(default)]
+    searchable: bool,
+    #[darling(default)]
+    references: Option<Path>,
+    #[darling(default)]
+    relation: Option<String>,
+    #[darling(default)]
+    sql_type: Option<String>,
+    #[darling(default)]
+    renamed_from: Option<String>,
 }
 
 #[derive(Clone)]
@@ -44,6 +70,13 @@ struct EntityField {
     attr: EntityFieldAttr,
 }
 
+#[derive(Clone)]
+struct EntityRelation {
+    ident: Ident,
+    target: Path,
+    fk_ident_pascal: Ident,
+}
+
 struct EntityMeta {
     entity_ident: Ident,
     entity_vis: Visibility,
@@ -53,13 +86,20 @@ struct EntityMeta {
     replace_entity: Ident,
     filter_entity: Ident,
     sort_entity: Ident,
+    join_filter_entity: Ident,
+    join_sort_entity: Ident,
     sqlx_row_ident: Ident,
     sea_query_ident: Ident,
+    search_query_ident: Ident,
     primary_key: EntityField,
     created_at: Option<EntityField>,
     updated_at: Option<EntityField>,
+    created_by: Option<EntityField>,
+    updated_by: Option<EntityField>,
+    tenant: Option<EntityField>,
     all_fields: Vec<EntityField>,
     user_defined_fields: Vec<EntityField>,
+    relations: Vec<EntityRelation>,
 }
 
 impl EntityMeta {
@@ -94,22 +134,68 @@ impl EntityMeta {
             replace_entity: format_ident!("Replace{}", input.ident),
             filter_entity: format_ident!("{}Filter", input.ident),
             sort_entity: format_ident!("{}Sort", input.ident),
+            join_filter_entity: format_ident!("{}JoinFilter", input.ident),
+            join_sort_entity: format_ident!("{}JoinSort", input.ident),
             sqlx_row_ident: format_ident!("{}SqlxRow", input.ident),
             sea_query_ident: format_ident!("{}SeaQueryIdent", input.ident),
+            search_query_ident: format_ident!("{}SearchQuery", input.ident),
             primary_key: Self::detect_primary_key(&input.ident, &parsed_fields)?,
             created_at: Self::detect_created_at(&input.ident, &parsed_fields)?,
             updated_at: Self::detect_updated_at(&input.ident, &parsed_fields)?,
+            created_by: Self::detect_created_by(&input.ident, &parsed_fields)?,
+            updated_by: Self::detect_updated_by(&input.ident, &parsed_fields)?,
+            tenant: Self::detect_tenant(&input.ident, &parsed_fields)?,
+            relations: Self::detect_relations(&parsed_fields)?,
             all_fields: parsed_fields.to_vec(),
             user_defined_fields: parsed_fields
                 .iter()
                 .filter(|field| !field.attr.primary_key)
                 .filter(|field| !field.attr.created_at)
                 .filter(|field| !field.attr.updated_at)
+                .filter(|field| !field.attr.created_by)
+                .filter(|field| !field.attr.updated_by)
+                .filter(|field| !field.attr.tenant)
                 .cloned()
                 .collect(),
         })
     }
 
+    fn detect_relations(all_fields: &[EntityField]) -> syn::Result<Vec<EntityRelation>> {
+        let mut relations = Vec::new();
+        for field in all_fields {
+            let Some(target) = field.attr.references.as_ref() else {
+                continue;
+            };
+            let field_name = field.ident.to_string();
+            let relation_name = match field.attr.relation.as_ref() {
+                Some(name) => name.clone(),
+                None => match field_name.strip_suffix("_id") {
+                    Some(name) if !name.is_empty() => name.to_string(),
+                    _ => Err(syn::Error::new_spanned(
+                        &field.ident,
+                        "Relation name cannot be inferred from a field without `_id` suffix, use `relation = \"...\"`",
+                    ))?,
+                },
+            };
+            if all_fields.iter().any(|f| f.ident == relation_name) {
+                Err(syn::Error::new_spanned(
+                    &field.ident,
+                    format!("Relation name `{}` conflicts with a field name", relation_name),
+                ))?
+            }
+            relations.push(EntityRelation {
+                ident: format_ident!("{}", relation_name),
+                target: target.clone(),
+                fk_ident_pascal: field.ident_pascal.clone(),
+            });
+        }
+        Ok(relations)
+    }
+
+    fn searchable_fields(&self) -> Vec<&EntityField> {
+        self.all_fields.iter().filter(|f| f.attr.searchable).collect()
+    }
+
     fn detect_primary_key(entity: &Ident, all_fields: &[EntityField]) -> syn::Result<EntityField> {
         let mut maybe_pk = None;
         for field in all_fields {
@@ -154,6 +240,60 @@ impl EntityMeta {
         }
         Ok(maybe_field)
     }
+
+    fn detect_created_by(entity: &Ident, all_fields: &[EntityField]) -> syn::Result<Option<EntityField>> {
+        let mut maybe_field = None;
+        for field in all_fields {
+            if field.attr.created_by {
+                match maybe_field {
+                    Some(_) => Err(syn::Error::new_spanned(
+                        entity,
+                        "No more than 1 field can be created_by",
+                    ))?,
+                    None => maybe_field = Some(field.clone()),
+                }
+            }
+        }
+        Ok(maybe_field)
+    }
+
+    fn detect_updated_by(entity: &Ident, all_fields: &[EntityField]) -> syn::Result<Option<EntityField>> {
+        let mut maybe_field = None;
+        for field in all_fields {
+            if field.attr.updated_by {
+                match maybe_field {
+                    Some(_) => Err(syn::Error::new_spanned(
+                        entity,
+                        "No more than 1 field can be updated_by",
+                    ))?,
+                    None => maybe_field = Some(field.clone()),
+                }
+            }
+        }
+        Ok(maybe_field)
+    }
+
+    fn detect_tenant(entity: &Ident, all_fields: &[EntityField]) -> syn::Result<Option<EntityField>> {
+        let mut maybe_field = None;
+        for field in all_fields {
+            if field.attr.tenant {
+                if field.attr.primary_key {
+                    Err(syn::Error::new_spanned(
+                        &field.ident,
+                        "The primary key cannot be a tenant",
+                    ))?
+                }
+                if option_inner_ty(&field.ty).is_some() {
+                    Err(syn::Error::new_spanned(&field.ty, "A tenant field cannot be optional"))?
+                }
+                match maybe_field {
+                    Some(_) => Err(syn::Error::new_spanned(entity, "No more than 1 field can be tenant"))?,
+                    None => maybe_field = Some(field.clone()),
+                }
+            }
+        }
+        Ok(maybe_field)
+    }
 }
 
 pub fn expand(input: DeriveInput) -> TokenStream {
@@ -177,9 +317,17 @@ fn expand_struct(input: &DeriveInput, data_struct: &DataStruct) -> syn::Result<T
             ts.extend(entity_row_impl(&entity_meta));
             ts.extend(entity_entity_trait_impl(&entity_meta));
             ts.extend(entity_query_trait_impl(&entity_meta));
+            ts.extend(entity_search_trait_impl(&entity_meta));
+            ts.extend(entity_fields_trait_impl(&entity_meta));
+            ts.extend(entity_schema_trait_impl(&entity_meta));
+            ts.extend(entity_history_trait_impl(&entity_meta));
+            ts.extend(entity_outbox_trait_impl(&entity_meta));
+            ts.extend(entity_relation_impl(&entity_meta));
             ts.extend(entity_route_trait_impl(&entity_meta));
             ts.extend(entity_collection_api_trait_impl(&entity_meta));
+            ts.extend(entity_odata_trait_impl(&entity_meta));
             ts.extend(entity_validation_hook_trait_impl(&entity_meta));
+            ts.extend(entity_register_impl(&entity_meta));
             Ok(ts)
         }
         Fields::Unnamed(_) => Err(syn::Error::new_spanned(
@@ -230,7 +378,7 @@ fn entity_types_impl(entity_meta: &EntityMeta) -> TokenStream {
         let field_ty = &f.ty;
         quote! {
             pub fn #field_ident() -> lazybe::filter::FilterCol<#entity, #field_ty> {
-                lazybe::filter::FilterCol::new(#entity_sea_query_ident::#field_ident_pascal)
+                lazybe::filter::FilterCol::new((#entity_sea_query_ident::Table, #entity_sea_query_ident::#field_ident_pascal))
             }
         }
     });
@@ -239,10 +387,48 @@ fn entity_types_impl(entity_meta: &EntityMeta) -> TokenStream {
         let field_ident_pascal = &f.ident_pascal;
         quote! {
             pub fn #field_ident() -> lazybe::sort::SortCol<#entity> {
-                lazybe::sort::SortCol::new(#entity_sea_query_ident::#field_ident_pascal)
+                lazybe::sort::SortCol::new((#entity_sea_query_ident::Table, #entity_sea_query_ident::#field_ident_pascal))
+            }
+        }
+    });
+    let filter_relation_method_defs = entity_meta.relations.iter().map(|r| {
+        let relation_ident = &r.ident;
+        let relation_str = r.ident.to_string();
+        let target = &r.target;
+        let fk_ident_pascal = &r.fk_ident_pascal;
+        quote! {
+            pub fn #relation_ident() -> <#target as lazybe::relation::Related>::JoinFilter<#entity> {
+                let path = lazybe::relation::JoinPath::new(sea_query::IntoIden::into_iden(#entity_sea_query_ident::Table))
+                    .join::<#target>(#relation_str, sea_query::IntoIden::into_iden(#entity_sea_query_ident::#fk_ident_pascal));
+                <#target as lazybe::relation::Related>::join_filter(path)
             }
         }
     });
+    let sort_relation_method_defs = entity_meta.relations.iter().map(|r| {
+        let relation_ident = &r.ident;
+        let relation_str = r.ident.to_string();
+        let target = &r.target;
+        let fk_ident_pascal = &r.fk_ident_pascal;
+        quote! {
+            pub fn #relation_ident() -> <#target as lazybe::relation::Related>::JoinSort<#entity> {
+                let path = lazybe::relation::JoinPath::new(sea_query::IntoIden::into_iden(#entity_sea_query_ident::Table))
+                    .join::<#target>(#relation_str, sea_query::IntoIden::into_iden(#entity_sea_query_ident::#fk_ident_pascal));
+                <#target as lazybe::relation::Related>::join_sort(path)
+            }
+        }
+    });
+    let search_method_defs = Some(quote! {
+        pub fn search(term: impl Into<String>) -> lazybe::filter::Filter<#entity> {
+            lazybe::filter::Filter::search(term)
+        }
+    })
+    .filter(|_| !entity_meta.searchable_fields().is_empty());
+    let search_rank_method_defs = Some(quote! {
+        pub fn search_rank(term: impl Into<String>) -> lazybe::sort::SortCol<#entity> {
+            lazybe::sort::SortCol::search_rank(term)
+        }
+    })
+    .filter(|_| !entity_meta.searchable_fields().is_empty());
     let derive_to_schema = Some(quote! { #[derive(utoipa::ToSchema)] }).filter(|_| entity_meta.attr.derive_to_schema);
     quote! {
         #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -276,6 +462,8 @@ fn entity_types_impl(entity_meta: &EntityMeta) -> TokenStream {
 
         impl #filter_entity {
             #(#filter_method_defs)*
+            #(#filter_relation_method_defs)*
+            #search_method_defs
         }
 
         #[derive(Debug, Clone)]
@@ -283,6 +471,8 @@ fn entity_types_impl(entity_meta: &EntityMeta) -> TokenStream {
 
         impl #sort_entity {
             #(#sort_method_defs)*
+            #(#sort_relation_method_defs)*
+            #search_rank_method_defs
         }
     }
 }
@@ -346,20 +536,61 @@ fn entity_route_trait_impl(entity_meta: &EntityMeta) -> TokenStream {
     let Some(base_url) = entity_meta.attr.endpoint.as_ref() else {
         return TokenStream::new();
     };
-    common::entity_route_trait_impl(entity, base_url)
+    common::entity_route_trait_impl(entity, base_url, entity_meta.attr.isolation.as_ref())
+}
+
+fn entity_register_impl(entity_meta: &EntityMeta) -> TokenStream {
+    let entity = &entity_meta.entity_ident;
+    match (&entity_meta.attr.register, &entity_meta.attr.endpoint) {
+        (Some(state), Some(_)) => common::entity_register_impl(entity, state, true),
+        (Some(_), None) => {
+            syn::Error::new_spanned(entity, "The register attribute requires an endpoint").into_compile_error()
+        }
+        (None, _) => TokenStream::new(),
+    }
 }
 
 fn entity_collection_api_trait_impl(entity_meta: &EntityMeta) -> TokenStream {
     if entity_meta.attr.endpoint.is_none() {
         return TokenStream::new();
     }
+    let search_query = Some(&entity_meta.search_query_ident).filter(|_| !entity_meta.searchable_fields().is_empty());
     common::entity_collection_api_trait_impl(
         &entity_meta.entity_ident,
+        &entity_meta.entity_vis,
         &entity_meta.attr.collection_api,
         Some((&entity_meta.primary_key.ident, &entity_meta.sort_entity)),
+        search_query.map(|i| (i, entity_meta.attr.derive_to_schema)),
     )
 }
 
+fn entity_odata_trait_impl(entity_meta: &EntityMeta) -> TokenStream {
+    if entity_meta.attr.endpoint.is_none() || !matches!(entity_meta.attr.collection_api, CollectionApi::OData) {
+        return TokenStream::new();
+    }
+    let entity = &entity_meta.entity_ident;
+    let sea_query_ident = &entity_meta.sea_query_ident;
+    let properties = entity_meta.all_fields.iter().map(|f| {
+        let field_str = f.ident.to_string();
+        let field_ident_pascal = &f.ident_pascal;
+        let field_ty = &f.ty;
+        if f.attr.json {
+            quote! { lazybe::odata::ODataProperty::select_only(#field_str) }
+        } else {
+            quote! {
+                lazybe::odata::ODataProperty::new::<#field_ty>(#field_str, (#sea_query_ident::Table, #sea_query_ident::#field_ident_pascal))
+            }
+        }
+    });
+    quote! {
+        impl lazybe::odata::ODataEntity for #entity {
+            fn odata_properties() -> Vec<lazybe::odata::ODataProperty> {
+                vec![#(#properties),*]
+            }
+        }
+    }
+}
+
 fn entity_validation_hook_trait_impl(entity_meta: &EntityMeta) -> TokenStream {
     if entity_meta.attr.endpoint.is_none() {
         return TokenStream::new();
@@ -375,6 +606,27 @@ fn entity_entity_trait_impl(entity_meta: &EntityMeta) -> TokenStream {
     let update_entity = &entity_meta.update_entity;
     let replace_entity = &entity_meta.replace_entity;
     let row_entity = &entity_meta.sqlx_row_ident;
+    let sea_query_ident = &entity_meta.sea_query_ident;
+    let tenant_column_def = entity_meta.tenant.as_ref().map(|f| {
+        let ident = &f.ident_pascal;
+        quote! {
+            fn tenant_column() -> Option<sea_query::DynIden> {
+                Some(sea_query::IntoIden::into_iden(#sea_query_ident::#ident))
+            }
+        }
+    });
+    let history_def = Some(quote! {
+        fn history() -> Option<lazybe::history::History<Self>> {
+            Some(lazybe::history::History::new())
+        }
+    })
+    .filter(|_| entity_meta.attr.history);
+    let outbox_def = Some(quote! {
+        fn outbox() -> Option<lazybe::outbox::Outbox<Self>> {
+            Some(lazybe::outbox::Outbox::new())
+        }
+    })
+    .filter(|_| entity_meta.attr.outbox);
     quote! {
         impl lazybe::Entity for #entity {
             type Pk = #pk_ty;
@@ -389,10 +641,136 @@ fn entity_entity_trait_impl(entity_meta: &EntityMeta) -> TokenStream {
 
         impl lazybe::TableEntity for #entity {
             type Row = #row_entity;
+
+            fn table() -> sea_query::DynIden {
+                sea_query::IntoIden::into_iden(#sea_query_ident::Table)
+            }
+
+            #tenant_column_def
+            #history_def
+            #outbox_def
         }
     }
 }
 
+fn entity_schema_trait_impl(entity_meta: &EntityMeta) -> TokenStream {
+    let entity = &entity_meta.entity_ident;
+    let sea_query_ident = &entity_meta.sea_query_ident;
+    let column_defs = entity_meta.all_fields.iter().map(|f| {
+        let field_ident_pascal = &f.ident_pascal;
+        let inner_ty = option_inner_ty(&f.ty);
+        let column_type = column_type(f);
+        let nullability = match inner_ty {
+            Some(_) => quote! { .null() },
+            None => quote! { .not_null() },
+        };
+        let primary_key = match (f.attr.primary_key, &f.attr.generate_with) {
+            (true, None) => quote! { .primary_key().auto_increment() },
+            (true, Some(_)) => quote! { .primary_key() },
+            (false, _) => TokenStream::new(),
+        };
+        let default = Some(quote! { .default(sea_query::Expr::current_timestamp()) })
+            .filter(|_| f.attr.created_at || f.attr.updated_at);
+        quote! {
+            .col(
+                sea_query::ColumnDef::new_with_type(#sea_query_ident::#field_ident_pascal, #column_type)
+                    #nullability
+                    #primary_key
+                    #default
+            )
+        }
+    });
+    let renamed_columns = entity_meta
+        .all_fields
+        .iter()
+        .filter_map(|f| {
+            let renamed_from = f.attr.renamed_from.as_ref()?;
+            let column = f.ident.to_string();
+            Some(quote! { (#renamed_from, #column) })
+        })
+        .collect::<Vec<_>>();
+    let renamed_columns_def = Some(quote! {
+        fn renamed_columns() -> Vec<(&'static str, &'static str)> {
+            vec![#(#renamed_columns),*]
+        }
+    })
+    .filter(|_| !renamed_columns.is_empty());
+    quote! {
+        impl lazybe::schema::TableSchema for #entity {
+            fn create_table() -> sea_query::TableCreateStatement {
+                sea_query::Table::create()
+                    .table(#sea_query_ident::Table)
+                    #(#column_defs)*
+                    .to_owned()
+            }
+
+            #renamed_columns_def
+        }
+    }
+}
+
+fn entity_history_trait_impl(entity_meta: &EntityMeta) -> TokenStream {
+    if !entity_meta.attr.history {
+        return TokenStream::new();
+    }
+    let entity = &entity_meta.entity_ident;
+    let pk_ident = &entity_meta.primary_key.ident;
+    let key_type = column_type(&entity_meta.primary_key);
+    let tenant_type = match &entity_meta.tenant {
+        Some(f) => {
+            let tenant_type = column_type(f);
+            quote! { Some(#tenant_type) }
+        }
+        None => quote! { None },
+    };
+    quote! {
+        impl lazybe::history::HistoryEntity for #entity {
+            fn create_history_table() -> sea_query::TableCreateStatement {
+                lazybe::history::create_table::<Self>(#key_type, #tenant_type)
+            }
+
+            fn history_key(&self) -> sea_query::Value {
+                self.#pk_ident.clone().into()
+            }
+        }
+    }
+}
+
+fn entity_outbox_trait_impl(entity_meta: &EntityMeta) -> TokenStream {
+    if !entity_meta.attr.outbox {
+        return TokenStream::new();
+    }
+    let entity = &entity_meta.entity_ident;
+    quote! {
+        impl lazybe::outbox::OutboxEntity for #entity {}
+    }
+}
+
+/// The column type of a field, which is inferred from the Rust type unless it is set by `sql_type`
+fn column_type(f: &EntityField) -> TokenStream {
+    match (&f.attr.sql_type, option_inner_ty(&f.ty)) {
+        (Some(sql_type), _) => quote! { sea_query::ColumnType::custom(#sql_type) },
+        (None, _) if f.attr.json => quote! { sea_query::ColumnType::JsonBinary },
+        (None, Some(ty)) => common::infer_column_type(ty),
+        (None, None) => common::infer_column_type(&f.ty),
+    }
+}
+
+/// The `T` of `Option<T>`, or `None` if the type is not an `Option`
+fn option_inner_ty(ty: &Type) -> Option<&Type> {
+    let Type::Path(type_path) = ty else {
+        return None;
+    };
+    let segment = type_path.path.segments.last().filter(|s| s.ident == "Option")?;
+    let PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+    match args.args.first() {
+        Some(GenericArgument::Type(inner_ty)) if args.args.len() == 1 => Some(inner_ty),
+        _ => None,
+    }
+}
+
 fn entity_query_trait_impl(entity_meta: &EntityMeta) -> TokenStream {
     let mut ts = TokenStream::new();
     ts.extend(get_query_trait_impl(entity_meta));
@@ -403,6 +781,205 @@ fn entity_query_trait_impl(entity_meta: &EntityMeta) -> TokenStream {
     ts
 }
 
+fn entity_fields_trait_impl(entity_meta: &EntityMeta) -> TokenStream {
+    let entity = &entity_meta.entity_ident;
+    let sea_query_ident = &entity_meta.sea_query_ident;
+    let field_columns = entity_meta.all_fields.iter().map(|f| {
+        let field_str = f.ident.to_string();
+        let field_ident_pascal = &f.ident_pascal;
+        quote! {
+            (#field_str, sea_query::IntoColumnRef::into_column_ref((#sea_query_ident::Table, #sea_query_ident::#field_ident_pascal)))
+        }
+    });
+    let mut ts = quote! {
+        impl lazybe::fields::SelectFields for #entity {
+            fn field_columns() -> Vec<(&'static str, sea_query::ColumnRef)> {
+                vec![#(#field_columns),*]
+            }
+        }
+    };
+    if entity_meta.attr.endpoint.is_none() {
+        return ts;
+    }
+
+    // decoding a field requires it to be serializable, which is only expected from entities with an endpoint
+    let decode_tys = entity_meta.all_fields.iter().map(|f| {
+        let ty = &f.ty;
+        if f.attr.json {
+            quote! { sqlx::types::Json<#ty> }
+        } else {
+            quote! { #ty }
+        }
+    });
+    let decode_bounds = decode_tys.clone().map(|ty| {
+        quote! { #ty: for<'r> sqlx::Decode<'r, R::Database> + sqlx::Type<R::Database> }
+    });
+    let decode_arms = entity_meta.all_fields.iter().zip(decode_tys).map(|(f, ty)| {
+        let field_str = f.ident.to_string();
+        let value = if f.attr.json {
+            quote! { sqlx::Row::try_get::<#ty, _>(row, #field_str)?.0 }
+        } else {
+            quote! { sqlx::Row::try_get::<#ty, _>(row, #field_str)? }
+        };
+        quote! { #field_str => partial.insert(field, #value)? }
+    });
+    ts.extend(quote! {
+        impl<R> lazybe::fields::FromPartialRow<R> for #entity
+        where
+            R: sqlx::Row,
+            &'static str: sqlx::ColumnIndex<R>,
+            #(#decode_bounds),*
+        {
+            fn from_partial_row(
+                row: &R,
+                fields: &lazybe::fields::Fields<Self>,
+            ) -> Result<lazybe::fields::Partial<Self>, sqlx::Error> {
+                let mut partial = lazybe::fields::Partial::new();
+                for field in fields.names() {
+                    match *field {
+                        #(#decode_arms,)*
+                        _ => {}
+                    }
+                }
+                Ok(partial)
+            }
+        }
+    });
+    ts
+}
+
+fn entity_search_trait_impl(entity_meta: &EntityMeta) -> TokenStream {
+    let searchable_fields = entity_meta.searchable_fields();
+    if searchable_fields.is_empty() {
+        return TokenStream::new();
+    }
+    let entity = &entity_meta.entity_ident;
+    let sea_query_ident = &entity_meta.sea_query_ident;
+    let pk_ident_pascal = &entity_meta.primary_key.ident_pascal;
+    let searchable_idents_pascal = searchable_fields.iter().map(|f| &f.ident_pascal);
+    quote! {
+        impl lazybe::search::SearchableEntity for #entity {
+            fn search_table() -> sea_query::DynIden {
+                sea_query::IntoIden::into_iden(#sea_query_ident::Table)
+            }
+
+            fn search_key() -> sea_query::DynIden {
+                sea_query::IntoIden::into_iden(#sea_query_ident::#pk_ident_pascal)
+            }
+
+            fn search_columns() -> Vec<sea_query::DynIden> {
+                vec![
+                    #(sea_query::IntoIden::into_iden(#sea_query_ident::#searchable_idents_pascal)),*
+                ]
+            }
+        }
+    }
+}
+
+fn entity_relation_impl(entity_meta: &EntityMeta) -> TokenStream {
+    let entity = &entity_meta.entity_ident;
+    let entity_vis = &entity_meta.entity_vis;
+    let sea_query_ident = &entity_meta.sea_query_ident;
+    let join_filter_entity = &entity_meta.join_filter_entity;
+    let join_sort_entity = &entity_meta.join_sort_entity;
+    let pk_ident_pascal = &entity_meta.primary_key.ident_pascal;
+    let filter_method_defs = entity_meta.all_fields.iter().map(|f| {
+        let field_ident = &f.ident;
+        let field_ident_pascal = &f.ident_pascal;
+        let field_ty = &f.ty;
+        quote! {
+            pub fn #field_ident(self) -> lazybe::filter::FilterCol<Src, #field_ty> {
+                lazybe::filter::FilterCol::new((self.path.alias(), #sea_query_ident::#field_ident_pascal))
+                    .with_joins(self.path.into_joins())
+            }
+        }
+    });
+    let sort_method_defs = entity_meta.all_fields.iter().map(|f| {
+        let field_ident = &f.ident;
+        let field_ident_pascal = &f.ident_pascal;
+        quote! {
+            pub fn #field_ident(self) -> lazybe::sort::SortCol<Src> {
+                lazybe::sort::SortCol::new((self.path.alias(), #sea_query_ident::#field_ident_pascal))
+                    .with_joins(self.path.into_joins())
+            }
+        }
+    });
+    let filter_relation_method_defs = entity_meta.relations.iter().map(|r| {
+        let relation_ident = &r.ident;
+        let relation_str = r.ident.to_string();
+        let target = &r.target;
+        let fk_ident_pascal = &r.fk_ident_pascal;
+        quote! {
+            pub fn #relation_ident(self) -> <#target as lazybe::relation::Related>::JoinFilter<Src> {
+                let path = self.path.join::<#target>(#relation_str, sea_query::IntoIden::into_iden(#sea_query_ident::#fk_ident_pascal));
+                <#target as lazybe::relation::Related>::join_filter(path)
+            }
+        }
+    });
+    let sort_relation_method_defs = entity_meta.relations.iter().map(|r| {
+        let relation_ident = &r.ident;
+        let relation_str = r.ident.to_string();
+        let target = &r.target;
+        let fk_ident_pascal = &r.fk_ident_pascal;
+        quote! {
+            pub fn #relation_ident(self) -> <#target as lazybe::relation::Related>::JoinSort<Src> {
+                let path = self.path.join::<#target>(#relation_str, sea_query::IntoIden::into_iden(#sea_query_ident::#fk_ident_pascal));
+                <#target as lazybe::relation::Related>::join_sort(path)
+            }
+        }
+    });
+    quote! {
+        #[derive(Debug, Clone)]
+        #entity_vis struct #join_filter_entity<Src> {
+            path: lazybe::relation::JoinPath,
+            src: std::marker::PhantomData<Src>,
+        }
+
+        impl<Src> #join_filter_entity<Src> {
+            #(#filter_method_defs)*
+            #(#filter_relation_method_defs)*
+        }
+
+        #[derive(Debug, Clone)]
+        #entity_vis struct #join_sort_entity<Src> {
+            path: lazybe::relation::JoinPath,
+            src: std::marker::PhantomData<Src>,
+        }
+
+        impl<Src> #join_sort_entity<Src> {
+            #(#sort_method_defs)*
+            #(#sort_relation_method_defs)*
+        }
+
+        impl lazybe::relation::Related for #entity {
+            type JoinFilter<Src> = #join_filter_entity<Src>;
+            type JoinSort<Src> = #join_sort_entity<Src>;
+
+            fn join_table() -> sea_query::DynIden {
+                sea_query::IntoIden::into_iden(#sea_query_ident::Table)
+            }
+
+            fn join_key() -> sea_query::DynIden {
+                sea_query::IntoIden::into_iden(#sea_query_ident::#pk_ident_pascal)
+            }
+
+            fn join_filter<Src>(path: lazybe::relation::JoinPath) -> Self::JoinFilter<Src> {
+                #join_filter_entity {
+                    path,
+                    src: std::marker::PhantomData,
+                }
+            }
+
+            fn join_sort<Src>(path: lazybe::relation::JoinPath) -> Self::JoinSort<Src> {
+                #join_sort_entity {
+                    path,
+                    src: std::marker::PhantomData,
+                }
+            }
+        }
+    }
+}
+
 fn create_query_trait_impl(entity_meta: &EntityMeta) -> TokenStream {
     let entity = &entity_meta.entity_ident;
     let sea_query_ident = &entity_meta.sea_query_ident;
@@ -453,39 +1030,113 @@ fn create_query_trait_impl(entity_meta: &EntityMeta) -> TokenStream {
             quote! { #sea_query_ident::#pk_ident }
         })
         .into_iter();
-    let pk_value = entity_meta
-        .primary_key
-        .attr
-        .generate_with
+    let pk_value = entity_meta.primary_key.attr.generate_with.as_ref().map(|f| {
+        let f_ident = format_ident!("{}", f);
+        quote! {
+            let id: sea_query::Value = #f_ident(&input).into();
+        }
+    });
+    let pk_value_expr = pk_value.as_ref().map(|_| quote! { id.clone().into() }).into_iter();
+    let tenant_ident_pascal = entity_meta
+        .tenant
         .as_ref()
         .map(|f| {
-            let f_ident = format_ident!("{}", f);
-            quote! {
-                (#f_ident(&input).into())
-            }
+            let ident = &f.ident_pascal;
+            quote! { #sea_query_ident::#ident }
         })
         .into_iter();
+    // a create without a tenant fails before the statement is built
+    let tenant_value = entity_meta
+        .tenant
+        .as_ref()
+        .map(|_| quote! { let tenant = stamp.create_tenant::<Self>()?; });
+    let tenant_value_expr = tenant_value.as_ref().map(|_| quote! { tenant.into() }).into_iter();
+    // without an actor, NULL is inserted into an optional field and a create with a required field fails
+    let actor_fields = [&entity_meta.created_by, &entity_meta.updated_by];
+    let actor_idents_pascal = actor_fields.iter().filter_map(|f| f.as_ref()).map(|f| {
+        let ident = &f.ident_pascal;
+        quote! { #sea_query_ident::#ident }
+    });
+    let actor_value = actor_fields
+        .iter()
+        .filter_map(|f| f.as_ref())
+        .any(|f| option_inner_ty(&f.ty).is_none())
+        .then(|| quote! { let actor = stamp.create_actor::<Self>()?; });
+    let actor_values = actor_fields.iter().filter_map(|f| f.as_ref()).map(|f| {
+        if option_inner_ty(&f.ty).is_some() {
+            quote! { stamp.actor.clone().unwrap_or(sea_query::Value::Bool(None)).into() }
+        } else {
+            quote! { actor.clone().into() }
+        }
+    });
+    let stamp_ident = match (&entity_meta.tenant, actor_fields) {
+        (None, [None, None]) => format_ident!("_stamp"),
+        _ => format_ident!("stamp"),
+    };
+    let insert_stmt = quote! {
+        sea_query::Query::insert()
+            .into_table(#sea_query_ident::Table)
+            .columns([
+                #(#pk_ident_pascal,)*
+                #(#sea_query_ident::#user_defined_fields_ident_pascal,)*
+                #(#created_at_ident_pascal,)*
+                #(#updated_at_ident_pascal,)*
+                #(#actor_idents_pascal,)*
+                #(#tenant_ident_pascal,)*
+            ])
+            .values_panic([
+                #(#pk_value_expr,)*
+                #(#user_defined_fields_value,)*
+                #(#created_at_value,)*
+                #(#updated_at_value,)*
+                #(#actor_values,)*
+                #(#tenant_value_expr,)*
+            ])
+    };
+
+    // without RETURNING, the inserted record is found by the generated ID or the last auto-increment ID
+    let pk_ident = &entity_meta.primary_key.ident_pascal;
+    let inserted_id = match &pk_value {
+        Some(_) => quote! { id },
+        None => quote! { sea_query::Expr::cust("LAST_INSERT_ID()") },
+    };
+    let all_field_idents_pascal = entity_meta.all_fields.iter().map(|f| f.ident_pascal.clone());
 
     quote! {
         impl lazybe::query::CreateQuery for #entity {
-            fn create_query(input: Self::Create) -> sea_query::InsertStatement {
+            fn create_query(
+                input: Self::Create,
+                #stamp_ident: &lazybe::query::Stamp,
+            ) -> Result<sea_query::InsertStatement, sqlx::Error> {
+                #tenant_value
+                #actor_value
                 #now_value
-                sea_query::Query::insert()
-                    .into_table(#sea_query_ident::Table)
+                #pk_value
+                Ok(#insert_stmt
+                    .returning_all()
+                    .to_owned())
+            }
+
+            fn create_then_get_query(
+                input: Self::Create,
+                #stamp_ident: &lazybe::query::Stamp,
+            ) -> Result<(sea_query::InsertStatement, sea_query::SelectStatement), sqlx::Error> {
+                #tenant_value
+                #actor_value
+                #now_value
+                #pk_value
+                let insert_stmt = #insert_stmt.to_owned();
+                let get_stmt = sea_query::Query::select()
                     .columns([
-                        #(#pk_ident_pascal,)*
-                        #(#sea_query_ident::#user_defined_fields_ident_pascal,)*
-                        #(#created_at_ident_pascal,)*
-                        #(#updated_at_ident_pascal,)*
-                    ])
-                   .values_panic([
-                       #(#pk_value,)*
-                        #(#user_defined_fields_value,)*
-                        #(#created_at_value,)*
-                        #(#updated_at_value,)*
+                        #(#sea_query_ident::#all_field_idents_pascal),*
                     ])
-                    .returning_all()
-                    .to_owned()
+                    .from(#sea_query_ident::Table)
+                    .cond_where(
+                        sea_query::Cond::all()
+                            .add(sea_query::Expr::col(#sea_query_ident::#pk_ident).eq(#inserted_id))
+                    )
+                    .to_owned();
+                Ok((insert_stmt, get_stmt))
             }
         }
     }
@@ -523,16 +1174,41 @@ fn update_query_trait_impl(entity_meta: &EntityMeta) -> TokenStream {
             quote! { values.push((#sea_query_ident::#ident, now.into())); }
         })
         .into_iter();
+    let update_updated_by = entity_meta
+        .updated_by
+        .as_ref()
+        .map(|f| {
+            let ident = &f.ident_pascal;
+            if option_inner_ty(&f.ty).is_some() {
+                quote! {
+                    values.push((#sea_query_ident::#ident, stamp.actor.clone().unwrap_or(sea_query::Value::Bool(None)).into()));
+                }
+            } else {
+                quote! {
+                    values.push((#sea_query_ident::#ident, stamp.update_actor::<Self>()?.into()));
+                }
+            }
+        })
+        .into_iter();
+    let stamp_ident = match entity_meta.updated_by {
+        Some(_) => format_ident!("stamp"),
+        None => format_ident!("_stamp"),
+    };
     quote! {
         impl lazybe::query::UpdateQuery for #entity {
-            fn update_query(id: Self::Pk, input: Self::Update) -> sea_query::UpdateStatement {
+            fn update_query(
+                id: Self::Pk,
+                input: Self::Update,
+                #stamp_ident: &lazybe::query::Stamp,
+            ) -> Result<sea_query::UpdateStatement, sqlx::Error> {
                 #now_value
 
                 let mut values = Vec::new();
                 #(#update_user_defined_fields)*
                 #(#update_updated_at)*
+                #(#update_updated_by)*
 
-                sea_query::Query::update()
+                Ok(sea_query::Query::update()
                     .table(#sea_query_ident::Table)
                     .values(values)
                     .cond_where(
@@ -540,7 +1216,7 @@ fn update_query_trait_impl(entity_meta: &EntityMeta) -> TokenStream {
                             .add(sea_query::Expr::col(#sea_query_ident::#pk_ident_pascal).eq(id))
                     )
                     .returning_all()
-                    .to_owned()
+                    .to_owned())
             }
         }
     }
@@ -576,13 +1252,13 @@ fn list_query_trait_impl(entity_meta: &EntityMeta) -> TokenStream {
     quote! {
         impl lazybe::query::ListQuery for #entity {
             fn list_query(filter: lazybe::filter::Filter<Self>) -> sea_query::SelectStatement {
-                sea_query::Query::select()
+                let mut stmt = sea_query::Query::select()
                     .columns([
-                        #(#sea_query_ident::#all_field_idents_pascal),*
+                        #((#sea_query_ident::Table, #sea_query_ident::#all_field_idents_pascal)),*
                     ])
                     .from(#sea_query_ident::Table)
-                    .cond_where(sea_query::Cond::all().add(filter))
-                    .to_owned()
+                    .to_owned();
+                stmt.cond_where(sea_query::Cond::all().add(filter)).to_owned()
             }
         }
     }
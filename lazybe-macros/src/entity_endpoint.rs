@@ -3,7 +3,7 @@ use proc_macro2::TokenStream;
 use quote::quote;
 use syn::{Data, DataStruct, DeriveInput, Fields, FieldsNamed, Ident, Type};
 
-use crate::common::{self, CollectionApi, ValidationHook};
+use crate::common::{self, CollectionApi, IsolationLevel, ValidationHook};
 
 #[derive(Clone, FromDeriveInput)]
 #[darling(attributes(lazybe))]
@@ -14,6 +14,8 @@ struct EntityAttr {
     #[darling(default)]
     validation: ValidationHook,
     #[darling(default)]
+    isolation: Option<IsolationLevel>,
+    #[darling(default)]
     pk_ty: Option<Type>,
     #[darling(default)]
     create_ty: Option<Type>,
@@ -21,6 +23,8 @@ struct EntityAttr {
     update_ty: Option<Type>,
     #[darling(default)]
     replace_ty: Option<Type>,
+    #[darling(default)]
+    register: Option<Type>,
 }
 
 pub struct EndpointMeta {
@@ -54,20 +58,31 @@ fn expand_struct(input: &DeriveInput, data_struct: &DataStruct) -> syn::Result<T
         Fields::Named(fields_named) => {
             let endpoint_meta = EndpointMeta::try_parse(input, fields_named)?;
             let entity_entity_trait_impl = entity_entity_trait_impl(&endpoint_meta);
-            let entity_route_trait_impl =
-                common::entity_route_trait_impl(&endpoint_meta.entity_ident, &endpoint_meta.attr.endpoint);
+            let entity_route_trait_impl = common::entity_route_trait_impl(
+                &endpoint_meta.entity_ident,
+                &endpoint_meta.attr.endpoint,
+                endpoint_meta.attr.isolation.as_ref(),
+            );
             let entity_collection_api_trait_impl = common::entity_collection_api_trait_impl(
                 &endpoint_meta.entity_ident,
+                &input.vis,
                 &endpoint_meta.attr.collection_api,
                 None,
+                None,
             );
             let entity_validation_hook_trait_impl =
                 common::entity_validation_hook_trait_impl(&endpoint_meta.entity_ident, &endpoint_meta.attr.validation);
+            let entity_register_impl = endpoint_meta
+                .attr
+                .register
+                .as_ref()
+                .map(|state| common::entity_register_impl(&endpoint_meta.entity_ident, state, false));
             Ok(quote! {
                 #entity_entity_trait_impl
                 #entity_route_trait_impl
                 #entity_collection_api_trait_impl
                 #entity_validation_hook_trait_impl
+                #entity_register_impl
             })
         }
         Fields::Unnamed(_) => Err(syn::Error::new_spanned(
@@ -66,6 +66,12 @@ fn expand_enum(input: &DeriveInput, data_enum: &DataEnum) -> syn::Result<TokenSt
             }
         }
 
+        impl lazybe::schema::SqlType for #ident {
+            fn sql_type() -> sea_query::ColumnType {
+                sea_query::ColumnType::Text
+            }
+        }
+
         impl<Db> sqlx::Type<Db> for #ident
         where
             Db: sqlx::Database,
@@ -32,18 +32,45 @@ pub fn derive_newtype(input: TokenStream) -> TokenStream {
 /// - `endpoint = "..."` - The base URL path for exposing HTTP API. (e.g. `endpoint = /books`)
 /// - `collection_api = "..."` - The collection API style for listing entities.
 ///   - `list` - (default) Return a collection as a list without filtering, sorting, paging.
+///     If any field is `searchable`, the list can be narrowed and ranked by a full-text search term with `?q=...`.
 ///   - `manual` - Do not derive and manually provide the trait impl.
+///   - `odata` - Accept OData query options (`$filter`, `$orderby`, `$top`, `$skip`, `$select`, `$count`)
+///     and return an OData collection response. See `ODataQuery` for the supported syntax.
 /// - `validation = "..."` - The validation hook to run on entity modification via API.
 ///    - `default` - (default) A no-op validation which always pass.
 ///    - `manual` - Do not derive and manually provide the trait impl.
+/// - `isolation = "..."` - The isolation level of the transactions of the create, update and delete endpoints.
+///   One of `read_uncommitted`, `read_committed`, `repeatable_read` or `serializable`.
+///   Defaults to the isolation level of `RouteConfig::transaction_options`.
 /// - `derive_to_schema` - Derive `ToSchema` for all sibling types. This is useful for generating OpenAPI documeentation on generated types.
+/// - `register = "..."` - Register the endpoints in the global registry for the axum state (e.g. `register = "AppState"`).
+///   Requires `endpoint`. See `lazybe::registry` for mounting every registered entity at once.
+/// - `history` - Record every create, update and delete in the `{table}_history` table, in the same transaction as the change,
+///   with the operation, the timestamp, the actor and JSON snapshots before and after the change. Requires `Serialize`.
+///   See `HistoryEntity` for the history table and `HistoryRouter` for the `GET /{collection}/{id}/history` endpoint.
+/// - `outbox` - Store an `EntityEvent` of every create, update and delete in the outbox table, in the same transaction as the change.
+///   Requires `Serialize`. See `OutboxWorker` for delivering the stored events with an `OutboxDispatcher`.
 ///
 /// # Field attributes
 /// - `primary_key` - Specify the field to be used as primary key.
 /// - `generate_with = "..."` - A function use for generating an ID. If omitted, ID should be generated by the database.
 /// - `created_at` - Specify the field for created_at timestamp. The time is stamped once a record is created.
 /// - `updated_at` - Specify the field for updated_at timestamp. The time is stamped once a record is updated.
+/// - `created_by` - Specify the field for the actor who created a record. The actor is stamped once a record is created.
+/// - `updated_by` - Specify the field for the actor who last changed a record. The actor is stamped once a record is created or updated.
+///   See `DbCtx::actor` and `RouteConfig::actor` for providing the actor. Without an actor, `Option` fields are set to `NULL`
+///   and a change fails if a field is not an `Option`.
+/// - `tenant` - Specify the field that scopes the records to a tenant. It is hidden from the input types,
+///   stamped on created records and added to the condition of every other operation. See `DbCtx::tenant`
+///   and `RouteConfig::tenant` for providing the current tenant.
 /// - `json` - The field should be encoded as JSON column.
+/// - `searchable` - The field is included in full-text search. See `SearchableEntity` for the database setup.
+/// - `references = "..."` - The field is a foreign key to the primary key of another entity (e.g. `references = "Author"`).
+///   The filter and sort columns of the referenced entity become available through a join (e.g. `BookFilter::author().name()`).
+/// - `relation = "..."` - The name of the relation declared by `references`. Defaults to the field name without `_id` suffix.
+/// - `sql_type = "..."` - The column type in the generated `CREATE TABLE` statement (e.g. `sql_type = "varchar(64)"`).
+///   Defaults to the type inferred by `SqlType`, or the name of the Rust type (e.g. `Decimal`) if it does not implement `SqlType`.
+/// - `renamed_from = "..."` - The previous name of the column, so that a migration renames the column instead of replacing it.
 pub fn derive_entity(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
     entity::expand(input).into()
@@ -59,13 +86,18 @@ pub fn derive_entity(input: TokenStream) -> TokenStream {
 /// - `collection_api = "..."` - The collection api style for listing entities.
 ///   - `list` - (default) Return a collection as a list without filtering, sorting, paging.
 ///   - `manual` - Do not derive and manually provide the trait impl.
+///   - `odata` - Accept OData query options. The `ODataEntity` trait must be implemented manually.
 /// - `validation = "..."` - The validation hook to run on database modifications.
 ///    - `default` - (default) A no-op validation which always pass.
 ///    - `manual` - Do not derive and manually provide the trait impl.
+/// - `isolation = "..."` - The isolation level of the transactions of the create, update and delete endpoints.
+///   One of `read_uncommitted`, `read_committed`, `repeatable_read` or `serializable`.
 /// - `pk_ty = "..."` - Define the type of public key if applicable (e.g. `pk_ty = "i32"`)
 /// - `create_ty = "..."` - Define the type that can be use to create this custom entity type if applicable (e.g. `create_ty = "Vec<Book>"`)
 /// - `update_ty = "..."` - Define the type that can be use to update this custom entity type if applicable
 /// - `replace_ty = "..."` - Define the type that can be use to replace this custom entity type if applicable
+/// - `register = "..."` - Register the endpoints in the global registry for the axum state (e.g. `register = "AppState"`).
+///   Only the endpoints of the implemented entity operations are registered. See `lazybe::registry`.
 pub fn derive_entity_endpoint(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
     entity_endpoint::expand(input).into()
@@ -2,6 +2,8 @@ use proc_macro2::TokenStream;
 use quote::quote;
 use syn::{Data, DataStruct, DeriveInput, Fields};
 
+use crate::common::infer_column_type;
+
 pub fn expand(input: DeriveInput) -> TokenStream {
     match &input.data {
         Data::Struct(data_struct) => expand_struct(&input, data_struct).unwrap_or_else(|e| e.into_compile_error()),
@@ -26,6 +28,7 @@ fn expand_struct(input: &DeriveInput, data_struct: &DataStruct) -> syn::Result<T
             let field = fields_unnamed.unnamed.iter().next().unwrap();
             let newtype = &input.ident;
             let inner_ty = &field.ty;
+            let inner_column_type = infer_column_type(inner_ty);
             Ok(quote! {
                 impl<'r, Db> sqlx::Decode<'r, Db> for #newtype
                 where
@@ -59,6 +62,12 @@ fn expand_struct(input: &DeriveInput, data_struct: &DataStruct) -> syn::Result<T
                         <#inner_ty as sea_query::Nullable>::null()
                     }
                 }
+
+                impl lazybe::schema::SqlType for #newtype {
+                    fn sql_type() -> sea_query::ColumnType {
+                        #inner_column_type
+                    }
+                }
             })
         }
         Fields::Named(_) => Err(syn::Error::new_spanned(
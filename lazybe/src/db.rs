@@ -1,26 +1,337 @@
-use sea_query::QueryBuilder;
-use sqlx::{Database, Transaction};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use sea_query::{DynIden, QueryBuilder, SimpleExpr, TableRef};
+use sqlx::{Acquire, Database, Pool, Transaction};
 
 use crate::Entity;
-use crate::entity::ops::{CreateEntity, DeleteEntity, GetEntity, ListEntity, UpdateEntity};
+use crate::entity::ops::{
+    self, CreateEntity, DeleteEntity, GetEntity, GetHistory, GetPartialEntity, ListEntity, ListPartialEntity,
+    UpdateEntity,
+};
+use crate::fields::{Fields, Partial};
 use crate::filter::Filter;
+use crate::history::HistoryRecord;
 use crate::page::{Page, PaginationInput};
+use crate::query::{CreateQuery, DeleteQuery, GetQuery, ListQuery, UpdateQuery};
+use crate::schema::migration::ColumnSnapshot;
+use crate::search::Search;
 use crate::sort::Sort;
 
 /// A context containing information about the target database.
 pub trait DbCtx<Db> {
-    type Qb: QueryBuilder + Default + Send;
+    type Qb: QueryBuilder + Dialect + Default + Send;
     fn query_builder(&self) -> Self::Qb;
+
+    /// Resolve a table defined on an entity to the table that is queried at runtime,
+    /// e.g. to qualify it with a schema or to add a prefix. Defaults to the table itself.
+    ///
+    /// Selected tables are aliased with their original name, so columns are still referenced by the entity table.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use lazybe::db::DbCtx;
+    /// # use sea_query::{Alias, DynIden, IntoIden, SqliteQueryBuilder, TableRef};
+    /// # use sqlx::Sqlite;
+    /// /// Stores the table `book` as `t1_book`
+    /// struct PrefixDbCtx {
+    ///     prefix: String,
+    /// }
+    ///
+    /// impl DbCtx<Sqlite> for PrefixDbCtx {
+    ///     type Qb = SqliteQueryBuilder;
+    ///
+    ///     fn query_builder(&self) -> Self::Qb {
+    ///         SqliteQueryBuilder
+    ///     }
+    ///
+    ///     fn table_ref(&self, table: DynIden) -> TableRef {
+    ///         let table = Alias::new(format!("{}{}", self.prefix, table.to_string()));
+    ///         TableRef::Table(table.into_iden())
+    ///     }
+    /// }
+    /// ```
+    fn table_ref(&self, table: DynIden) -> TableRef {
+        TableRef::Table(table)
+    }
+
+    /// Entity operations taking at least this long are reported with a `WARN` event
+    /// containing the entity, the operation, the redacted SQL and the duration.
+    /// Defaults to `None`, which disables the report.
+    ///
+    /// Every operation is also traced in a `db_op` span at `INFO` level regardless of this threshold.
+    fn slow_query_threshold(&self) -> Option<Duration> {
+        None
+    }
+
+    /// The tenant that the records of entities with a `#[lazybe(tenant)]` field are scoped to.
+    /// Such records are only read, updated and deleted within the tenant, and they are created with it.
+    ///
+    /// Defaults to `None`, in which case the scoped entities match no records and cannot be created.
+    /// See [`TenantDbCtx`] for scoping another context.
+    fn tenant(&self) -> Option<sea_query::Value> {
+        None
+    }
+
+    /// Who makes the changes, e.g. a user ID, stamped on the `#[lazybe(created_by)]` field of created records
+    /// and the `#[lazybe(updated_by)]` field of created and updated records.
+    /// It is also recorded in the history of `#[lazybe(history)]` entities.
+    ///
+    /// Defaults to `None`, in which case optional fields are set to `NULL` and a change of an entity
+    /// with a field that is not optional fails with [`sqlx::Error::Configuration`].
+    /// See [`ActorDbCtx`] for acting with another context.
+    fn actor(&self) -> Option<sea_query::Value> {
+        None
+    }
+}
+
+impl<Ctx, Db> DbCtx<Db> for &Ctx
+where
+    Ctx: DbCtx<Db>,
+{
+    type Qb = Ctx::Qb;
+
+    fn query_builder(&self) -> Self::Qb {
+        (*self).query_builder()
+    }
+
+    fn table_ref(&self, table: DynIden) -> TableRef {
+        (*self).table_ref(table)
+    }
+
+    fn slow_query_threshold(&self) -> Option<Duration> {
+        (*self).slow_query_threshold()
+    }
+
+    fn tenant(&self) -> Option<sea_query::Value> {
+        (*self).tenant()
+    }
+
+    fn actor(&self) -> Option<sea_query::Value> {
+        (*self).actor()
+    }
+}
+
+/// A context scoped to a tenant, which delegates everything else to the wrapped context.
+///
+/// The endpoints scope their contexts to the tenant of [`RouteConfig::tenant`](crate::router::RouteConfig::tenant).
+///
+/// # Example
+///
+/// ```
+/// # use lazybe::db::sqlite::SqliteDbCtx;
+/// # use lazybe::db::{DbSql, TenantDbCtx};
+/// # use lazybe::macros::Entity;
+/// #[derive(Entity)]
+/// #[lazybe(table = "note")]
+/// pub struct Note {
+///     #[lazybe(primary_key)]
+///     pub id: i32,
+///     #[lazybe(tenant)]
+///     pub tenant_id: i32,
+///     pub text: String,
+/// }
+///
+/// let ctx = TenantDbCtx::new(SqliteDbCtx, Some(7.into()));
+/// assert_eq!(
+///     ctx.get_sql::<Note>(1),
+///     [r#"SELECT "id", "tenant_id", "text" FROM "note" WHERE "id" = 1 AND "note"."tenant_id" = 7"#]
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct TenantDbCtx<Ctx> {
+    ctx: Ctx,
+    tenant: Option<sea_query::Value>,
+}
+
+impl<Ctx> TenantDbCtx<Ctx> {
+    /// Scope the context to the tenant. Without a tenant, the tenant of the wrapped context is used.
+    pub fn new(ctx: Ctx, tenant: Option<sea_query::Value>) -> Self {
+        Self { ctx, tenant }
+    }
+
+    /// The wrapped context
+    pub fn inner(&self) -> &Ctx {
+        &self.ctx
+    }
+}
+
+impl<Ctx, Db> DbCtx<Db> for TenantDbCtx<Ctx>
+where
+    Ctx: DbCtx<Db>,
+{
+    type Qb = Ctx::Qb;
+
+    fn query_builder(&self) -> Self::Qb {
+        self.ctx.query_builder()
+    }
+
+    fn table_ref(&self, table: DynIden) -> TableRef {
+        self.ctx.table_ref(table)
+    }
+
+    fn slow_query_threshold(&self) -> Option<Duration> {
+        self.ctx.slow_query_threshold()
+    }
+
+    fn tenant(&self) -> Option<sea_query::Value> {
+        self.tenant.clone().or_else(|| self.ctx.tenant())
+    }
+
+    fn actor(&self) -> Option<sea_query::Value> {
+        self.ctx.actor()
+    }
+}
+
+/// A context acting for an actor, which delegates everything else to the wrapped context.
+///
+/// The create and update endpoints act for the actor of [`RouteConfig::actor`](crate::router::RouteConfig::actor),
+/// and [`DbOps::create_as`] and [`DbOps::update_as`] act for the given actor.
+///
+/// # Example
+///
+/// ```
+/// # use lazybe::db::sqlite::SqliteDbCtx;
+/// # use lazybe::db::{ActorDbCtx, DbSql};
+/// # use lazybe::macros::Entity;
+/// #[derive(Entity)]
+/// #[lazybe(table = "note")]
+/// pub struct Note {
+///     #[lazybe(primary_key)]
+///     pub id: i32,
+///     pub text: String,
+///     #[lazybe(created_by)]
+///     pub created_by: String,
+///     #[lazybe(updated_by)]
+///     pub updated_by: String,
+/// }
+///
+/// let ctx = ActorDbCtx::new(SqliteDbCtx, Some("alice".into()));
+/// let input = CreateNote {
+///     text: "hello".to_string(),
+/// };
+/// assert_eq!(
+///     ctx.create_sql::<Note>(input).unwrap(),
+///     [r#"INSERT INTO "note" ("text", "created_by", "updated_by") VALUES ('hello', 'alice', 'alice') RETURNING *"#]
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct ActorDbCtx<Ctx> {
+    ctx: Ctx,
+    actor: Option<sea_query::Value>,
+}
+
+impl<Ctx> ActorDbCtx<Ctx> {
+    /// Act for the actor. Without an actor, the actor of the wrapped context is used.
+    pub fn new(ctx: Ctx, actor: Option<sea_query::Value>) -> Self {
+        Self { ctx, actor }
+    }
+
+    /// The wrapped context
+    pub fn inner(&self) -> &Ctx {
+        &self.ctx
+    }
+}
+
+impl<Ctx, Db> DbCtx<Db> for ActorDbCtx<Ctx>
+where
+    Ctx: DbCtx<Db>,
+{
+    type Qb = Ctx::Qb;
+
+    fn query_builder(&self) -> Self::Qb {
+        self.ctx.query_builder()
+    }
+
+    fn table_ref(&self, table: DynIden) -> TableRef {
+        self.ctx.table_ref(table)
+    }
+
+    fn slow_query_threshold(&self) -> Option<Duration> {
+        self.ctx.slow_query_threshold()
+    }
+
+    fn tenant(&self) -> Option<sea_query::Value> {
+        self.ctx.tenant()
+    }
+
+    fn actor(&self) -> Option<sea_query::Value> {
+        self.actor.clone().or_else(|| self.ctx.actor())
+    }
+}
+
+/// Database specific SQL that cannot be expressed uniformly by [`sea_query`].
+pub trait Dialect {
+    /// A condition that matches records containing every word of the search term
+    fn search_cond(&self, search: &Search) -> SimpleExpr;
+
+    /// A relevance score of a record for the search term. A higher score is more relevant.
+    fn search_rank(&self, search: &Search) -> SimpleExpr;
+
+    /// Whether `INSERT` and `UPDATE` statements can return the affected record with `RETURNING`.
+    /// Otherwise, the record is read back with a separate query in the same transaction.
+    fn supports_returning(&self) -> bool {
+        true
+    }
+
+    /// The statement that begins a transaction with the isolation level.
+    /// Returns `None` when the isolation level cannot be chosen, e.g. SQLite transactions are always serializable,
+    /// so the requested isolation level is ignored.
+    #[allow(unused_variables)]
+    fn begin_statement(&self, isolation_level: IsolationLevel) -> Option<String> {
+        None
+    }
+
+    /// A query listing the columns of a table in the connected database in their declared order.
+    /// Each row has the column name, the type name and `'YES'` or `'NO'` for whether the column is nullable.
+    /// The schema defaults to the current schema.
+    fn columns_query(&self, schema: Option<&str>, table: &str) -> String;
+
+    /// Whether a column can store a value of any type regardless of its declared type, as in SQLite.
+    fn is_dynamically_typed(&self) -> bool {
+        false
+    }
+
+    /// The statements that change a column to the type and the nullability of the new column.
+    /// Returns `None` when a column cannot be altered, e.g. in SQLite, so the table is rebuilt instead.
+    #[allow(unused_variables)]
+    fn alter_column_statements(&self, table: &str, old: &ColumnSnapshot, new: &ColumnSnapshot) -> Option<Vec<String>> {
+        None
+    }
+
+    /// The statements that add a column to a table. A `NOT NULL` column without a default is added with
+    /// the `backfill` value as its default, so that the existing records have a value.
+    /// Returns `None` when the column cannot be added, e.g. in SQLite with a default of `CURRENT_TIMESTAMP`,
+    /// so the table is rebuilt instead.
+    #[allow(unused_variables)]
+    fn add_column_statements(
+        &self,
+        table: &str,
+        column: &ColumnSnapshot,
+        backfill: Option<&str>,
+    ) -> Option<Vec<String>> {
+        None
+    }
+}
+
+/// The number of rows changed by a statement, as reported by the query result of a database
+pub trait RowsAffected {
+    fn rows_affected(&self) -> u64;
 }
 
 /// Integration with sqlite database
 #[cfg(feature = "sqlite")]
 #[doc(cfg(feature = "sqlite"))]
 pub mod sqlite {
-    use sea_query::SqliteQueryBuilder;
+    use sea_query::{Expr, Query, QueryBuilder, SimpleExpr, SqliteQueryBuilder};
     use sqlx::Sqlite;
+    use sqlx::sqlite::SqliteQueryResult;
 
-    use super::DbCtx;
+    use super::{DbCtx, Dialect, RowsAffected};
+    use crate::schema::migration::{ColumnSnapshot, quoted};
+    use crate::search::Search;
 
     #[derive(Debug, Clone, Default)]
     pub struct SqliteDbCtx;
@@ -32,16 +343,109 @@ pub mod sqlite {
             SqliteQueryBuilder
         }
     }
+
+    impl RowsAffected for SqliteQueryResult {
+        fn rows_affected(&self) -> u64 {
+            SqliteQueryResult::rows_affected(self)
+        }
+    }
+
+    impl Dialect for SqliteQueryBuilder {
+        fn search_cond(&self, search: &Search) -> SimpleExpr {
+            let fts = search.fts_table();
+            Expr::col((search.table(), search.key())).in_subquery(
+                Query::select()
+                    .column(search.key())
+                    .from(search.fts_table_ref())
+                    .and_where(Expr::cust_with_exprs(
+                        "? MATCH ?",
+                        [Expr::col(fts).into(), Expr::val(search.fts_match_query()).into()],
+                    ))
+                    .to_owned(),
+            )
+        }
+
+        fn search_rank(&self, search: &Search) -> SimpleExpr {
+            // bm25 scores better matches with a lower value, so it is negated
+            let match_query = search.fts_match_query();
+            if match_query.is_empty() {
+                return Expr::val(0).into();
+            }
+            let fts = search.fts_table();
+            let bm25 = Query::select()
+                .expr(Expr::cust_with_exprs("bm25(?)", [Expr::col(fts.clone()).into()]))
+                .from(search.fts_table_ref())
+                .and_where(Expr::cust_with_exprs(
+                    "? MATCH ?",
+                    [Expr::col(fts.clone()).into(), Expr::val(match_query).into()],
+                ))
+                .and_where(Expr::col((fts, search.key())).equals((search.table(), search.key())))
+                .to_owned();
+            Expr::cust_with_exprs(
+                "-?",
+                [SimpleExpr::SubQuery(None, Box::new(bm25.into_sub_query_statement()))],
+            )
+        }
+
+        fn columns_query(&self, schema: Option<&str>, table: &str) -> String {
+            // a primary key is nullable unless declared NOT NULL,
+            // but the only INTEGER primary key of a table is the rowid, which cannot be null
+            let args = match schema {
+                Some(schema) => format!(
+                    "{}, {}",
+                    self.value_to_string(&table.into()),
+                    self.value_to_string(&schema.into())
+                ),
+                None => self.value_to_string(&table.into()),
+            };
+            format!(
+                "SELECT name, type, CASE WHEN \"notnull\" = 1 OR (pk = 1 AND lower(type) = 'integer' \
+                 AND (SELECT count(*) FROM pragma_table_info({args}) WHERE pk > 0) = 1) THEN 'NO' ELSE 'YES' END \
+                 FROM pragma_table_info({args}) ORDER BY cid"
+            )
+        }
+
+        fn is_dynamically_typed(&self) -> bool {
+            true
+        }
+
+        fn add_column_statements(
+            &self,
+            table: &str,
+            column: &ColumnSnapshot,
+            backfill: Option<&str>,
+        ) -> Option<Vec<String>> {
+            // an added column cannot default to the current time or to an expression,
+            // and a NOT NULL column needs a default even when the table is empty
+            let definition = column.definition();
+            if definition.contains("DEFAULT CURRENT_")
+                || definition.contains("DEFAULT (")
+                || (!column.nullable() && !column.has_default() && backfill.is_none())
+            {
+                return None;
+            }
+            let default = backfill.map(|value| format!(" DEFAULT {}", value)).unwrap_or_default();
+            Some(vec![format!(
+                "ALTER TABLE {} ADD COLUMN {}{}",
+                quoted(table, self),
+                definition,
+                default
+            )])
+        }
+    }
 }
 
 /// Integration with postgres database
 #[cfg(feature = "postgres")]
 #[doc(cfg(feature = "postgres"))]
 pub mod postgres {
-    use sea_query::PostgresQueryBuilder;
+    use sea_query::{Expr, PostgresQueryBuilder, QueryBuilder, SimpleExpr};
     use sqlx::Postgres;
+    use sqlx::postgres::PgQueryResult;
 
-    use super::DbCtx;
+    use super::{DbCtx, Dialect, IsolationLevel, RowsAffected};
+    use crate::schema::migration::{ColumnSnapshot, add_column_with_backfill, quoted};
+    use crate::search::Search;
 
     #[derive(Debug, Clone, Default)]
     pub struct PostgresDbCtx;
@@ -53,24 +457,538 @@ pub mod postgres {
             PostgresQueryBuilder
         }
     }
+
+    impl RowsAffected for PgQueryResult {
+        fn rows_affected(&self) -> u64 {
+            PgQueryResult::rows_affected(self)
+        }
+    }
+
+    impl Dialect for PostgresQueryBuilder {
+        fn search_cond(&self, search: &Search) -> SimpleExpr {
+            let (document, mut exprs) = tsvector_document(search);
+            exprs.push(Expr::val(search.term()).into());
+            let sql = format!("to_tsvector({}) @@ plainto_tsquery(${})", document, exprs.len());
+            Expr::cust_with_exprs(sql, exprs)
+        }
+
+        fn search_rank(&self, search: &Search) -> SimpleExpr {
+            let (document, mut exprs) = tsvector_document(search);
+            exprs.push(Expr::val(search.term()).into());
+            let sql = format!("ts_rank(to_tsvector({}), plainto_tsquery(${}))", document, exprs.len());
+            Expr::cust_with_exprs(sql, exprs)
+        }
+
+        fn begin_statement(&self, isolation_level: IsolationLevel) -> Option<String> {
+            Some(format!("BEGIN ISOLATION LEVEL {}", isolation_level.as_sql()))
+        }
+
+        fn columns_query(&self, schema: Option<&str>, table: &str) -> String {
+            let schema = match schema {
+                Some(schema) => self.value_to_string(&schema.into()),
+                None => "current_schema()".to_string(),
+            };
+            format!(
+                "SELECT column_name::text, udt_name::text, is_nullable::text FROM information_schema.columns \
+                 WHERE table_schema = {} AND table_name = {} ORDER BY ordinal_position",
+                schema,
+                self.value_to_string(&table.into())
+            )
+        }
+
+        fn alter_column_statements(
+            &self,
+            table: &str,
+            old: &ColumnSnapshot,
+            new: &ColumnSnapshot,
+        ) -> Option<Vec<String>> {
+            let alter_column = format!(
+                "ALTER TABLE {} ALTER COLUMN {}",
+                quoted(table, self),
+                quoted(new.name(), self)
+            );
+            // a serial type is an integer with a sequence, which only exists in `CREATE TABLE`.
+            // The sequence of a serial column is kept when it changes to another serial type.
+            let sql_type = match new.sql_type() {
+                "smallserial" => "smallint",
+                "serial" => "integer",
+                "bigserial" => "bigint",
+                sql_type => sql_type,
+            };
+            let mut statements = Vec::new();
+            if old.sql_type() != new.sql_type() {
+                statements.push(format!(
+                    "{} TYPE {} USING {}::{}",
+                    alter_column,
+                    sql_type,
+                    quoted(new.name(), self),
+                    sql_type
+                ));
+            }
+            if old.nullable() != new.nullable() {
+                let nullability = if new.nullable() {
+                    "DROP NOT NULL"
+                } else {
+                    "SET NOT NULL"
+                };
+                statements.push(format!("{} {}", alter_column, nullability));
+            }
+            Some(statements)
+        }
+
+        fn add_column_statements(
+            &self,
+            table: &str,
+            column: &ColumnSnapshot,
+            backfill: Option<&str>,
+        ) -> Option<Vec<String>> {
+            Some(add_column_with_backfill(self, table, column, backfill))
+        }
+    }
+
+    /// Concatenate all searchable columns into a single text document
+    fn tsvector_document(search: &Search) -> (String, Vec<SimpleExpr>) {
+        let exprs: Vec<SimpleExpr> = search
+            .columns()
+            .iter()
+            .map(|col| Expr::col((search.table(), col.clone())).into())
+            .collect();
+        let document = (1..=exprs.len())
+            .map(|i| format!("coalesce(${}, '')", i))
+            .collect::<Vec<_>>()
+            .join(" || ' ' || ");
+        (document, exprs)
+    }
+}
+
+/// Integration with mysql and mariadb database
+#[cfg(feature = "mysql")]
+#[doc(cfg(feature = "mysql"))]
+pub mod mysql {
+    use sea_query::{Expr, MysqlQueryBuilder, QueryBuilder, SimpleExpr};
+    use sqlx::MySql;
+    use sqlx::mysql::MySqlQueryResult;
+
+    use super::{DbCtx, Dialect, IsolationLevel, RowsAffected};
+    use crate::schema::migration::{ColumnSnapshot, add_column_with_backfill, quoted};
+    use crate::search::Search;
+
+    #[derive(Debug, Clone, Default)]
+    pub struct MySqlDbCtx;
+
+    impl DbCtx<MySql> for MySqlDbCtx {
+        type Qb = MysqlQueryBuilder;
+
+        fn query_builder(&self) -> Self::Qb {
+            MysqlQueryBuilder
+        }
+    }
+
+    impl RowsAffected for MySqlQueryResult {
+        fn rows_affected(&self) -> u64 {
+            MySqlQueryResult::rows_affected(self)
+        }
+    }
+
+    impl Dialect for MysqlQueryBuilder {
+        fn search_cond(&self, search: &Search) -> SimpleExpr {
+            // every word is required and quoted so that boolean mode operators in user input are treated as plain text
+            let query = search
+                .term()
+                .split_whitespace()
+                .map(|word| format!("+\"{}\"", word.replace('"', "")))
+                .collect::<Vec<_>>()
+                .join(" ");
+            let (columns, mut exprs) = match_columns(search);
+            exprs.push(Expr::val(query).into());
+            Expr::cust_with_exprs(format!("MATCH ({}) AGAINST (? IN BOOLEAN MODE)", columns), exprs)
+        }
+
+        fn search_rank(&self, search: &Search) -> SimpleExpr {
+            let (columns, mut exprs) = match_columns(search);
+            exprs.push(Expr::val(search.term()).into());
+            Expr::cust_with_exprs(
+                format!("MATCH ({}) AGAINST (? IN NATURAL LANGUAGE MODE)", columns),
+                exprs,
+            )
+        }
+
+        fn supports_returning(&self) -> bool {
+            false
+        }
+
+        fn begin_statement(&self, isolation_level: IsolationLevel) -> Option<String> {
+            // the isolation level cannot be changed inside a transaction, so it is set for the next one
+            Some(format!(
+                "SET TRANSACTION ISOLATION LEVEL {}; START TRANSACTION",
+                isolation_level.as_sql()
+            ))
+        }
+
+        fn columns_query(&self, schema: Option<&str>, table: &str) -> String {
+            let schema = match schema {
+                Some(schema) => self.value_to_string(&schema.into()),
+                None => "DATABASE()".to_string(),
+            };
+            format!(
+                "SELECT CAST(column_name AS CHAR), CAST(column_type AS CHAR), CAST(is_nullable AS CHAR) \
+                 FROM information_schema.columns WHERE table_schema = {} AND table_name = {} ORDER BY ordinal_position",
+                schema,
+                self.value_to_string(&table.into())
+            )
+        }
+
+        fn alter_column_statements(
+            &self,
+            table: &str,
+            _: &ColumnSnapshot,
+            new: &ColumnSnapshot,
+        ) -> Option<Vec<String>> {
+            Some(vec![format!(
+                "ALTER TABLE {} MODIFY COLUMN {}",
+                quoted(table, self),
+                new.definition()
+            )])
+        }
+
+        fn add_column_statements(
+            &self,
+            table: &str,
+            column: &ColumnSnapshot,
+            backfill: Option<&str>,
+        ) -> Option<Vec<String>> {
+            // a TEXT, BLOB or JSON column cannot have a default, the existing records get an empty value
+            let sql_type = column.sql_type().to_lowercase();
+            let backfill = backfill.filter(|_| !["text", "blob", "json"].iter().any(|t| sql_type.contains(t)));
+            Some(add_column_with_backfill(self, table, column, backfill))
+        }
+    }
+
+    /// The column list of a `MATCH (..)` expression which must be the same as the `FULLTEXT` index
+    fn match_columns(search: &Search) -> (String, Vec<SimpleExpr>) {
+        let exprs: Vec<SimpleExpr> = search
+            .columns()
+            .iter()
+            .map(|col| Expr::col((search.table(), col.clone())).into())
+            .collect();
+        let columns = vec!["?"; exprs.len()].join(", ");
+        (columns, exprs)
+    }
+}
+
+/// A pool of the primary database together with a pool of a read replica.
+///
+/// Writes go to [`ReplicatedPool::primary`] and reads go to [`ReplicatedPool::read`].
+/// Reads can opt-in to use the primary for a while after a write with [`ReplicatedPool::read_after_write`],
+/// so that recent writes are visible even when the replica is lagging behind.
+///
+/// The last write is shared by all clones of the pool, so a write by any client sends the reads of every client
+/// to the primary during the window. To track the window per client instead, e.g. per session,
+/// store the time of the client's last write and read with [`ReplicatedPool::read_since`].
+///
+/// # Example
+///
+/// ```
+/// # use std::time::{Duration, Instant};
+/// # use lazybe::db::ReplicatedPool;
+/// # use sqlx::SqlitePool;
+/// # async fn example(primary: SqlitePool, replica: SqlitePool) -> Result<(), sqlx::Error> {
+/// let pools = ReplicatedPool::new(primary, replica).read_after_write(Duration::from_secs(5));
+///
+/// // reads from the replica
+/// let _ = pools.read();
+///
+/// // writes to the primary, then reads from the primary for the next 5 seconds
+/// let _ = pools.primary();
+/// pools.mark_write();
+/// let _ = pools.read();
+///
+/// // reads from the primary within 5 seconds after a write of the same client
+/// let last_write = Some(Instant::now());
+/// let _ = pools.read_since(last_write);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct ReplicatedPool<Db: Database> {
+    primary: Pool<Db>,
+    replica: Pool<Db>,
+    read_after_write: Option<Duration>,
+    last_write: Arc<Mutex<Option<Instant>>>,
+}
+
+impl<Db: Database> Clone for ReplicatedPool<Db> {
+    fn clone(&self) -> Self {
+        Self {
+            primary: self.primary.clone(),
+            replica: self.replica.clone(),
+            read_after_write: self.read_after_write,
+            last_write: self.last_write.clone(),
+        }
+    }
+}
+
+impl<Db: Database> ReplicatedPool<Db> {
+    pub fn new(primary: Pool<Db>, replica: Pool<Db>) -> Self {
+        Self {
+            primary,
+            replica,
+            read_after_write: None,
+            last_write: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Read from the primary within `window` after the last write, as recorded by [`ReplicatedPool::mark_write`]
+    pub fn read_after_write(self, window: Duration) -> Self {
+        Self {
+            read_after_write: Some(window),
+            ..self
+        }
+    }
+
+    /// The pool for writes
+    pub fn primary(&self) -> &Pool<Db> {
+        &self.primary
+    }
+
+    /// The pool for reads. This is the replica unless any write was recorded by [`ReplicatedPool::mark_write`]
+    /// within the read after write window.
+    pub fn read(&self) -> &Pool<Db> {
+        let last_write = *self.last_write.lock().expect("last write lock is poisoned");
+        self.read_since(last_write)
+    }
+
+    /// The pool for reads of a client whose last write was at `last_write`.
+    /// This is the replica unless `last_write` is within the read after write window.
+    pub fn read_since(&self, last_write: Option<Instant>) -> &Pool<Db> {
+        match (self.read_after_write, last_write) {
+            (Some(window), Some(last_write)) if last_write.elapsed() < window => &self.primary,
+            _ => &self.replica,
+        }
+    }
+
+    /// Record that a write was committed to the primary, which affects the reads of all clones of the pool
+    pub fn mark_write(&self) {
+        if self.read_after_write.is_some() {
+            *self.last_write.lock().expect("last write lock is poisoned") = Some(Instant::now());
+        }
+    }
+}
+
+/// The isolation level of a database transaction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IsolationLevel {
+    ReadUncommitted,
+    ReadCommitted,
+    RepeatableRead,
+    Serializable,
+}
+
+impl IsolationLevel {
+    /// The isolation level in SQL syntax (e.g. `REPEATABLE READ`)
+    pub fn as_sql(&self) -> &'static str {
+        match self {
+            IsolationLevel::ReadUncommitted => "READ UNCOMMITTED",
+            IsolationLevel::ReadCommitted => "READ COMMITTED",
+            IsolationLevel::RepeatableRead => "REPEATABLE READ",
+            IsolationLevel::Serializable => "SERIALIZABLE",
+        }
+    }
+}
+
+/// Defines how [`with_transaction`] begins a transaction and retries it.
+///
+/// By default, a transaction uses the isolation level of the database
+/// and is attempted up to 3 times with a backoff from 10 milliseconds up to 1 second.
+#[derive(Debug, Clone)]
+pub struct TransactionOptions {
+    isolation_level: Option<IsolationLevel>,
+    max_attempts: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+}
+
+impl Default for TransactionOptions {
+    fn default() -> Self {
+        Self {
+            isolation_level: None,
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_secs(1),
+        }
+    }
+}
+
+impl TransactionOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Begin the transaction with the isolation level. See [`Dialect::begin_statement`].
+    ///
+    /// The isolation level is ignored when the database cannot choose it, e.g. SQLite transactions are always serializable.
+    pub fn isolation_level(self, isolation_level: IsolationLevel) -> Self {
+        Self {
+            isolation_level: Some(isolation_level),
+            ..self
+        }
+    }
+
+    /// The number of times a transaction is attempted, including the first attempt. Use `1` to disable retries.
+    pub fn max_attempts(self, max_attempts: u32) -> Self {
+        Self {
+            max_attempts: max_attempts.max(1),
+            ..self
+        }
+    }
+
+    /// The delay before the first retry, which is doubled on each subsequent retry up to `max`
+    pub fn backoff(self, initial: Duration, max: Duration) -> Self {
+        Self {
+            initial_backoff: initial,
+            max_backoff: max,
+            ..self
+        }
+    }
+
+    fn backoff_after(&self, attempt: u32) -> Duration {
+        let factor = 1u32.checked_shl(attempt - 1).unwrap_or(u32::MAX);
+        self.initial_backoff.saturating_mul(factor).min(self.max_backoff)
+    }
+}
+
+/// An error returned from a [`with_transaction`] closure
+pub trait TransactionError: From<sqlx::Error> {
+    /// The database error that caused this error, which decides whether the transaction is retried
+    fn as_db_error(&self) -> Option<&sqlx::Error>;
+}
+
+impl TransactionError for sqlx::Error {
+    fn as_db_error(&self) -> Option<&sqlx::Error> {
+        Some(self)
+    }
+}
+
+/// Whether the error is a serialization failure (SQLSTATE `40001`) or a deadlock (SQLSTATE `40P01`),
+/// so that the transaction can succeed when it is retried
+pub fn is_retryable(error: &sqlx::Error) -> bool {
+    match error {
+        sqlx::Error::Database(e) => matches!(e.code().as_deref(), Some("40001" | "40P01")),
+        _ => false,
+    }
+}
+
+/// A future returned from a [`with_transaction`] closure
+pub type TransactionFuture<'t, T, E> = Pin<Box<dyn Future<Output = Result<T, E>> + Send + 't>>;
+
+/// Run `f` in a transaction which is committed when `f` succeeds and rolled back when it fails.
+///
+/// The transaction begins with the isolation level of `options`.
+/// When it fails with an error that [`is_retryable`], the whole transaction is run again after a backoff,
+/// so `f` is called once per attempt and must not consume its captured values.
+///
+/// # Example
+///
+/// ```
+/// # use lazybe::db::{DbOps, IsolationLevel, TransactionOptions, with_transaction};
+/// # use lazybe::db::sqlite::SqliteDbCtx;
+/// # use lazybe::macros::Entity;
+/// # use sqlx::SqlitePool;
+/// #[derive(Debug, Clone, Entity)]
+/// #[lazybe(table = "book")]
+/// pub struct Book {
+///     #[lazybe(primary_key)]
+///     pub id: i32,
+///     pub title: String,
+/// }
+///
+/// async fn rename(
+///     pool: &SqlitePool,
+///     id: i32,
+///     title: String,
+/// ) -> Result<Option<Book>, sqlx::Error> {
+///     let options = TransactionOptions::new().isolation_level(IsolationLevel::Serializable);
+///     with_transaction(&SqliteDbCtx, pool, &options, |ctx, tx| {
+///         let input = UpdateBook {
+///             title: Some(title.clone()),
+///         };
+///         Box::pin(async move { ctx.update::<Book>(&mut *tx, id, input).await })
+///     })
+///     .await
+/// }
+/// ```
+pub async fn with_transaction<Ctx, Db, T, E, F>(
+    ctx: &Ctx,
+    pool: &Pool<Db>,
+    options: &TransactionOptions,
+    mut f: F,
+) -> Result<T, E>
+where
+    Ctx: DbCtx<Db>,
+    Db: Database,
+    E: TransactionError,
+    F: for<'t> FnMut(&'t Ctx, &'t mut Transaction<'static, Db>) -> TransactionFuture<'t, T, E>,
+{
+    let begin_statement = options.isolation_level.and_then(|level| {
+        let statement = ctx.query_builder().begin_statement(level);
+        if statement.is_none() {
+            tracing::debug!(
+                isolation_level = level.as_sql(),
+                "The isolation level is ignored as the database cannot choose it"
+            );
+        }
+        statement
+    });
+    let mut attempt = 1;
+    loop {
+        let result = async {
+            let mut tx = match &begin_statement {
+                Some(statement) => pool.begin_with(statement.clone()).await?,
+                None => pool.begin().await?,
+            };
+            // the transaction is rolled back when it is dropped on error
+            let value = f(ctx, &mut tx).await?;
+            tx.commit().await?;
+            Ok::<_, E>(value)
+        }
+        .await;
+        match result {
+            Err(e) if attempt < options.max_attempts && e.as_db_error().is_some_and(is_retryable) => {
+                let backoff = options.backoff_after(attempt);
+                tracing::warn!(
+                    attempt,
+                    ?backoff,
+                    "Retrying a database transaction: {}",
+                    e.as_db_error().unwrap()
+                );
+                tokio::time::sleep(backoff).await;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
 }
 
 /// Database operations
+///
+/// Each operation runs on a connection acquired from `conn`, which can be a `&Pool`, a `&mut PoolConnection`
+/// or a `&mut Transaction`. Operations that execute multiple statements use the same connection for all of them,
+/// but only run atomically when `conn` is a transaction.
 pub trait DbOps<Db>
 where
     Db: Database,
 {
-    fn get<T>(
+    fn get<'a, T>(
         &self,
-        tx: &mut Transaction<'_, Db>,
+        conn: impl Acquire<'a, Database = Db> + Send + 'a,
         id: <T as Entity>::Pk,
     ) -> impl Future<Output = Result<Option<T>, sqlx::Error>> + Send
     where
         T: GetEntity<Db>;
 
-    fn list<T>(
+    fn list<'a, T>(
         &self,
-        tx: &mut Transaction<'_, Db>,
+        conn: impl Acquire<'a, Database = Db> + Send + 'a,
         filter: Filter<T>,
         sort: Sort<T>,
         pagination: Option<PaginationInput>,
@@ -78,30 +996,85 @@ where
     where
         T: ListEntity<Db>;
 
-    fn create<T>(
+    /// Like [`DbOps::get`] but only read the given fields
+    fn get_partial<'a, T>(
+        &self,
+        conn: impl Acquire<'a, Database = Db> + Send + 'a,
+        id: <T as Entity>::Pk,
+        fields: Fields<T>,
+    ) -> impl Future<Output = Result<Option<Partial<T>>, sqlx::Error>> + Send
+    where
+        T: GetPartialEntity<Db>;
+
+    /// Like [`DbOps::list`] but only read the given fields
+    fn list_partial<'a, T>(
         &self,
-        tx: &mut Transaction<'_, Db>,
+        conn: impl Acquire<'a, Database = Db> + Send + 'a,
+        filter: Filter<T>,
+        sort: Sort<T>,
+        pagination: Option<PaginationInput>,
+        fields: Fields<T>,
+    ) -> impl Future<Output = Result<Page<Partial<T>>, sqlx::Error>> + Send
+    where
+        T: ListPartialEntity<Db>;
+
+    fn create<'a, T>(
+        &self,
+        conn: impl Acquire<'a, Database = Db> + Send + 'a,
         input: <T as Entity>::Create,
     ) -> impl Future<Output = Result<T, sqlx::Error>> + Send
     where
         T: CreateEntity<Db>;
 
-    fn update<T>(
+    fn update<'a, T>(
         &self,
-        tx: &mut Transaction<'_, Db>,
+        conn: impl Acquire<'a, Database = Db> + Send + 'a,
         id: <T as Entity>::Pk,
         input: <T as Entity>::Update,
     ) -> impl Future<Output = Result<Option<T>, sqlx::Error>> + Send
     where
         T: UpdateEntity<Db>;
 
-    fn delete<T>(
+    /// Like [`DbOps::create`] on behalf of an actor, see [`DbCtx::actor`]
+    fn create_as<'a, T>(
+        &self,
+        conn: impl Acquire<'a, Database = Db> + Send + 'a,
+        input: <T as Entity>::Create,
+        actor: impl Into<sea_query::Value>,
+    ) -> impl Future<Output = Result<T, sqlx::Error>> + Send
+    where
+        T: CreateEntity<Db>,
+        <T as Entity>::Create: Send;
+
+    /// Like [`DbOps::update`] on behalf of an actor, see [`DbCtx::actor`]
+    fn update_as<'a, T>(
+        &self,
+        conn: impl Acquire<'a, Database = Db> + Send + 'a,
+        id: <T as Entity>::Pk,
+        input: <T as Entity>::Update,
+        actor: impl Into<sea_query::Value>,
+    ) -> impl Future<Output = Result<Option<T>, sqlx::Error>> + Send
+    where
+        T: UpdateEntity<Db>,
+        <T as Entity>::Pk: Send,
+        <T as Entity>::Update: Send;
+
+    fn delete<'a, T>(
         &self,
-        tx: &mut Transaction<'_, Db>,
+        conn: impl Acquire<'a, Database = Db> + Send + 'a,
         id: <T as Entity>::Pk,
-    ) -> impl Future<Output = Result<(), sqlx::Error>> + Send
+    ) -> impl Future<Output = Result<bool, sqlx::Error>> + Send
     where
         T: DeleteEntity<Db>;
+
+    /// The history records of a `#[lazybe(history)]` entity in the order of the changes
+    fn get_history<'a, T>(
+        &self,
+        conn: impl Acquire<'a, Database = Db> + Send + 'a,
+        id: <T as Entity>::Pk,
+    ) -> impl Future<Output = Result<Vec<HistoryRecord>, sqlx::Error>> + Send
+    where
+        T: GetHistory<Db>;
 }
 
 impl<Ctx, Db> DbOps<Db> for Ctx
@@ -109,20 +1082,20 @@ where
     Ctx: DbCtx<Db> + Sync,
     Db: Database,
 {
-    fn get<T>(
+    fn get<'a, T>(
         &self,
-        tx: &mut Transaction<'_, Db>,
+        conn: impl Acquire<'a, Database = Db> + Send + 'a,
         id: <T as Entity>::Pk,
     ) -> impl Future<Output = Result<Option<T>, sqlx::Error>> + Send
     where
         T: GetEntity<Db>,
     {
-        <T as GetEntity<Db>>::get(self, tx, id)
+        <T as GetEntity<Db>>::get(self, conn, id)
     }
 
-    fn list<T>(
+    fn list<'a, T>(
         &self,
-        tx: &mut Transaction<'_, Db>,
+        conn: impl Acquire<'a, Database = Db> + Send + 'a,
         filter: Filter<T>,
         sort: Sort<T>,
         pagination: Option<PaginationInput>,
@@ -130,40 +1103,231 @@ where
     where
         T: ListEntity<Db>,
     {
-        <T as ListEntity<Db>>::list(self, tx, filter, sort, pagination)
+        <T as ListEntity<Db>>::list(self, conn, filter, sort, pagination)
+    }
+
+    fn get_partial<'a, T>(
+        &self,
+        conn: impl Acquire<'a, Database = Db> + Send + 'a,
+        id: <T as Entity>::Pk,
+        fields: Fields<T>,
+    ) -> impl Future<Output = Result<Option<Partial<T>>, sqlx::Error>> + Send
+    where
+        T: GetPartialEntity<Db>,
+    {
+        <T as GetPartialEntity<Db>>::get_partial(self, conn, id, fields)
     }
 
-    fn create<T>(
+    fn list_partial<'a, T>(
         &self,
-        tx: &mut Transaction<'_, Db>,
+        conn: impl Acquire<'a, Database = Db> + Send + 'a,
+        filter: Filter<T>,
+        sort: Sort<T>,
+        pagination: Option<PaginationInput>,
+        fields: Fields<T>,
+    ) -> impl Future<Output = Result<Page<Partial<T>>, sqlx::Error>> + Send
+    where
+        T: ListPartialEntity<Db>,
+    {
+        <T as ListPartialEntity<Db>>::list_partial(self, conn, filter, sort, pagination, fields)
+    }
+
+    fn create<'a, T>(
+        &self,
+        conn: impl Acquire<'a, Database = Db> + Send + 'a,
         input: <T as Entity>::Create,
     ) -> impl Future<Output = Result<T, sqlx::Error>> + Send
     where
         T: CreateEntity<Db>,
     {
-        <T as CreateEntity<Db>>::create(self, tx, input)
+        <T as CreateEntity<Db>>::create(self, conn, input)
     }
 
-    fn update<T>(
+    fn update<'a, T>(
         &self,
-        tx: &mut Transaction<'_, Db>,
+        conn: impl Acquire<'a, Database = Db> + Send + 'a,
         id: <T as Entity>::Pk,
         input: <T as Entity>::Update,
     ) -> impl Future<Output = Result<Option<T>, sqlx::Error>> + Send
     where
         T: UpdateEntity<Db>,
     {
-        <T as UpdateEntity<Db>>::update(self, tx, id, input)
+        <T as UpdateEntity<Db>>::update(self, conn, id, input)
     }
 
-    fn delete<T>(
+    fn create_as<'a, T>(
         &self,
-        tx: &mut Transaction<'_, Db>,
+        conn: impl Acquire<'a, Database = Db> + Send + 'a,
+        input: <T as Entity>::Create,
+        actor: impl Into<sea_query::Value>,
+    ) -> impl Future<Output = Result<T, sqlx::Error>> + Send
+    where
+        T: CreateEntity<Db>,
+        <T as Entity>::Create: Send,
+    {
+        let ctx = ActorDbCtx::new(self, Some(actor.into()));
+        async move {
+            // boxed since the future of a generic operation cannot be proven `Send` while it borrows the context
+            let op: Pin<Box<dyn Future<Output = _> + Send + '_>> =
+                Box::pin(<T as CreateEntity<Db>>::create(&ctx, conn, input));
+            op.await
+        }
+    }
+
+    fn update_as<'a, T>(
+        &self,
+        conn: impl Acquire<'a, Database = Db> + Send + 'a,
         id: <T as Entity>::Pk,
-    ) -> impl Future<Output = Result<(), sqlx::Error>> + Send
+        input: <T as Entity>::Update,
+        actor: impl Into<sea_query::Value>,
+    ) -> impl Future<Output = Result<Option<T>, sqlx::Error>> + Send
+    where
+        T: UpdateEntity<Db>,
+        <T as Entity>::Pk: Send,
+        <T as Entity>::Update: Send,
+    {
+        let ctx = ActorDbCtx::new(self, Some(actor.into()));
+        async move {
+            let op: Pin<Box<dyn Future<Output = _> + Send + '_>> =
+                Box::pin(<T as UpdateEntity<Db>>::update(&ctx, conn, id, input));
+            op.await
+        }
+    }
+
+    fn delete<'a, T>(
+        &self,
+        conn: impl Acquire<'a, Database = Db> + Send + 'a,
+        id: <T as Entity>::Pk,
+    ) -> impl Future<Output = Result<bool, sqlx::Error>> + Send
     where
         T: DeleteEntity<Db>,
     {
-        <T as DeleteEntity<Db>>::delete(self, tx, id)
+        <T as DeleteEntity<Db>>::delete(self, conn, id)
+    }
+
+    fn get_history<'a, T>(
+        &self,
+        conn: impl Acquire<'a, Database = Db> + Send + 'a,
+        id: <T as Entity>::Pk,
+    ) -> impl Future<Output = Result<Vec<HistoryRecord>, sqlx::Error>> + Send
+    where
+        T: GetHistory<Db>,
+    {
+        <T as GetHistory<Db>>::get_history(self, conn, id)
+    }
+}
+
+/// The SQL statements executed by [`DbOps`], rendered without a database connection.
+///
+/// The statements are rendered by the dialect of the context with the values inlined,
+/// exactly as they are sent to the database, which makes them suitable for snapshot tests.
+///
+/// # Example
+///
+/// ```
+/// # use lazybe::db::DbSql;
+/// # use lazybe::db::sqlite::SqliteDbCtx;
+/// # use lazybe::filter::Filter;
+/// # use lazybe::macros::Entity;
+/// # use lazybe::page::PaginationInput;
+/// # use lazybe::sort::Sort;
+/// #[derive(Entity)]
+/// #[lazybe(table = "book")]
+/// pub struct Book {
+///     #[lazybe(primary_key)]
+///     pub id: i32,
+///     pub title: String,
+/// }
+///
+/// let sql = SqliteDbCtx.list_sql::<Book>(
+///     Filter::all([BookFilter::title().eq("Dune".to_string())]),
+///     Sort::new([BookSort::id().desc()]),
+///     Some(PaginationInput {
+///         page: 1,
+///         limit: 10,
+///     }),
+/// );
+/// assert_eq!(
+///     sql,
+///     [
+///         r#"SELECT "book"."id", "book"."title" FROM "book" WHERE "book"."title" = 'Dune' ORDER BY "book"."id" DESC LIMIT 10 OFFSET 10"#,
+///         r#"SELECT COUNT(*) AS "count" FROM "book" WHERE "book"."title" = 'Dune'"#,
+///     ]
+/// );
+/// ```
+pub trait DbSql<Db> {
+    fn get_sql<T>(&self, id: <T as Entity>::Pk) -> Vec<String>
+    where
+        T: GetQuery;
+
+    /// The data statement followed by the count statement
+    fn list_sql<T>(&self, filter: Filter<T>, sort: Sort<T>, pagination: Option<PaginationInput>) -> Vec<String>
+    where
+        T: ListQuery;
+
+    /// Fails like the operation before sending a statement, e.g. when an entity with a tenant is created without one
+    fn create_sql<T>(&self, input: <T as Entity>::Create) -> Result<Vec<String>, sqlx::Error>
+    where
+        T: CreateQuery;
+
+    /// Fails like the operation before sending a statement, e.g. when an entity with a required actor is updated without one
+    fn update_sql<T>(&self, id: <T as Entity>::Pk, input: <T as Entity>::Update) -> Result<Vec<String>, sqlx::Error>
+    where
+        T: UpdateQuery + GetQuery;
+
+    fn delete_sql<T>(&self, id: <T as Entity>::Pk) -> Vec<String>
+    where
+        T: DeleteQuery;
+}
+
+impl<Ctx, Db> DbSql<Db> for Ctx
+where
+    Ctx: DbCtx<Db>,
+{
+    fn get_sql<T>(&self, id: <T as Entity>::Pk) -> Vec<String>
+    where
+        T: GetQuery,
+    {
+        let query = ops::get_statement::<T, _, _>(self, id);
+        vec![query.to_string(self.query_builder())]
+    }
+
+    fn list_sql<T>(&self, filter: Filter<T>, sort: Sort<T>, pagination: Option<PaginationInput>) -> Vec<String>
+    where
+        T: ListQuery,
+    {
+        let (data_query, count_query) = ops::list_statements(self, filter, sort, pagination.as_ref(), |_| {});
+        vec![
+            data_query.to_string(self.query_builder()),
+            count_query.to_string(self.query_builder()),
+        ]
+    }
+
+    fn create_sql<T>(&self, input: <T as Entity>::Create) -> Result<Vec<String>, sqlx::Error>
+    where
+        T: CreateQuery,
+    {
+        let (query, get_query) = ops::create_statements::<T, _, _>(self, input)?;
+        Ok(std::iter::once(query.to_string(self.query_builder()))
+            .chain(get_query.map(|q| q.to_string(self.query_builder())))
+            .collect())
+    }
+
+    fn update_sql<T>(&self, id: <T as Entity>::Pk, input: <T as Entity>::Update) -> Result<Vec<String>, sqlx::Error>
+    where
+        T: UpdateQuery + GetQuery,
+    {
+        let (query, get_query) = ops::update_statements::<T, _, _>(self, id, input)?;
+        Ok(std::iter::once(query.to_string(self.query_builder()))
+            .chain(get_query.map(|q| q.to_string(self.query_builder())))
+            .collect())
+    }
+
+    fn delete_sql<T>(&self, id: <T as Entity>::Pk) -> Vec<String>
+    where
+        T: DeleteQuery,
+    {
+        let query = ops::delete_statement::<T, _, _>(self, id);
+        vec![query.to_string(self.query_builder())]
     }
 }
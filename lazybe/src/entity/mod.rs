@@ -2,6 +2,7 @@ use std::fmt::Debug;
 
 /// Utilities and traits for performing CRUD operations on an Entity
 pub mod ops;
+mod trace;
 
 /// A representation of an identifiable resource in the application domain.
 pub trait Entity: Sized {
@@ -24,4 +25,25 @@ pub trait Entity: Sized {
 /// A special type of entity that is directly mapped to the database table.
 pub trait TableEntity: Entity {
     type Row;
+
+    /// The table where the entity is stored
+    fn table() -> sea_query::DynIden;
+
+    /// The column of the `#[lazybe(tenant)]` field, which scopes the records to [`DbCtx::tenant`](crate::db::DbCtx::tenant).
+    /// Defaults to `None` for entities shared by all tenants.
+    fn tenant_column() -> Option<sea_query::DynIden> {
+        None
+    }
+
+    /// The history of a `#[lazybe(history)]` entity, whose changes are recorded by the create, update and delete operations.
+    /// Defaults to `None` for entities without history.
+    fn history() -> Option<crate::history::History<Self>> {
+        None
+    }
+
+    /// The outbox of a `#[lazybe(outbox)]` entity, whose changes are published as events by the create, update and delete operations.
+    /// Defaults to `None` for entities without events.
+    fn outbox() -> Option<crate::outbox::Outbox<Self>> {
+        None
+    }
 }
@@ -1,13 +1,22 @@
 use std::ops::DerefMut;
 
 use projection::CountResult;
-use sea_query::{Alias, Asterisk, Expr};
-use sqlx::{Database, Executor, FromRow, IntoArguments, Transaction};
+use sea_query::{
+    Alias, Asterisk, DeleteStatement, Expr, InsertStatement, LockType, QueryBuilder, SelectStatement, SimpleExpr,
+    TableRef, UpdateStatement,
+};
+use sqlx::{Acquire, Connection, Database, Executor, FromRow, IntoArguments};
 
-use crate::db::DbCtx;
+use super::trace::OpTrace;
+use crate::db::{DbCtx, Dialect, RowsAffected};
+use crate::fields::{Fields, FromPartialRow, Partial};
 use crate::filter::Filter;
+use crate::history::row::HistoryRow;
+use crate::history::{self, HistoryEntity, HistoryOperation, HistoryRecord, HistoryWriter};
+use crate::outbox::{EntityEvent, OutboxWriter};
 use crate::page::{Page, PaginationInput};
-use crate::query::{CreateQuery, DeleteQuery, GetQuery, ListQuery, UpdateQuery};
+use crate::query::{CreateQuery, DeleteQuery, GetQuery, ListQuery, Stamp, UpdateQuery};
+use crate::search::Search;
 use crate::sort::Sort;
 use crate::{Entity, TableEntity};
 
@@ -15,9 +24,9 @@ pub trait GetEntity<Db>: Entity
 where
     Db: Database,
 {
-    fn get<Ctx>(
+    fn get<'a, Ctx>(
         ctx: &Ctx,
-        tx: &mut Transaction<'_, Db>,
+        conn: impl Acquire<'a, Database = Db> + Send + 'a,
         id: Self::Pk,
     ) -> impl Future<Output = Result<Option<Self>, sqlx::Error>> + Send
     where
@@ -28,9 +37,9 @@ pub trait ListEntity<Db>: Entity
 where
     Db: Database,
 {
-    fn list<Ctx>(
+    fn list<'a, Ctx>(
         ctx: &Ctx,
-        tx: &mut Transaction<'_, Db>,
+        conn: impl Acquire<'a, Database = Db> + Send + 'a,
         filter: Filter<Self>,
         sort: Sort<Self>,
         pagination: Option<PaginationInput>,
@@ -39,13 +48,43 @@ where
         Ctx: DbCtx<Db> + Sync;
 }
 
+pub trait GetPartialEntity<Db>: Entity
+where
+    Db: Database,
+{
+    fn get_partial<'a, Ctx>(
+        ctx: &Ctx,
+        conn: impl Acquire<'a, Database = Db> + Send + 'a,
+        id: Self::Pk,
+        fields: Fields<Self>,
+    ) -> impl Future<Output = Result<Option<Partial<Self>>, sqlx::Error>> + Send
+    where
+        Ctx: DbCtx<Db> + Sync;
+}
+
+pub trait ListPartialEntity<Db>: Entity
+where
+    Db: Database,
+{
+    fn list_partial<'a, Ctx>(
+        ctx: &Ctx,
+        conn: impl Acquire<'a, Database = Db> + Send + 'a,
+        filter: Filter<Self>,
+        sort: Sort<Self>,
+        pagination: Option<PaginationInput>,
+        fields: Fields<Self>,
+    ) -> impl Future<Output = Result<Page<Partial<Self>>, sqlx::Error>> + Send
+    where
+        Ctx: DbCtx<Db> + Sync;
+}
+
 pub trait CreateEntity<Db>: Entity
 where
     Db: Database,
 {
-    fn create<Ctx>(
+    fn create<'a, Ctx>(
         ctx: &Ctx,
-        tx: &mut Transaction<'_, Db>,
+        conn: impl Acquire<'a, Database = Db> + Send + 'a,
         input: Self::Create,
     ) -> impl Future<Output = Result<Self, sqlx::Error>> + Send
     where
@@ -56,9 +95,9 @@ pub trait UpdateEntity<Db>: Entity
 where
     Db: Database,
 {
-    fn update<Ctx>(
+    fn update<'a, Ctx>(
         ctx: &Ctx,
-        tx: &mut Transaction<'_, Db>,
+        conn: impl Acquire<'a, Database = Db> + Send + 'a,
         id: Self::Pk,
         input: Self::Update,
     ) -> impl Future<Output = Result<Option<Self>, sqlx::Error>> + Send
@@ -70,11 +109,26 @@ pub trait DeleteEntity<Db>: Entity
 where
     Db: Database,
 {
-    fn delete<Ctx>(
+    /// Delete the record and return whether it was found
+    fn delete<'a, Ctx>(
         ctx: &Ctx,
-        tx: &mut Transaction<'_, Db>,
+        conn: impl Acquire<'a, Database = Db> + Send + 'a,
         id: Self::Pk,
-    ) -> impl Future<Output = Result<(), sqlx::Error>> + Send
+    ) -> impl Future<Output = Result<bool, sqlx::Error>> + Send
+    where
+        Ctx: DbCtx<Db> + Sync;
+}
+
+pub trait GetHistory<Db>: Entity
+where
+    Db: Database,
+{
+    /// The history records of an entity in the order of the changes, see [`HistoryEntity`]
+    fn get_history<'a, Ctx>(
+        ctx: &Ctx,
+        conn: impl Acquire<'a, Database = Db> + Send + 'a,
+        id: Self::Pk,
+    ) -> impl Future<Output = Result<Vec<HistoryRecord>, sqlx::Error>> + Send
     where
         Ctx: DbCtx<Db> + Sync;
 }
@@ -87,20 +141,23 @@ where
     for<'c> &'c mut <Db as Database>::Connection: Executor<'c, Database = Db>,
     <T as TableEntity>::Row: Into<T> + for<'r> FromRow<'r, Db::Row> + Send + Unpin,
 {
-    fn get<Ctx>(
+    fn get<'a, Ctx>(
         ctx: &Ctx,
-        tx: &mut Transaction<'_, Db>,
+        conn: impl Acquire<'a, Database = Db> + Send + 'a,
         id: Self::Pk,
     ) -> impl Future<Output = Result<Option<Self>, sqlx::Error>> + Send
     where
         Ctx: DbCtx<Db> + Sync,
     {
-        let query = <T as GetQuery>::get_query(id).to_string(ctx.query_builder());
-        async move {
+        let mut trace = OpTrace::new::<T>("get", ctx.slow_query_threshold());
+        let query = trace.render(&get_statement::<T, _, _>(ctx, id), ctx.query_builder());
+        let op = async move {
+            let mut conn = conn.acquire().await?;
             let maybe_entity: Option<<T as TableEntity>::Row> =
-                sqlx::query_as(&query).fetch_optional(tx.deref_mut()).await?;
+                sqlx::query_as(&query).fetch_optional(conn.deref_mut()).await?;
             Ok(maybe_entity.map(|i| i.into()))
-        }
+        };
+        trace.run(op, |entity| Some(entity.iter().count()))
     }
 }
 
@@ -113,9 +170,9 @@ where
     <T as TableEntity>::Row: Into<T> + for<'r> FromRow<'r, Db::Row> + Send + Unpin,
     CountResult: for<'r> FromRow<'r, Db::Row> + Send + Unpin,
 {
-    fn list<Ctx>(
+    fn list<'a, Ctx>(
         ctx: &Ctx,
-        tx: &mut Transaction<'_, Db>,
+        conn: impl Acquire<'a, Database = Db> + Send + 'a,
         filter: Filter<Self>,
         sort: Sort<Self>,
         pagination: Option<PaginationInput>,
@@ -123,124 +180,610 @@ where
     where
         Ctx: DbCtx<Db> + Sync,
     {
-        let mut base_query = <T as ListQuery>::list_query(filter);
-
-        // count
-        let count_query = {
-            let mut stm = base_query.clone();
-            stm.clear_selects()
-                .clear_order_by()
-                .expr_as(Expr::col(Asterisk).count(), Alias::new("count"));
-            stm.to_owned().to_string(ctx.query_builder())
+        let mut trace = OpTrace::new::<T>("list", ctx.slow_query_threshold());
+        let (data_query, count_query) = list_statements(ctx, filter, sort, pagination.as_ref(), |_| {});
+        let count_query = trace.render(&count_query, ctx.query_builder());
+        let data_query = trace.render(&data_query, ctx.query_builder());
+        let op = async move {
+            let mut conn = conn.acquire().await?;
+            let data_result: Vec<<T as TableEntity>::Row> =
+                sqlx::query_as(&data_query).fetch_all(conn.deref_mut()).await?;
+            let count_result: CountResult = sqlx::query_as(&count_query).fetch_one(conn.deref_mut()).await?;
+            Ok(into_page(
+                pagination,
+                count_result,
+                data_result.into_iter().map(|i| i.into()).collect(),
+            ))
         };
+        trace.run(op, |page| Some(page.data.len()))
+    }
+}
 
-        // data
-        let data_query = {
-            // sort
-            let order_by = sort.into_order_exprs();
-            base_query = base_query.order_by_columns(order_by).to_owned();
+impl<T, Db> GetPartialEntity<Db> for T
+where
+    Db: Database,
+    T: GetQuery + FromPartialRow<Db::Row>,
+    for<'q> <Db as Database>::Arguments<'q>: IntoArguments<'q, Db>,
+    for<'c> &'c mut <Db as Database>::Connection: Executor<'c, Database = Db>,
+{
+    fn get_partial<'a, Ctx>(
+        ctx: &Ctx,
+        conn: impl Acquire<'a, Database = Db> + Send + 'a,
+        id: Self::Pk,
+        fields: Fields<Self>,
+    ) -> impl Future<Output = Result<Option<Partial<Self>>, sqlx::Error>> + Send
+    where
+        Ctx: DbCtx<Db> + Sync,
+    {
+        let mut trace = OpTrace::new::<T>("get_partial", ctx.slow_query_threshold());
+        let mut query = get_statement::<T, _, _>(ctx, id);
+        fields.apply(&mut query);
+        let query = trace.render(&query, ctx.query_builder());
+        let op = async move {
+            let mut conn = conn.acquire().await?;
+            let maybe_row = sqlx::query(&query).fetch_optional(conn.deref_mut()).await?;
+            maybe_row
+                .map(|row| <T as FromPartialRow<Db::Row>>::from_partial_row(&row, &fields))
+                .transpose()
+        };
+        trace.run(op, |entity| Some(entity.iter().count()))
+    }
+}
 
-            // filter
-            if let Some(p) = &pagination {
-                base_query = base_query.limit(p.limit.into()).offset(p.offset().into()).to_owned();
-            }
-            base_query.to_string(ctx.query_builder())
+impl<T, Db> ListPartialEntity<Db> for T
+where
+    Db: Database,
+    T: ListQuery + FromPartialRow<Db::Row>,
+    for<'q> <Db as Database>::Arguments<'q>: IntoArguments<'q, Db>,
+    for<'c> &'c mut <Db as Database>::Connection: Executor<'c, Database = Db>,
+    CountResult: for<'r> FromRow<'r, Db::Row> + Send + Unpin,
+{
+    fn list_partial<'a, Ctx>(
+        ctx: &Ctx,
+        conn: impl Acquire<'a, Database = Db> + Send + 'a,
+        filter: Filter<Self>,
+        sort: Sort<Self>,
+        pagination: Option<PaginationInput>,
+        fields: Fields<Self>,
+    ) -> impl Future<Output = Result<Page<Partial<Self>>, sqlx::Error>> + Send
+    where
+        Ctx: DbCtx<Db> + Sync,
+    {
+        let mut trace = OpTrace::new::<T>("list_partial", ctx.slow_query_threshold());
+        let (data_query, count_query) = list_statements(ctx, filter, sort, pagination.as_ref(), |q| fields.apply(q));
+        let count_query = trace.render(&count_query, ctx.query_builder());
+        let data_query = trace.render(&data_query, ctx.query_builder());
+        let op = async move {
+            let mut conn = conn.acquire().await?;
+            let rows = sqlx::query(&data_query).fetch_all(conn.deref_mut()).await?;
+            let count_result: CountResult = sqlx::query_as(&count_query).fetch_one(conn.deref_mut()).await?;
+            let data = rows
+                .iter()
+                .map(|row| <T as FromPartialRow<Db::Row>>::from_partial_row(row, &fields))
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(into_page(pagination, count_result, data))
         };
+        trace.run(op, |page| Some(page.data.len()))
+    }
+}
 
-        async move {
-            let data_result: Vec<<T as TableEntity>::Row> =
-                sqlx::query_as(&data_query).fetch_all(tx.deref_mut()).await?;
+/// Build the statement of a get operation
+pub(crate) fn get_statement<T, Ctx, Db>(ctx: &Ctx, id: T::Pk) -> SelectStatement
+where
+    T: GetQuery,
+    Ctx: DbCtx<Db>,
+{
+    let mut query = <T as GetQuery>::get_query(id);
+    query.resolve_table::<T, _, _>(ctx);
+    query.scope_tenant::<T, _, _>(ctx);
+    query
+}
 
-            let count_result: CountResult = sqlx::query_as(&count_query).fetch_one(tx.deref_mut()).await?;
+/// Build the statement reading a record before it is changed, which locks the record until the change is committed
+fn locked_get_statement<T, Ctx, Db>(ctx: &Ctx, id: T::Pk) -> SelectStatement
+where
+    T: GetQuery,
+    Ctx: DbCtx<Db>,
+{
+    let mut query = get_statement::<T, _, _>(ctx, id);
+    query.lock(LockType::Update);
+    query
+}
 
-            let total_records: u32 = count_result.count.try_into().expect("record count does not fit in u32");
-            let mut page: u32 = 0;
-            let mut page_size: u32 = total_records;
-            if let Some(p) = pagination {
-                page = p.page;
-                page_size = p.limit;
-            }
+/// Build the data query and the count query of a list operation.
+/// The selected columns of the data query can be adjusted by `select`.
+pub(crate) fn list_statements<T, Ctx, Db>(
+    ctx: &Ctx,
+    filter: Filter<T>,
+    sort: Sort<T>,
+    pagination: Option<&PaginationInput>,
+    select: impl FnOnce(&mut SelectStatement),
+) -> (SelectStatement, SelectStatement)
+where
+    T: ListQuery,
+    Ctx: DbCtx<Db>,
+{
+    let dialect = ctx.query_builder();
+    let resolve_search = |search: Search| search.resolve_fts_table(|table| ctx.table_ref(table));
+    let filter = filter.render_searches_with(&dialect, &resolve_search);
+    let filter_joins = filter.joins().to_vec();
+    let mut base_query = <T as ListQuery>::list_query(filter);
+    base_query.resolve_table::<T, _, _>(ctx);
+    base_query.scope_tenant::<T, _, _>(ctx);
+
+    // joins
+    for join in &filter_joins {
+        join.apply_in(&mut base_query, ctx);
+    }
 
-            let result = Page {
-                page,
-                page_size,
-                total_records,
-                data: data_result.into_iter().map(|i| i.into()).collect(),
+    // count
+    let count_query = {
+        let mut stm = base_query.clone();
+        stm.clear_selects()
+            .clear_order_by()
+            .expr_as(Expr::col(Asterisk).count(), Alias::new("count"));
+        stm
+    };
+
+    // data
+    let data_query = {
+        // fields
+        select(&mut base_query);
+
+        // sort
+        for join in sort.joins().iter().filter(|j| !filter_joins.contains(j)) {
+            join.apply_in(&mut base_query, ctx);
+        }
+        for (expr, order, nulls) in sort.into_order_exprs_with(&dialect, &resolve_search) {
+            match nulls {
+                Some(nulls) => base_query.order_by_expr_with_nulls(expr, order, nulls),
+                None => base_query.order_by_expr(expr, order),
             };
-            Ok(result)
         }
+
+        // filter
+        if let Some(p) = pagination {
+            base_query = base_query.limit(p.limit.into()).offset(p.offset().into()).to_owned();
+        }
+        base_query
+    };
+
+    (data_query, count_query)
+}
+
+fn into_page<U>(pagination: Option<PaginationInput>, count_result: CountResult, data: Vec<U>) -> Page<U> {
+    let total_records: u32 = count_result.count.try_into().expect("record count does not fit in u32");
+    let mut page: u32 = 0;
+    let mut page_size: u32 = total_records;
+    if let Some(p) = pagination {
+        page = p.page;
+        page_size = p.limit;
+    }
+    Page {
+        page,
+        page_size,
+        total_records,
+        data,
     }
 }
 
 impl<T, Db> CreateEntity<Db> for T
 where
     Db: Database,
-    T: CreateQuery,
+    T: CreateQuery + Send,
     for<'q> <Db as Database>::Arguments<'q>: IntoArguments<'q, Db>,
     for<'c> &'c mut <Db as Database>::Connection: Executor<'c, Database = Db>,
     <T as TableEntity>::Row: Into<T> + for<'r> FromRow<'r, Db::Row> + Send + Unpin,
 {
-    fn create<Ctx>(
+    fn create<'a, Ctx>(
         ctx: &Ctx,
-        tx: &mut sqlx::Transaction<'_, Db>,
+        conn: impl Acquire<'a, Database = Db> + Send + 'a,
         input: Self::Create,
     ) -> impl Future<Output = Result<Self, sqlx::Error>> + Send
     where
         Ctx: DbCtx<Db> + Sync,
     {
-        let query = <T as CreateQuery>::create_query(input).to_string(ctx.query_builder());
-        async move {
-            let entity: <T as TableEntity>::Row = sqlx::query_as(&query).fetch_one(tx.deref_mut()).await?;
-            Ok(entity.into())
+        let mut trace = OpTrace::new::<T>("create", ctx.slow_query_threshold());
+        let statements = create_statements::<T, _, _>(ctx, input).map(|(query, get_query)| {
+            let query = trace.render(&query, ctx.query_builder());
+            let get_query = get_query.map(|q| trace.render(&q, ctx.query_builder()));
+            (query, get_query)
+        });
+        let records = ChangeRecords::<T>::new(ctx);
+        let op = async move {
+            let (query, get_query) = statements?;
+            let mut conn = conn.acquire().await?;
+            let Some(records) = records else {
+                return Ok(fetch_created::<T, Db>(conn.deref_mut(), &query, get_query.as_deref())
+                    .await?
+                    .into());
+            };
+            // the change and its records are written in the same transaction
+            let mut tx = conn.begin().await?;
+            let entity: T = fetch_created::<T, Db>(tx.deref_mut(), &query, get_query.as_deref())
+                .await?
+                .into();
+            for record in records.statements::<Ctx::Qb>(HistoryOperation::Create, None, Some(&entity))? {
+                sqlx::query(&record).execute(tx.deref_mut()).await?;
+            }
+            tx.commit().await?;
+            Ok(entity)
+        };
+        trace.run(op, |_| Some(1))
+    }
+}
+
+/// Run the statements of a create operation and read the created record
+async fn fetch_created<T, Db>(
+    conn: &mut Db::Connection,
+    query: &str,
+    get_query: Option<&str>,
+) -> Result<<T as TableEntity>::Row, sqlx::Error>
+where
+    Db: Database,
+    T: TableEntity,
+    for<'q> <Db as Database>::Arguments<'q>: IntoArguments<'q, Db>,
+    for<'c> &'c mut <Db as Database>::Connection: Executor<'c, Database = Db>,
+    <T as TableEntity>::Row: for<'r> FromRow<'r, Db::Row> + Send + Unpin,
+{
+    match get_query {
+        Some(get_query) => {
+            sqlx::query(query).execute(&mut *conn).await?;
+            sqlx::query_as(get_query).fetch_one(conn).await
         }
+        None => sqlx::query_as(query).fetch_one(conn).await,
     }
 }
 
 impl<T, Db> UpdateEntity<Db> for T
 where
     Db: Database,
-    T: UpdateQuery,
+    T: UpdateQuery + GetQuery + Send,
     for<'q> <Db as Database>::Arguments<'q>: IntoArguments<'q, Db>,
     for<'c> &'c mut <Db as Database>::Connection: Executor<'c, Database = Db>,
     <T as TableEntity>::Row: Into<T> + for<'r> FromRow<'r, Db::Row> + Send + Unpin,
 {
-    fn update<Ctx>(
+    fn update<'a, Ctx>(
         ctx: &Ctx,
-        tx: &mut Transaction<'_, Db>,
+        conn: impl Acquire<'a, Database = Db> + Send + 'a,
         id: Self::Pk,
         input: Self::Update,
     ) -> impl Future<Output = Result<Option<Self>, sqlx::Error>> + Send
     where
         Ctx: DbCtx<Db> + Sync,
     {
-        let query = <T as UpdateQuery>::update_query(id, input).to_string(ctx.query_builder());
-        async move {
-            let maybe_entity: Option<<T as TableEntity>::Row> =
-                sqlx::query_as(&query).fetch_optional(tx.deref_mut()).await?;
-            Ok(maybe_entity.map(|i| i.into()))
+        let mut trace = OpTrace::new::<T>("update", ctx.slow_query_threshold());
+        let records = ChangeRecords::<T>::new(ctx);
+        let before_query = records
+            .as_ref()
+            .map(|_| trace.render(&locked_get_statement::<T, _, _>(ctx, id.clone()), ctx.query_builder()));
+        let statements = update_statements::<T, _, _>(ctx, id, input).map(|(query, get_query)| {
+            let query = trace.render(&query, ctx.query_builder());
+            let get_query = get_query.map(|q| trace.render(&q, ctx.query_builder()));
+            (query, get_query)
+        });
+        let op = async move {
+            let (query, get_query) = statements?;
+            let mut conn = conn.acquire().await?;
+            let Some((records, before_query)) = records.zip(before_query) else {
+                let maybe_entity = fetch_updated::<T, Db>(conn.deref_mut(), &query, get_query.as_deref()).await?;
+                return Ok(maybe_entity.map(|i| i.into()));
+            };
+            // the change and its records are written in the same transaction
+            let mut tx = conn.begin().await?;
+            let maybe_before: Option<<T as TableEntity>::Row> =
+                sqlx::query_as(&before_query).fetch_optional(tx.deref_mut()).await?;
+            let Some(before) = maybe_before.map(|i| i.into()) else {
+                return Ok(None);
+            };
+            let maybe_after: Option<T> = fetch_updated::<T, Db>(tx.deref_mut(), &query, get_query.as_deref())
+                .await?
+                .map(|i| i.into());
+            if let Some(after) = &maybe_after {
+                for record in records.statements::<Ctx::Qb>(HistoryOperation::Update, Some(&before), Some(after))? {
+                    sqlx::query(&record).execute(tx.deref_mut()).await?;
+                }
+            }
+            tx.commit().await?;
+            Ok(maybe_after)
+        };
+        trace.run(op, |entity| Some(entity.iter().count()))
+    }
+}
+
+/// Run the statements of an update operation and read the updated record
+async fn fetch_updated<T, Db>(
+    conn: &mut Db::Connection,
+    query: &str,
+    get_query: Option<&str>,
+) -> Result<Option<<T as TableEntity>::Row>, sqlx::Error>
+where
+    Db: Database,
+    T: TableEntity,
+    for<'q> <Db as Database>::Arguments<'q>: IntoArguments<'q, Db>,
+    for<'c> &'c mut <Db as Database>::Connection: Executor<'c, Database = Db>,
+    <T as TableEntity>::Row: for<'r> FromRow<'r, Db::Row> + Send + Unpin,
+{
+    match get_query {
+        Some(get_query) => {
+            sqlx::query(query).execute(&mut *conn).await?;
+            sqlx::query_as(get_query).fetch_optional(conn).await
         }
+        None => sqlx::query_as(query).fetch_optional(conn).await,
     }
 }
 
 impl<T, Db> DeleteEntity<Db> for T
 where
     Db: Database,
-    T: DeleteQuery,
+    Db::QueryResult: RowsAffected,
+    T: DeleteQuery + GetQuery + Send,
     for<'q> <Db as Database>::Arguments<'q>: IntoArguments<'q, Db>,
     for<'c> &'c mut <Db as Database>::Connection: Executor<'c, Database = Db>,
+    <T as TableEntity>::Row: Into<T> + for<'r> FromRow<'r, Db::Row> + Send + Unpin,
 {
-    fn delete<Ctx>(
+    fn delete<'a, Ctx>(
         ctx: &Ctx,
-        tx: &mut Transaction<'_, Db>,
+        conn: impl Acquire<'a, Database = Db> + Send + 'a,
         id: Self::Pk,
-    ) -> impl Future<Output = Result<(), sqlx::Error>> + Send
+    ) -> impl Future<Output = Result<bool, sqlx::Error>> + Send
     where
         Ctx: DbCtx<Db> + Sync,
     {
-        let query = <T as DeleteQuery>::delete_query(id).to_string(ctx.query_builder());
+        let mut trace = OpTrace::new::<T>("delete", ctx.slow_query_threshold());
+        let records = ChangeRecords::<T>::new(ctx);
+        let before_query = records
+            .as_ref()
+            .map(|_| trace.render(&locked_get_statement::<T, _, _>(ctx, id.clone()), ctx.query_builder()));
+        let query = trace.render(&delete_statement::<T, _, _>(ctx, id), ctx.query_builder());
+        let op = async move {
+            let mut conn = conn.acquire().await?;
+            let Some((records, before_query)) = records.zip(before_query) else {
+                let result = sqlx::query(&query).execute(conn.deref_mut()).await?;
+                return Ok(result.rows_affected());
+            };
+            // the change and its records are written in the same transaction
+            let mut tx = conn.begin().await?;
+            let maybe_before: Option<<T as TableEntity>::Row> =
+                sqlx::query_as(&before_query).fetch_optional(tx.deref_mut()).await?;
+            let Some(before) = maybe_before.map(|i| i.into()) else {
+                return Ok(0);
+            };
+            let result = sqlx::query(&query).execute(tx.deref_mut()).await?;
+            for record in records.statements::<Ctx::Qb>(HistoryOperation::Delete, Some(&before), None)? {
+                sqlx::query(&record).execute(tx.deref_mut()).await?;
+            }
+            tx.commit().await?;
+            Ok(result.rows_affected())
+        };
         async move {
-            sqlx::query(&query).execute(tx.deref_mut()).await?;
-            Ok(())
+            let rows = trace.run(op, |rows| usize::try_from(*rows).ok()).await?;
+            Ok(rows > 0)
+        }
+    }
+}
+
+impl<T, Db> GetHistory<Db> for T
+where
+    Db: Database,
+    T: HistoryEntity,
+    <T as Entity>::Pk: Into<sea_query::Value>,
+    for<'q> <Db as Database>::Arguments<'q>: IntoArguments<'q, Db>,
+    for<'c> &'c mut <Db as Database>::Connection: Executor<'c, Database = Db>,
+    HistoryRow: for<'r> FromRow<'r, Db::Row> + Send + Unpin,
+{
+    fn get_history<'a, Ctx>(
+        ctx: &Ctx,
+        conn: impl Acquire<'a, Database = Db> + Send + 'a,
+        id: Self::Pk,
+    ) -> impl Future<Output = Result<Vec<HistoryRecord>, sqlx::Error>> + Send
+    where
+        Ctx: DbCtx<Db> + Sync,
+    {
+        let mut trace = OpTrace::new::<T>("get_history", ctx.slow_query_threshold());
+        let query = trace.render(&history::list_statement::<T, _, _>(ctx, id.into()), ctx.query_builder());
+        let op = async move {
+            let mut conn = conn.acquire().await?;
+            let rows: Vec<HistoryRow> = sqlx::query_as(&query).fetch_all(conn.deref_mut()).await?;
+            rows.into_iter()
+                .map(HistoryRecord::try_from)
+                .collect::<Result<Vec<_>, _>>()
+        };
+        trace.run(op, |records| Some(records.len()))
+    }
+}
+
+/// The records written in the same transaction as a change of `T`,
+/// i.e. its history record and its event in the outbox
+struct ChangeRecords<T> {
+    history: Option<HistoryWriter<T>>,
+    outbox: Option<OutboxWriter<T>>,
+}
+
+impl<T: TableEntity> ChangeRecords<T> {
+    /// The records of the changes made with a context, or `None` when no records are written for `T`
+    fn new<Ctx: DbCtx<Db>, Db>(ctx: &Ctx) -> Option<Self> {
+        let history = HistoryWriter::new(ctx);
+        let outbox = OutboxWriter::new(ctx);
+        (history.is_some() || outbox.is_some()).then_some(Self { history, outbox })
+    }
+
+    /// The statements recording a change from `before` to `after`, rendered by the query builder `Qb`.
+    /// Fails when a record cannot be built, which fails the change.
+    fn statements<Qb: QueryBuilder + Default>(
+        &self,
+        operation: HistoryOperation,
+        before: Option<&T>,
+        after: Option<&T>,
+    ) -> Result<Vec<String>, sqlx::Error> {
+        let history = self
+            .history
+            .as_ref()
+            .map(|history| history.insert_statement(operation, before, after))
+            .transpose()?;
+        let event = match operation {
+            HistoryOperation::Create => after.map(EntityEvent::Created),
+            HistoryOperation::Update => after.map(EntityEvent::Updated),
+            HistoryOperation::Delete => before.map(EntityEvent::Deleted),
+        };
+        let outbox = self
+            .outbox
+            .as_ref()
+            .zip(event)
+            .map(|(outbox, event)| outbox.insert_statement(event))
+            .transpose()?;
+        Ok(history
+            .into_iter()
+            .chain(outbox)
+            .map(|stmt| stmt.to_string(Qb::default()))
+            .collect())
+    }
+}
+
+/// The values of the context that are written to the records
+fn stamp<Ctx: DbCtx<Db>, Db>(ctx: &Ctx) -> Stamp {
+    Stamp {
+        tenant: ctx.tenant(),
+        actor: ctx.actor(),
+    }
+}
+
+/// Build the statements of a create operation.
+/// The created record is read back with a second statement when the database does not support `RETURNING`.
+/// Fails if the entity has a tenant column but the context has no tenant,
+/// or a `created_by` or `updated_by` field that is not optional but the context has no actor.
+pub(crate) fn create_statements<T, Ctx, Db>(
+    ctx: &Ctx,
+    input: T::Create,
+) -> Result<(InsertStatement, Option<SelectStatement>), sqlx::Error>
+where
+    T: CreateQuery,
+    Ctx: DbCtx<Db>,
+{
+    let stamp = stamp(ctx);
+    if ctx.query_builder().supports_returning() {
+        let mut query = <T as CreateQuery>::create_query(input, &stamp)?;
+        query.resolve_table::<T, _, _>(ctx);
+        Ok((query, None))
+    } else {
+        let (mut query, mut get_query) = <T as CreateQuery>::create_then_get_query(input, &stamp)?;
+        query.resolve_table::<T, _, _>(ctx);
+        get_query.resolve_table::<T, _, _>(ctx);
+        get_query.scope_tenant::<T, _, _>(ctx);
+        Ok((query, Some(get_query)))
+    }
+}
+
+/// Build the statements of an update operation.
+/// The updated record is read back with a second statement when the database does not support `RETURNING`.
+/// Fails if the entity has an `updated_by` field that is not optional but the context has no actor.
+pub(crate) fn update_statements<T, Ctx, Db>(
+    ctx: &Ctx,
+    id: T::Pk,
+    input: T::Update,
+) -> Result<(UpdateStatement, Option<SelectStatement>), sqlx::Error>
+where
+    T: UpdateQuery + GetQuery,
+    Ctx: DbCtx<Db>,
+{
+    let stamp = stamp(ctx);
+    if ctx.query_builder().supports_returning() {
+        let mut query = <T as UpdateQuery>::update_query(id, input, &stamp)?;
+        query.resolve_table::<T, _, _>(ctx);
+        query.scope_tenant::<T, _, _>(ctx);
+        Ok((query, None))
+    } else {
+        let mut query = <T as UpdateQuery>::update_query(id.clone(), input, &stamp)?;
+        query.resolve_table::<T, _, _>(ctx);
+        query.scope_tenant::<T, _, _>(ctx);
+        Ok((query, Some(get_statement::<T, _, _>(ctx, id))))
+    }
+}
+
+/// Build the statement of a delete operation
+pub(crate) fn delete_statement<T, Ctx, Db>(ctx: &Ctx, id: T::Pk) -> DeleteStatement
+where
+    T: DeleteQuery,
+    Ctx: DbCtx<Db>,
+{
+    let mut query = <T as DeleteQuery>::delete_query(id);
+    query.resolve_table::<T, _, _>(ctx);
+    query.scope_tenant::<T, _, _>(ctx);
+    query
+}
+
+/// Replace the entity table of a statement with the table resolved by [`DbCtx::table_ref`]
+trait ResolveTable {
+    fn resolve_table<T: TableEntity, Ctx: DbCtx<Db>, Db>(&mut self, ctx: &Ctx);
+}
+
+impl ResolveTable for SelectStatement {
+    fn resolve_table<T: TableEntity, Ctx: DbCtx<Db>, Db>(&mut self, ctx: &Ctx) {
+        // the original table name is kept as an alias for the columns qualified by the entity table
+        let table = <T as TableEntity>::table();
+        let table_ref = ctx.table_ref(table.clone());
+        if table_ref != TableRef::Table(table.clone()) {
+            self.from_clear().from(table_ref.alias(table));
+        }
+    }
+}
+
+impl ResolveTable for InsertStatement {
+    fn resolve_table<T: TableEntity, Ctx: DbCtx<Db>, Db>(&mut self, ctx: &Ctx) {
+        self.into_table(ctx.table_ref(<T as TableEntity>::table()));
+    }
+}
+
+impl ResolveTable for UpdateStatement {
+    fn resolve_table<T: TableEntity, Ctx: DbCtx<Db>, Db>(&mut self, ctx: &Ctx) {
+        self.table(ctx.table_ref(<T as TableEntity>::table()));
+    }
+}
+
+impl ResolveTable for DeleteStatement {
+    fn resolve_table<T: TableEntity, Ctx: DbCtx<Db>, Db>(&mut self, ctx: &Ctx) {
+        self.from_table(ctx.table_ref(<T as TableEntity>::table()));
+    }
+}
+
+/// Restrict a statement to the records of the tenant of [`DbCtx::tenant`].
+/// A statement of an entity with a tenant column matches no records without a tenant.
+trait ScopeTenant {
+    fn scope_tenant<T: TableEntity, Ctx: DbCtx<Db>, Db>(&mut self, ctx: &Ctx);
+}
+
+/// The condition on the tenant column, qualified by the entity table when `qualified`
+pub(crate) fn tenant_cond<T: TableEntity, Ctx: DbCtx<Db>, Db>(ctx: &Ctx, qualified: bool) -> Option<SimpleExpr> {
+    let column = <T as TableEntity>::tenant_column()?;
+    let cond = match (ctx.tenant(), qualified) {
+        (Some(tenant), true) => Expr::col((<T as TableEntity>::table(), column)).eq(tenant),
+        (Some(tenant), false) => Expr::col(column).eq(tenant),
+        (None, _) => Expr::val(1).eq(0),
+    };
+    Some(cond)
+}
+
+impl ScopeTenant for SelectStatement {
+    fn scope_tenant<T: TableEntity, Ctx: DbCtx<Db>, Db>(&mut self, ctx: &Ctx) {
+        // the selected table keeps the entity table name as an alias, so the column can be qualified
+        if let Some(cond) = tenant_cond::<T, _, _>(ctx, true) {
+            self.cond_where(cond);
+        }
+    }
+}
+
+impl ScopeTenant for UpdateStatement {
+    fn scope_tenant<T: TableEntity, Ctx: DbCtx<Db>, Db>(&mut self, ctx: &Ctx) {
+        if let Some(cond) = tenant_cond::<T, _, _>(ctx, false) {
+            self.cond_where(cond);
+        }
+    }
+}
+
+impl ScopeTenant for DeleteStatement {
+    fn scope_tenant<T: TableEntity, Ctx: DbCtx<Db>, Db>(&mut self, ctx: &Ctx) {
+        if let Some(cond) = tenant_cond::<T, _, _>(ctx, false) {
+            self.cond_where(cond);
         }
     }
 }
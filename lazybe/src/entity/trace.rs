@@ -0,0 +1,83 @@
+use std::time::{Duration, Instant};
+
+use sea_query::{QueryBuilder, QueryStatementWriter};
+use tracing::{Instrument, Span, field};
+
+use crate::Entity;
+
+/// Traces an entity operation with a span containing the entity name, the operation,
+/// the SQL statements with their values redacted, the duration and the number of rows.
+pub(super) struct OpTrace {
+    entity: &'static str,
+    op: &'static str,
+    span: Span,
+    sql: Option<Vec<String>>,
+    slow_query_threshold: Option<Duration>,
+}
+
+impl OpTrace {
+    pub fn new<T: Entity>(op: &'static str, slow_query_threshold: Option<Duration>) -> Self {
+        let entity = T::entity_name();
+        let span = tracing::info_span!(
+            "db_op",
+            entity,
+            op,
+            sql = field::Empty,
+            duration_ms = field::Empty,
+            rows = field::Empty
+        );
+        // the SQL is rendered a second time with placeholders, so it is only done when it is reported
+        let sql = (!span.is_disabled() || slow_query_threshold.is_some()).then(Vec::new);
+        Self {
+            entity,
+            op,
+            span,
+            sql,
+            slow_query_threshold,
+        }
+    }
+
+    /// Render a statement to be executed and record it with its values replaced by placeholders
+    pub fn render<S, Qb>(&mut self, stmt: &S, query_builder: Qb) -> String
+    where
+        S: QueryStatementWriter,
+        Qb: QueryBuilder + Default,
+    {
+        if let Some(sql) = &mut self.sql {
+            sql.push(stmt.build(Qb::default()).0);
+        }
+        stmt.to_string(query_builder)
+    }
+
+    /// Run the operation in the span and report it when it is slower than the threshold.
+    /// The number of rows is recorded when `rows` can tell it from the result.
+    pub async fn run<R>(
+        self,
+        op: impl Future<Output = Result<R, sqlx::Error>>,
+        rows: impl FnOnce(&R) -> Option<usize>,
+    ) -> Result<R, sqlx::Error> {
+        let sql = self.sql.as_ref().map(|sql| sql.join("; ")).unwrap_or_default();
+        self.span.record("sql", sql.as_str());
+
+        let start = Instant::now();
+        let result = op.instrument(self.span.clone()).await;
+        let elapsed = start.elapsed();
+        let duration_ms = elapsed.as_secs_f64() * 1000.0;
+        self.span.record("duration_ms", duration_ms);
+        if let Some(rows) = result.as_ref().ok().and_then(rows) {
+            self.span.record("rows", rows);
+        }
+
+        if self.slow_query_threshold.is_some_and(|threshold| elapsed >= threshold) {
+            tracing::warn!(
+                parent: &self.span,
+                entity = self.entity,
+                op = self.op,
+                sql,
+                duration_ms,
+                "Slow database operation"
+            );
+        }
+        result
+    }
+}
@@ -0,0 +1,164 @@
+use std::fmt::Display;
+
+use sqlx::error::{DatabaseError, ErrorKind};
+
+use crate::db::TransactionError;
+
+/// An error from a database operation with constraint violations classified,
+/// so that they can be reported as a client error instead of an internal error.
+///
+/// # Example
+///
+/// ```
+/// # use lazybe::Error;
+/// fn describe(error: sqlx::Error) -> String {
+///     match Error::from(error) {
+///         Error::UniqueViolation(v) => format!("duplicate {}", v.target().unwrap_or_default()),
+///         Error::Database(e) => format!("database error: {}", e),
+///         e => e.to_string(),
+///     }
+/// }
+/// ```
+#[derive(Debug)]
+pub enum Error {
+    /// A record with the same value of a unique key already exists
+    UniqueViolation(ConstraintViolation),
+    /// A referenced record does not exist, or a record is still referenced by another record
+    ForeignKeyViolation(ConstraintViolation),
+    /// A required column is null
+    NotNullViolation(ConstraintViolation),
+    /// A value does not satisfy a check constraint
+    CheckViolation(ConstraintViolation),
+    /// Any other error
+    Database(sqlx::Error),
+}
+
+impl Error {
+    /// The violated constraint, or `None` if this is not a constraint violation
+    pub fn violation(&self) -> Option<&ConstraintViolation> {
+        match self {
+            Error::UniqueViolation(v)
+            | Error::ForeignKeyViolation(v)
+            | Error::NotNullViolation(v)
+            | Error::CheckViolation(v) => Some(v),
+            Error::Database(_) => None,
+        }
+    }
+
+    /// The underlying database error
+    pub fn db_error(&self) -> &sqlx::Error {
+        match self {
+            Error::UniqueViolation(v)
+            | Error::ForeignKeyViolation(v)
+            | Error::NotNullViolation(v)
+            | Error::CheckViolation(v) => &v.source,
+            Error::Database(e) => e,
+        }
+    }
+}
+
+impl From<sqlx::Error> for Error {
+    fn from(error: sqlx::Error) -> Self {
+        let sqlx::Error::Database(db_error) = &error else {
+            return Error::Database(error);
+        };
+        let kind = db_error.kind();
+        let (constraint, columns) = violated_names(&**db_error, &kind);
+        let violation = |source| ConstraintViolation {
+            constraint,
+            columns,
+            source,
+        };
+        match kind {
+            ErrorKind::UniqueViolation => Error::UniqueViolation(violation(error)),
+            ErrorKind::ForeignKeyViolation => Error::ForeignKeyViolation(violation(error)),
+            ErrorKind::NotNullViolation => Error::NotNullViolation(violation(error)),
+            ErrorKind::CheckViolation => Error::CheckViolation(violation(error)),
+            _ => Error::Database(error),
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let kind = match self {
+            Error::UniqueViolation(_) => "Unique",
+            Error::ForeignKeyViolation(_) => "Foreign key",
+            Error::NotNullViolation(_) => "Not null",
+            Error::CheckViolation(_) => "Check",
+            Error::Database(e) => return write!(f, "{}", e),
+        };
+        match self.violation().and_then(|v| v.target()) {
+            Some(target) => write!(f, "{} constraint violation on `{}`", kind, target),
+            None => write!(f, "{} constraint violation", kind),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(self.db_error())
+    }
+}
+
+impl TransactionError for Error {
+    fn as_db_error(&self) -> Option<&sqlx::Error> {
+        Some(self.db_error())
+    }
+}
+
+/// A violated constraint and the columns it applies to, as far as they are reported by the database
+#[derive(Debug)]
+pub struct ConstraintViolation {
+    constraint: Option<String>,
+    columns: Vec<String>,
+    source: sqlx::Error,
+}
+
+impl ConstraintViolation {
+    /// The name of the violated constraint
+    pub fn constraint(&self) -> Option<&str> {
+        self.constraint.as_deref()
+    }
+
+    /// The columns of the violated constraint
+    pub fn columns(&self) -> &[String] {
+        &self.columns
+    }
+
+    /// The name of the constraint, or its columns if the name is unknown
+    pub fn target(&self) -> Option<String> {
+        match (&self.constraint, self.columns.is_empty()) {
+            (Some(constraint), _) => Some(constraint.clone()),
+            (None, false) => Some(self.columns.join(", ")),
+            (None, true) => None,
+        }
+    }
+}
+
+/// Extract the constraint name and the columns from a database error
+fn violated_names(db_error: &dyn DatabaseError, kind: &ErrorKind) -> (Option<String>, Vec<String>) {
+    let mut constraint = db_error.constraint().map(str::to_string);
+    let mut columns = Vec::new();
+
+    #[cfg(feature = "postgres")]
+    if let Some(pg_error) = db_error.try_downcast_ref::<sqlx::postgres::PgDatabaseError>() {
+        columns.extend(pg_error.column().map(str::to_string));
+    }
+
+    // SQLite only reports the names in the message (e.g. `UNIQUE constraint failed: book.isbn`)
+    if constraint.is_none() && columns.is_empty() {
+        if let Some((_, names)) = db_error.message().split_once("constraint failed: ") {
+            match kind {
+                ErrorKind::CheckViolation => constraint = Some(names.to_string()),
+                _ => {
+                    columns = names
+                        .split(", ")
+                        .map(|name| name.rsplit('.').next().unwrap_or(name).to_string())
+                        .collect()
+                }
+            }
+        }
+    }
+    (constraint, columns)
+}
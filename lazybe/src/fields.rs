@@ -0,0 +1,159 @@
+use std::fmt::Display;
+use std::marker::PhantomData;
+
+use sea_query::{ColumnRef, SelectStatement};
+use serde::Serialize;
+
+use crate::TableEntity;
+
+/// An entity whose fields can be selected individually.
+///
+/// This trait is derived by the [`Entity`](crate::macros::Entity) macro.
+/// The fields are named after the entity fields.
+pub trait SelectFields: TableEntity {
+    /// The selectable fields and the columns they are read from
+    fn field_columns() -> Vec<(&'static str, ColumnRef)>;
+}
+
+/// Decode a subset of fields of an entity from a database row.
+///
+/// This trait is derived by the [`Entity`](crate::macros::Entity) macro for entities with an `endpoint`.
+/// Each field is serialized with its own [`Serialize`] impl.
+pub trait FromPartialRow<R>: SelectFields {
+    fn from_partial_row(row: &R, fields: &Fields<Self>) -> Result<Partial<Self>, sqlx::Error>;
+}
+
+/// A non-empty set of fields of an entity to read, e.g. from `?fields=id,title`.
+#[derive(Debug)]
+pub struct Fields<T> {
+    names: Vec<&'static str>,
+    entity: PhantomData<fn() -> T>,
+}
+
+impl<T> Clone for Fields<T> {
+    fn clone(&self) -> Self {
+        Self {
+            names: self.names.clone(),
+            entity: PhantomData,
+        }
+    }
+}
+
+impl<T: SelectFields> Fields<T> {
+    pub fn new<I, S>(names: I) -> Result<Self, FieldsError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let columns = T::field_columns();
+        let mut selected = Vec::new();
+        for name in names {
+            let name = name.as_ref().trim();
+            if name.is_empty() {
+                continue;
+            }
+            let (field, _) = columns
+                .iter()
+                .find(|(field, _)| *field == name)
+                .ok_or_else(|| FieldsError::Unknown(name.to_string()))?;
+            if !selected.contains(field) {
+                selected.push(*field);
+            }
+        }
+        if selected.is_empty() {
+            Err(FieldsError::Empty)?
+        }
+        Ok(Self {
+            names: selected,
+            entity: PhantomData,
+        })
+    }
+
+    /// Parse a comma separated list of fields
+    pub fn parse(fields: &str) -> Result<Self, FieldsError> {
+        Self::new(fields.split(','))
+    }
+
+    /// Replace the selected columns of a statement with the columns of these fields
+    pub fn apply(&self, stmt: &mut SelectStatement) {
+        let columns = T::field_columns()
+            .into_iter()
+            .filter(|(field, _)| self.names.contains(field))
+            .map(|(_, col)| col);
+        stmt.clear_selects().columns(columns);
+    }
+}
+
+impl<T> Fields<T> {
+    pub fn names(&self) -> &[&'static str] {
+        &self.names
+    }
+}
+
+/// An invalid set of fields
+#[derive(Debug, Clone)]
+pub enum FieldsError {
+    /// A field name that does not exist on the entity
+    Unknown(String),
+    /// No field is selected
+    Empty,
+}
+
+impl Display for FieldsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FieldsError::Unknown(name) => write!(f, "Unknown field `{}`", name),
+            FieldsError::Empty => write!(f, "At least one field must be selected"),
+        }
+    }
+}
+
+impl std::error::Error for FieldsError {}
+
+/// An entity with only the selected fields, serialized as a JSON object.
+#[derive(Debug, Serialize)]
+#[serde(transparent)]
+pub struct Partial<T> {
+    values: serde_json::Map<String, serde_json::Value>,
+    #[serde(skip)]
+    entity: PhantomData<fn() -> T>,
+}
+
+impl<T> Clone for Partial<T> {
+    fn clone(&self) -> Self {
+        Self {
+            values: self.values.clone(),
+            entity: PhantomData,
+        }
+    }
+}
+
+impl<T> Default for Partial<T> {
+    fn default() -> Self {
+        Self {
+            values: serde_json::Map::new(),
+            entity: PhantomData,
+        }
+    }
+}
+
+impl<T> Partial<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Serialize and add a field value
+    pub fn insert<V: Serialize>(&mut self, field: &str, value: V) -> Result<(), sqlx::Error> {
+        let value = serde_json::to_value(value).map_err(|e| sqlx::Error::Decode(Box::new(e)))?;
+        self.values.insert(field.to_string(), value);
+        Ok(())
+    }
+
+    pub fn get(&self, field: &str) -> Option<&serde_json::Value> {
+        self.values.get(field)
+    }
+
+    pub fn into_map(self) -> serde_json::Map<String, serde_json::Value> {
+        self.values
+    }
+}
@@ -1,16 +1,64 @@
 use std::marker::PhantomData;
 
-use sea_query::{Cond, Condition, ConditionExpression, Expr, IntoColumnRef, IntoLikeExpr, SimpleExpr};
+use sea_query::{Cond, Condition, ConditionExpression, Expr, IntoColumnRef, IntoLikeExpr, LikeExpr, SimpleExpr};
+
+use crate::db::Dialect;
+use crate::relation::{Join, merge_joins};
+use crate::search::{Search, SearchableEntity};
 
 #[derive(Debug, Clone)]
 pub struct Filter<Entity> {
-    cond: Condition,
+    node: FilterNode,
+    joins: Vec<Join>,
     entity: PhantomData<Entity>,
 }
 
+/// A filter is kept as a tree because the SQL of a search term depends on the database,
+/// so it can only be rendered into a [`Condition`] by a [`Dialect`].
+#[derive(Debug, Clone)]
+enum FilterNode {
+    Cond(Condition),
+    Search(Search),
+    All(Vec<FilterNode>),
+    Any(Vec<FilterNode>),
+    Not(Box<FilterNode>),
+}
+
+impl FilterNode {
+    fn into_cond(self, render: &impl Fn(Search) -> ConditionExpression) -> Condition {
+        match self {
+            FilterNode::Cond(cond) => cond,
+            FilterNode::Search(search) => Cond::all().add(render(search)),
+            FilterNode::All(nodes) => nodes
+                .into_iter()
+                .fold(Cond::all(), |cond, node| cond.add(node.into_cond(render))),
+            FilterNode::Any(nodes) => nodes
+                .into_iter()
+                .fold(Cond::any(), |cond, node| cond.add(node.into_cond(render))),
+            FilterNode::Not(node) => node.into_cond(render).not(),
+        }
+    }
+}
+
+/// A search that has not been rendered by a [`Dialect`] falls back to `LIKE`,
+/// where every word must be contained in one of the searchable columns.
+fn like_search_cond(search: Search) -> ConditionExpression {
+    let table = search.table();
+    let cond = search.term().split_whitespace().fold(Cond::all(), |cond, word| {
+        let escaped = word.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+        let pattern = format!("%{escaped}%");
+        let any_column = search.columns().iter().fold(Cond::any(), |any, col| {
+            any.add(Expr::col((table.clone(), col.clone())).like(LikeExpr::new(pattern.clone()).escape('\\')))
+        });
+        cond.add(any_column)
+    });
+    cond.into()
+}
+
 impl<Entity> From<Filter<Entity>> for ConditionExpression {
+    /// Searches that were not rendered with [`Filter::render_searches`] are matched with `LIKE`.
     fn from(value: Filter<Entity>) -> Self {
-        Self::Condition(value.cond)
+        Self::Condition(value.node.into_cond(&like_search_cond))
     }
 }
 
@@ -18,7 +66,8 @@ impl<Entity> From<FilterExpr<Entity>> for Filter<Entity> {
     fn from(value: FilterExpr<Entity>) -> Self {
         let cond = Cond::all().add(value.expr);
         Self {
-            cond,
+            node: FilterNode::Cond(cond),
+            joins: value.joins,
             entity: PhantomData,
         }
     }
@@ -27,44 +76,97 @@ impl<Entity> From<FilterExpr<Entity>> for Filter<Entity> {
 impl<Entity> Filter<Entity> {
     pub fn empty() -> Self {
         Self {
-            cond: Cond::all(),
+            node: FilterNode::Cond(Cond::all()),
+            joins: Vec::new(),
             entity: PhantomData,
         }
     }
 
+    /// Negate the filter.
     #[allow(clippy::should_implement_trait)]
     pub fn not(self) -> Self {
         Self {
-            cond: self.cond.not(),
+            node: FilterNode::Not(Box::new(self.node)),
+            joins: self.joins,
+            entity: PhantomData,
+        }
+    }
+
+    /// The joins required by the columns of related entities used in this filter
+    pub fn joins(&self) -> &[Join] {
+        &self.joins
+    }
+
+    /// Render every search term of this filter as a condition of the database.
+    ///
+    /// [`ListEntity`](crate::entity::ops::ListEntity) does this before the filter is passed to
+    /// [`ListQuery`](crate::query::ListQuery), so a search can be used anywhere in a filter.
+    pub fn render_searches<D: Dialect>(self, dialect: &D) -> Self {
+        self.render_searches_with(dialect, &|search| search)
+    }
+
+    /// Like [`Filter::render_searches`] but every search is adjusted by `resolve` before it is rendered
+    pub(crate) fn render_searches_with<D: Dialect>(self, dialect: &D, resolve: &impl Fn(Search) -> Search) -> Self {
+        let cond = self
+            .node
+            .into_cond(&|search| dialect.search_cond(&resolve(search)).into());
+        Self {
+            node: FilterNode::Cond(cond),
+            joins: self.joins,
             entity: PhantomData,
         }
     }
 
+    /// Match records where all filters match
     pub fn all<E>(exprs: impl IntoIterator<Item = E>) -> Self
     where
         E: Into<Filter<Entity>>,
     {
-        let cond = Cond::all();
-        Self::add_exprs_to_cond(cond, exprs)
+        Self::combine(FilterNode::All, exprs)
     }
 
+    /// Match records where any of the filters match
     pub fn any<E>(exprs: impl IntoIterator<Item = E>) -> Self
     where
         E: Into<Filter<Entity>>,
     {
-        let cond = Cond::any();
-        Self::add_exprs_to_cond(cond, exprs)
+        Self::combine(FilterNode::Any, exprs)
     }
 
-    fn add_exprs_to_cond<E>(mut cond: Condition, exprs: impl IntoIterator<Item = E>) -> Self
+    fn combine<E>(node: fn(Vec<FilterNode>) -> FilterNode, exprs: impl IntoIterator<Item = E>) -> Self
     where
         E: Into<Filter<Entity>>,
     {
+        let mut nodes = Vec::new();
+        let mut joins = Vec::new();
         for expr in exprs {
-            cond = cond.add(expr.into());
+            let filter = expr.into();
+            merge_joins(&mut joins, filter.joins);
+            nodes.push(filter.node);
         }
         Self {
-            cond,
+            node: node(nodes),
+            joins,
+            entity: PhantomData,
+        }
+    }
+}
+
+impl<Entity: SearchableEntity> Filter<Entity> {
+    /// Match records where the searchable fields contain every word of the term.
+    ///
+    /// A search can be combined with other filters like any other filter.
+    /// A blank term does not filter anything.
+    pub fn search(term: impl Into<String>) -> Self {
+        let term = term.into();
+        let node = if term.trim().is_empty() {
+            FilterNode::Cond(Cond::all())
+        } else {
+            FilterNode::Search(Search::new::<Entity>(term))
+        };
+        Self {
+            node,
+            joins: Vec::new(),
             entity: PhantomData,
         }
     }
@@ -73,10 +175,20 @@ impl<Entity> Filter<Entity> {
 #[derive(Debug, Clone)]
 pub struct FilterExpr<Entity> {
     expr: SimpleExpr,
+    joins: Vec<Join>,
     entity: PhantomData<Entity>,
 }
 
 impl<Entity> FilterExpr<Entity> {
+    /// A filter on an arbitrary SQL expression, e.g. `Expr::cust("length(title) > 10")`
+    pub fn new(expr: impl Into<SimpleExpr>) -> Self {
+        Self {
+            expr: expr.into(),
+            joins: Vec::new(),
+            entity: PhantomData,
+        }
+    }
+
     pub fn into_expr(self) -> SimpleExpr {
         self.expr
     }
@@ -85,6 +197,7 @@ impl<Entity> FilterExpr<Entity> {
 #[derive(Debug, Clone)]
 pub struct FilterCol<Entity, Col> {
     col_expr: Expr,
+    joins: Vec<Join>,
     entity: PhantomData<Entity>,
     col_ty: PhantomData<Col>,
 }
@@ -93,10 +206,19 @@ impl<Entity, Col> FilterCol<Entity, Col> {
     pub fn new<C: IntoColumnRef>(col: C) -> Self {
         Self {
             col_expr: Expr::col(col),
+            joins: Vec::new(),
             entity: PhantomData,
             col_ty: PhantomData,
         }
     }
+
+    /// A column of a related entity that is reachable through the given joins
+    pub fn with_joins(self, joins: impl IntoIterator<Item = Join>) -> Self {
+        Self {
+            joins: joins.into_iter().collect(),
+            ..self
+        }
+    }
 }
 
 impl<Entity, Col> FilterCol<Entity, Col>
@@ -107,6 +229,7 @@ where
         let expr = self.col_expr.eq(value);
         FilterExpr {
             expr,
+            joins: self.joins,
             entity: PhantomData,
         }
     }
@@ -115,6 +238,7 @@ where
         let expr = self.col_expr.ne(value);
         FilterExpr {
             expr,
+            joins: self.joins,
             entity: PhantomData,
         }
     }
@@ -123,6 +247,7 @@ where
         let expr = self.col_expr.gt(value);
         FilterExpr {
             expr,
+            joins: self.joins,
             entity: PhantomData,
         }
     }
@@ -131,6 +256,7 @@ where
         let expr = self.col_expr.gte(value);
         FilterExpr {
             expr,
+            joins: self.joins,
             entity: PhantomData,
         }
     }
@@ -139,6 +265,7 @@ where
         let expr = self.col_expr.lt(value);
         FilterExpr {
             expr,
+            joins: self.joins,
             entity: PhantomData,
         }
     }
@@ -147,6 +274,7 @@ where
         let expr = self.col_expr.lte(value);
         FilterExpr {
             expr,
+            joins: self.joins,
             entity: PhantomData,
         }
     }
@@ -158,6 +286,7 @@ where
         let expr = self.col_expr.is_in(values);
         FilterExpr {
             expr,
+            joins: self.joins,
             entity: PhantomData,
         }
     }
@@ -169,6 +298,7 @@ where
         let expr = self.col_expr.is_null();
         FilterExpr {
             expr,
+            joins: self.joins,
             entity: PhantomData,
         }
     }
@@ -181,6 +311,7 @@ where
         let expr = self.col_expr.like(like_stm);
         FilterExpr {
             expr,
+            joins: self.joins,
             entity: PhantomData,
         }
     }
@@ -193,6 +324,7 @@ where
         let expr = self.col_expr.not_like(like_stm);
         FilterExpr {
             expr,
+            joins: self.joins,
             entity: PhantomData,
         }
     }
@@ -0,0 +1,303 @@
+use std::marker::PhantomData;
+
+use chrono::{DateTime, Utc};
+use sea_query::{
+    Alias, ColumnDef, ColumnType, DynIden, Expr, Iden, InsertStatement, IntoIden, Order, Query, QueryBuilder,
+    SelectStatement, Table, TableCreateStatement, TableRef, Value,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::TableEntity;
+use crate::db::DbCtx;
+use crate::entity::ops::tenant_cond;
+
+/// An entity whose changes are recorded in a history table, derived by `#[lazybe(history)]`.
+///
+/// Every create, update and delete operation of [`entity::ops`](crate::entity::ops) writes a [`HistoryRecord`]
+/// in the same transaction as the change. The records of an entity are read by
+/// [`GetHistory`](crate::entity::ops::GetHistory).
+///
+/// # Example
+///
+/// ```
+/// # use lazybe::history::HistoryEntity;
+/// # use lazybe::macros::Entity;
+/// # use sea_query::SqliteQueryBuilder;
+/// # use serde::Serialize;
+/// #[derive(Serialize, Entity)]
+/// #[lazybe(table = "book", history)]
+/// pub struct Book {
+///     #[lazybe(primary_key)]
+///     pub id: i32,
+///     pub title: String,
+/// }
+///
+/// assert_eq!(Book::history_table().to_string(), "book_history");
+/// assert_eq!(
+///     Book::create_history_table().to_string(SqliteQueryBuilder),
+///     [
+///         r#"CREATE TABLE "book_history" ( "id" integer NOT NULL PRIMARY KEY AUTOINCREMENT,"#,
+///         r#""entity_id" integer NOT NULL, "operation" text NOT NULL, "changed_at" timestamp_with_timezone_text NOT NULL,"#,
+///         r#""actor" text NULL, "before" jsonb_text NULL, "after" jsonb_text NULL )"#,
+///     ]
+///     .join(" ")
+/// );
+/// ```
+pub trait HistoryEntity: TableEntity + Serialize {
+    /// The table where the history records are stored. Defaults to `{table}_history`.
+    fn history_table() -> DynIden {
+        Alias::new(format!("{}_history", Self::table().to_string())).into_iden()
+    }
+
+    /// The `CREATE TABLE` statement of the history table
+    fn create_history_table() -> TableCreateStatement;
+
+    /// The primary key of the entity, which is stored in the `entity_id` column
+    fn history_key(&self) -> Value;
+
+    /// The snapshot of the entity, which is stored in the `before` and `after` columns.
+    /// Defaults to the entity serialized to JSON.
+    ///
+    /// An error fails the change, so that no change is made without its history record.
+    fn history_snapshot(&self) -> Result<serde_json::Value, serde_json::Error> {
+        serde_json::to_value(self)
+    }
+}
+
+/// The history of an entity, provided by [`TableEntity::history`] for a [`HistoryEntity`]
+pub struct History<T> {
+    entity: PhantomData<fn() -> T>,
+    table: fn() -> DynIden,
+    create_table: fn() -> TableCreateStatement,
+    key: fn(&T) -> Value,
+    snapshot: fn(&T) -> Result<serde_json::Value, serde_json::Error>,
+}
+
+impl<T: HistoryEntity> History<T> {
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        Self {
+            entity: PhantomData,
+            table: T::history_table,
+            create_table: T::create_history_table,
+            key: T::history_key,
+            snapshot: T::history_snapshot,
+        }
+    }
+}
+
+impl<T> History<T> {
+    /// The table where the history records are stored
+    pub fn table(&self) -> DynIden {
+        (self.table)()
+    }
+
+    /// The `CREATE TABLE` statement of the history table
+    pub fn create_table(&self) -> TableCreateStatement {
+        (self.create_table)()
+    }
+}
+
+/// The operation that changed a record
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum HistoryOperation {
+    Create,
+    Update,
+    Delete,
+}
+
+impl HistoryOperation {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HistoryOperation::Create => "create",
+            HistoryOperation::Update => "update",
+            HistoryOperation::Delete => "delete",
+        }
+    }
+}
+
+/// A change of a record
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct HistoryRecord {
+    pub id: i64,
+    pub operation: HistoryOperation,
+    pub changed_at: DateTime<Utc>,
+    /// The actor of the change, see [`DbCtx::actor`]
+    pub actor: Option<String>,
+    /// The snapshot of the record before the change, or `None` when it is created
+    pub before: Option<serde_json::Value>,
+    /// The snapshot of the record after the change, or `None` when it is deleted
+    pub after: Option<serde_json::Value>,
+}
+
+#[derive(Iden)]
+enum HistoryColumn {
+    Id,
+    EntityId,
+    Operation,
+    ChangedAt,
+    Actor,
+    Before,
+    After,
+}
+
+/// The `CREATE TABLE` statement of the history table of an entity, used by the [`Entity`](crate::macros::Entity) macro
+#[doc(hidden)]
+pub fn create_table<T: HistoryEntity>(key_type: ColumnType, tenant_type: Option<ColumnType>) -> TableCreateStatement {
+    let mut stmt = Table::create();
+    stmt.table(T::history_table())
+        .col(
+            ColumnDef::new_with_type(HistoryColumn::Id, ColumnType::BigInteger)
+                .not_null()
+                .primary_key()
+                .auto_increment(),
+        )
+        .col(ColumnDef::new_with_type(HistoryColumn::EntityId, key_type).not_null());
+    if let (Some(column), Some(tenant_type)) = (T::tenant_column(), tenant_type) {
+        stmt.col(ColumnDef::new_with_type(column, tenant_type).not_null());
+    }
+    stmt.col(ColumnDef::new_with_type(HistoryColumn::Operation, ColumnType::Text).not_null())
+        .col(ColumnDef::new_with_type(HistoryColumn::ChangedAt, ColumnType::TimestampWithTimeZone).not_null())
+        .col(ColumnDef::new_with_type(HistoryColumn::Actor, ColumnType::Text).null())
+        .col(ColumnDef::new_with_type(HistoryColumn::Before, ColumnType::JsonBinary).null())
+        .col(ColumnDef::new_with_type(HistoryColumn::After, ColumnType::JsonBinary).null())
+        .to_owned()
+}
+
+/// Writes the history records of the changes made with a context
+pub(crate) struct HistoryWriter<T> {
+    history: History<T>,
+    table: TableRef,
+    tenant: Option<(DynIden, Value)>,
+    actor: Option<String>,
+}
+
+impl<T: TableEntity> HistoryWriter<T> {
+    /// The writer of the history of `T`, or `None` when the changes of `T` are not recorded
+    pub(crate) fn new<Ctx: DbCtx<Db>, Db>(ctx: &Ctx) -> Option<Self> {
+        let history = <T as TableEntity>::history()?;
+        let table = ctx.table_ref(history.table());
+        let tenant =
+            <T as TableEntity>::tenant_column().map(|column| (column, ctx.tenant().unwrap_or(Value::Bool(None))));
+        let actor = ctx.actor().and_then(|actor| match actor {
+            Value::String(actor) => actor.map(|s| *s),
+            actor => Some(ctx.query_builder().value_to_string(&actor)),
+        });
+        Some(Self {
+            history,
+            table,
+            tenant,
+            actor,
+        })
+    }
+
+    /// The statement recording a change from `before` to `after`.
+    /// Fails when a snapshot cannot be serialized.
+    pub(crate) fn insert_statement(
+        &self,
+        operation: HistoryOperation,
+        before: Option<&T>,
+        after: Option<&T>,
+    ) -> Result<InsertStatement, sqlx::Error> {
+        let key = after.or(before).map(self.history.key).unwrap_or(Value::Bool(None));
+        let snapshot = |entity: Option<&T>| match entity {
+            Some(entity) => (self.history.snapshot)(entity)
+                .map(|snapshot| Value::Json(Some(Box::new(snapshot))))
+                .map_err(|e| sqlx::Error::Encode(Box::new(e))),
+            None => Ok(Value::Json(None)),
+        };
+        let mut columns = vec![HistoryColumn::EntityId.into_iden()];
+        let mut values = vec![Expr::val(key).into()];
+        if let Some((column, tenant)) = &self.tenant {
+            columns.push(column.clone());
+            values.push(Expr::val(tenant.clone()).into());
+        }
+        columns.extend([
+            HistoryColumn::Operation.into_iden(),
+            HistoryColumn::ChangedAt.into_iden(),
+            HistoryColumn::Actor.into_iden(),
+            HistoryColumn::Before.into_iden(),
+            HistoryColumn::After.into_iden(),
+        ]);
+        values.extend([
+            Expr::val(operation.as_str()).into(),
+            Expr::val(Utc::now()).into(),
+            Expr::val(self.actor.clone()).into(),
+            Expr::val(snapshot(before)?).into(),
+            Expr::val(snapshot(after)?).into(),
+        ]);
+        Ok(Query::insert()
+            .into_table(self.table.clone())
+            .columns(columns)
+            .values_panic(values)
+            .to_owned())
+    }
+}
+
+/// The statement selecting the history records of an entity in the order of the changes
+pub(crate) fn list_statement<T, Ctx, Db>(ctx: &Ctx, id: Value) -> SelectStatement
+where
+    T: HistoryEntity,
+    Ctx: DbCtx<Db>,
+{
+    let mut query = Query::select();
+    query
+        .columns([
+            HistoryColumn::Id,
+            HistoryColumn::Operation,
+            HistoryColumn::ChangedAt,
+            HistoryColumn::Actor,
+            HistoryColumn::Before,
+            HistoryColumn::After,
+        ])
+        .from(ctx.table_ref(T::history_table()))
+        .and_where(Expr::col(HistoryColumn::EntityId).eq(id))
+        .order_by(HistoryColumn::Id, Order::Asc);
+    if let Some(cond) = tenant_cond::<T, _, _>(ctx, false) {
+        query.and_where(cond);
+    }
+    query
+}
+
+pub(crate) mod row {
+    use chrono::{DateTime, Utc};
+    use sqlx::types::Json;
+
+    use super::{HistoryOperation, HistoryRecord};
+
+    #[derive(sqlx::FromRow)]
+    pub struct HistoryRow {
+        pub id: i64,
+        pub operation: String,
+        pub changed_at: DateTime<Utc>,
+        pub actor: Option<String>,
+        pub before: Option<Json<serde_json::Value>>,
+        pub after: Option<Json<serde_json::Value>>,
+    }
+
+    impl TryFrom<HistoryRow> for HistoryRecord {
+        type Error = sqlx::Error;
+
+        fn try_from(row: HistoryRow) -> Result<Self, Self::Error> {
+            let operation = match row.operation.as_str() {
+                "create" => HistoryOperation::Create,
+                "update" => HistoryOperation::Update,
+                "delete" => HistoryOperation::Delete,
+                other => Err(sqlx::Error::Decode(
+                    format!("unknown history operation `{}`", other).into(),
+                ))?,
+            };
+            Ok(HistoryRecord {
+                id: row.id,
+                operation,
+                changed_at: row.changed_at,
+                actor: row.actor,
+                before: row.before.map(|json| json.0),
+                after: row.after.map(|json| json.0),
+            })
+        }
+    }
+}
@@ -54,6 +54,8 @@
 //!     - [`CreateQuery`](query::CreateQuery)
 //!     - [`UpdateQuery`](query::UpdateQuery)
 //!     - [`DeleteQuery`](query::DeleteQuery)
+//!   - [`SelectFields`](fields::SelectFields) - columns of the fields that can be selected individually
+//!   - [`TableSchema`](schema::TableSchema) - the `CREATE TABLE` statement of the entity table
 //!   - Entity CRUD operations
 //!     - [`GetEntity`](entity::ops::GetEntity)
 //!     - [`ListEntity`](entity::ops::ListEntity)
@@ -66,6 +68,7 @@
 //!   - `ReplaceTodo` - a sibling type used for replacing an instance
 //!   - `TodoFilter` - a sibling type for creating a [`Filter`](filter::Filter)
 //!   - `TodoSort` - a sibling type for creating a [`Sort`](sort::Sort)
+//!   - `TodoJoinFilter` and `TodoJoinSort` - sibling types for filtering and sorting on Todo from a related entity
 //!   - `TodoSqlxRow` - a sibling type used by [`sqlx`] that implements [`sqlx::FromRow`]
 //!   - `TodoSeaQueryIdent` - a sibling type used by [`sea_query`] for buiding query
 //!
@@ -251,6 +254,11 @@
 //! See also:
 //! - [`EntityCollectionApi`](router::EntityCollectionApi)
 //! - [`ValidationHook`](router::ValidationHook)
+//! - [`CrudRouter`](router::CrudRouter) for selecting the endpoints of an entity in a single router
+//! - [`registry`] for mounting every entity registered with `register = "..."` at once
+//! - [`RouteConfig::tenant`](router::RouteConfig::tenant) for scoping the endpoints to the tenant of a request
+//! - [`RouteConfig::actor`](router::RouteConfig::actor) for recording who makes the changes of a request
+//! - [`HistoryRouter`](router::HistoryRouter) for the history of a `#[lazybe(history)]` entity
 //!
 //!
 //! ## OpenAPI documentation
@@ -307,6 +315,9 @@
 //!
 //! See also:
 //! - [`openapi`] module
+//! - [`CrudRouterDoc`](openapi::CrudRouterDoc) for documenting the same endpoints as [`CrudRouter`](router::CrudRouter)
+//! - [`CrudOptions::prefix`](router::CrudOptions::prefix) and [`prefix_paths`](openapi::prefix_paths) for mounting under a path prefix
+//! - [`HistoryRouterDoc`](openapi::HistoryRouterDoc) for documenting the history endpoint
 //!
 //!
 //! ## Typed URI
@@ -360,6 +371,150 @@
 //! See [`EntityCollectionApi`](router::EntityCollectionApi)
 //!
 //!
+//! ## Selecting fields
+//!
+//! The get and list endpoints accept `?fields=...` to return only some fields of an entity,
+//! e.g. `GET /books?fields=id,title` responds with `[{"id": 1, "title": "Dune"}]`.
+//! Only the columns of the selected fields are read from the database and unknown fields are rejected with `400 Bad Request`.
+//! The same is available on the data access layer with [`DbOps::get_partial`](db::DbOps::get_partial)
+//! and [`DbOps::list_partial`](db::DbOps::list_partial) using [`Fields`](fields::Fields).
+//!
+//!
+//! ## OData collection API
+//!
+//! With `collection_api = "odata"`, the collection endpoint accepts the OData query options
+//! `$filter`, `$orderby`, `$top`, `$skip`, `$select` and `$count` on the entity fields,
+//! and responds with an OData envelope (`value`, `@odata.count`, `@odata.nextLink`).
+//! See [`ODataQuery`](odata::ODataQuery) for the supported syntax.
+//!
+//! ```
+//! use lazybe::macros::Entity;
+//! use lazybe::odata::{ODataQuery, ODataQueryParams};
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Serialize, Deserialize, Entity)]
+//! #[lazybe(table = "book", endpoint = "/books", collection_api = "odata")]
+//! pub struct Book {
+//!     #[lazybe(primary_key)]
+//!     pub id: i32,
+//!     pub title: String,
+//!     pub pages: Option<i32>,
+//! }
+//!
+//! // GET /books?$filter=contains(title,'Dune') and pages gt 300&$orderby=title desc&$top=10
+//! let query = ODataQuery::<Book>::parse(ODataQueryParams {
+//!     filter: Some("contains(title,'Dune') and pages gt 300".to_string()),
+//!     orderby: Some("title desc".to_string()),
+//!     top: Some(10),
+//!     ..Default::default()
+//! });
+//! assert!(query.is_ok());
+//!
+//! let query = ODataQuery::<Book>::parse(ODataQueryParams {
+//!     filter: Some("author eq 'Frank Herbert'".to_string()),
+//!     ..Default::default()
+//! });
+//! assert!(query.is_err());
+//! ```
+//!
+//!
+//! ## Sorting by expressions
+//!
+//! Besides plain columns, a [`SortCol`](sort::SortCol) can be case-insensitive with `lower()`,
+//! fall back to other columns with `coalesce(..)` or be any SQL expression with [`SortCol::expr`](sort::SortCol::expr).
+//! The position of `NULL` values is controlled with `nulls_first()` and `nulls_last()`.
+//!
+//! ```
+//! use lazybe::macros::Entity;
+//! use sea_query::Expr;
+//! use lazybe::sort::{Sort, SortCol};
+//!
+//! #[derive(Entity)]
+//! #[lazybe(table = "book")]
+//! pub struct Book {
+//!     #[lazybe(primary_key)]
+//!     pub id: i32,
+//!     pub title: String,
+//!     pub published_at: Option<i64>,
+//!     pub updated_at: Option<i64>,
+//!     pub created_at: i64,
+//! }
+//!
+//! // ORDER BY COALESCE(updated_at, created_at) DESC, published_at ASC NULLS LAST, LOWER(title) ASC, length(title) ASC
+//! let sort: Sort<Book> = Sort::new([
+//!     BookSort::updated_at().coalesce([BookSort::created_at()]).desc(),
+//!     BookSort::published_at().asc().nulls_last(),
+//!     BookSort::title().lower().asc(),
+//!     SortCol::expr(Expr::cust("length(title)")).asc(),
+//! ]);
+//! ```
+//!
+//!
+//! ## Filtering and sorting across related entities
+//!
+//! A foreign key field can declare the entity it references with the `references` attribute.
+//! The columns of the referenced entity can then be used to filter and sort the referencing entity.
+//! The necessary `LEFT JOIN` is added to the list query automatically.
+//!
+//! ```
+//! use lazybe::filter::Filter;
+//! use lazybe::macros::Entity;
+//! use lazybe::sort::Sort;
+//!
+//! #[derive(Entity)]
+//! #[lazybe(table = "author")]
+//! pub struct Author {
+//!     #[lazybe(primary_key)]
+//!     pub id: i32,
+//!     pub name: String,
+//!     pub country: String,
+//! }
+//!
+//! #[derive(Entity)]
+//! #[lazybe(table = "book")]
+//! pub struct Book {
+//!     #[lazybe(primary_key)]
+//!     pub id: i32,
+//!     pub title: String,
+//!     #[lazybe(references = "Author")]
+//!     pub author_id: i32,
+//! }
+//!
+//! // Books whose author is from Japan, sorted by the author name
+//! let filter: Filter<Book> =
+//!     Filter::all([BookFilter::author().country().eq("Japan".to_string())]);
+//! let sort: Sort<Book> = Sort::new([BookSort::author().name().asc()]);
+//! ```
+//!
+//!
+//! ## Full-text search
+//!
+//! Fields marked as `searchable` can be matched against a search term with [`Filter::search`](filter::Filter::search)
+//! and ranked by relevance with [`SortCol::search_rank`](sort::SortCol::search_rank).
+//! The generated collection API accepts the search term as `?q=...`.
+//! See [`SearchableEntity`](search::SearchableEntity) for the required database setup.
+//!
+//! ```
+//! use lazybe::filter::Filter;
+//! use lazybe::macros::Entity;
+//! use lazybe::sort::Sort;
+//!
+//! #[derive(Entity)]
+//! #[lazybe(table = "book")]
+//! pub struct Book {
+//!     #[lazybe(primary_key)]
+//!     pub id: i32,
+//!     #[lazybe(searchable)]
+//!     pub title: String,
+//!     #[lazybe(searchable)]
+//!     pub description: Option<String>,
+//! }
+//!
+//! let filter: Filter<Book> = BookFilter::search("wizard school");
+//! let sort: Sort<Book> = Sort::new([BookSort::search_rank("wizard school").desc()]);
+//! ```
+//!
+//!
 //! ## Custom ID generation
 //!
 //! The field that serves as the primary key is usually generated from the database.
@@ -434,7 +589,7 @@
 //! use lazybe::entity::ops::CreateEntity;
 //! use lazybe::macros::{Entity, EntityEndpoint};
 //! use serde::{Deserialize, Serialize};
-//! use sqlx::Sqlite;
+//! use sqlx::{Acquire, Sqlite};
 //!
 //! #[derive(Serialize, Deserialize, Entity)]
 //! #[lazybe(table = "book")]
@@ -470,20 +625,22 @@
 //! }
 //!
 //! impl CreateEntity<Sqlite> for BookFacade {
-//!     async fn create<Ctx>(
+//!     async fn create<'a, Ctx>(
 //!         ctx: &Ctx,
-//!         tx: &mut sqlx::Transaction<'_, Sqlite>,
+//!         conn: impl Acquire<'a, Database = Sqlite> + Send + 'a,
 //!         input: Self::Create,
 //!     ) -> Result<Self, sqlx::Error>
 //!     where
 //!         Ctx: DbCtx<Sqlite> + Sync,
 //!     {
-//!         let author = ctx.create::<Author>(tx, input.author).await?;
+//!         let mut tx = conn.begin().await?;
+//!         let author = ctx.create::<Author>(&mut tx, input.author).await?;
 //!         let create_book = CreateBook {
 //!             title: input.book,
 //!             author_id: author.id,
 //!         };
-//!         let book = ctx.create::<Book>(tx, create_book).await?;
+//!         let book = ctx.create::<Book>(&mut tx, create_book).await?;
+//!         tx.commit().await?;
 //!         Ok(Self { book, author })
 //!     }
 //! }
@@ -495,17 +652,32 @@
 //! - [`EntityEndpoint`](macros::EntityEndpoint)
 
 pub use entity::{Entity, TableEntity};
+pub use error::Error;
 
 /// Database interactions
 pub mod db;
 /// Traits and types for describing entity
 pub mod entity;
+/// Classified database errors
+pub mod error;
+/// Utilities for selecting a subset of fields
+pub mod fields;
 /// Utilities for filtering records
 pub mod filter;
+/// Utilities for recording the history of entity changes
+pub mod history;
+/// Utilities for publishing entity changes through a transactional outbox
+pub mod outbox;
 /// Utilities for pagination
 pub mod page;
 /// Triats and types for querying entities on a database
 pub mod query;
+/// Utilities for filtering and sorting across related entities
+pub mod relation;
+/// Utilities for generating and checking database schema
+pub mod schema;
+/// Utilities for full-text search
+pub mod search;
 /// Utilities for sorting records
 pub mod sort;
 
@@ -539,6 +711,16 @@ pub mod axum {
     pub use axum::*;
 }
 
+/// A global registry of entities for mounting their endpoints and documentation
+#[cfg(feature = "axum")]
+#[doc(cfg(feature = "axum"))]
+pub mod registry;
+
+/// OData query options for collection APIs
+#[cfg(feature = "axum")]
+#[doc(cfg(feature = "axum"))]
+pub mod odata;
+
 /// Utilities for generating a OpenAPI documentation
 #[cfg(feature = "openapi")]
 #[doc(cfg(feature = "openapi"))]
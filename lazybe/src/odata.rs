@@ -0,0 +1,736 @@
+use std::fmt::Display;
+use std::marker::PhantomData;
+
+use sea_query::{BinOper, ColumnRef, Expr, IntoColumnRef, LikeExpr, Order, SimpleExpr};
+use serde::de::DeserializeOwned;
+use serde::ser::{Error as _, SerializeMap};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::fields::{Fields, Partial};
+use crate::filter::{Filter, FilterExpr};
+use crate::page::{Page, PaginationInput};
+use crate::router::FieldsQuery;
+use crate::sort::{Sort, SortCol};
+
+/// An entity whose collection API accepts [OData query options](https://docs.oasis-open.org/odata/odata/v4.01/odata-v4.01-part2-url-conventions.html#sec_SystemQueryOptions).
+///
+/// This trait is derived by the [`Entity`](crate::macros::Entity) macro when `collection_api = "odata"` is set.
+/// The properties are named after the entity fields.
+pub trait ODataEntity: Sized {
+    /// The properties that can be used in the query options
+    fn odata_properties() -> Vec<ODataProperty>;
+}
+
+/// A property of an [`ODataEntity`] that can be referenced in the query options.
+#[derive(Debug, Clone)]
+pub struct ODataProperty {
+    name: &'static str,
+    column: Option<(ColumnRef, DecodeFn)>,
+}
+
+type DecodeFn = fn(serde_json::Value) -> Result<sea_query::Value, serde_json::Error>;
+
+impl ODataProperty {
+    /// A property that can be used in `$filter`, `$orderby` and `$select`.
+    ///
+    /// Literals in `$filter` are decoded as `V` before being compared with the column.
+    pub fn new<V>(name: &'static str, col: impl IntoColumnRef) -> Self
+    where
+        V: DeserializeOwned + Into<sea_query::Value>,
+    {
+        Self {
+            name,
+            column: Some((col.into_column_ref(), decode_value::<V>)),
+        }
+    }
+
+    /// A property that can only be used in `$select`
+    pub fn select_only(name: &'static str) -> Self {
+        Self { name, column: None }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+}
+
+fn decode_value<V>(value: serde_json::Value) -> Result<sea_query::Value, serde_json::Error>
+where
+    V: DeserializeOwned + Into<sea_query::Value>,
+{
+    serde_json::from_value::<V>(value).map(Into::into)
+}
+
+/// A page of at least `top` records that contains the records from `skip` to `skip + top`.
+///
+/// A page at `k = skip / top` with `(skip + top) / (k + 1)` records, rounded up, starts at or before `skip`
+/// and ends at or after `skip + top`. It is the page of `top` records when `skip` is a multiple of `top`.
+fn page_window(skip: u32, top: u32) -> PaginationInput {
+    let (skip, top) = (u64::from(skip), u64::from(top));
+    let page = skip / top;
+    let limit = (skip + top).div_ceil(page + 1);
+    PaginationInput {
+        page: page as u32,
+        limit: limit.min(u64::from(u32::MAX)) as u32,
+    }
+}
+
+/// An error from parsing the OData query options
+#[derive(Debug, Clone)]
+pub struct ODataError(String);
+
+impl ODataError {
+    fn new(msg: impl Into<String>) -> Self {
+        Self(msg.into())
+    }
+}
+
+impl Display for ODataError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for ODataError {}
+
+/// The raw OData query options of a collection endpoint
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ODataQueryParams {
+    #[serde(rename = "$filter", skip_serializing_if = "Option::is_none")]
+    pub filter: Option<String>,
+    #[serde(rename = "$orderby", skip_serializing_if = "Option::is_none")]
+    pub orderby: Option<String>,
+    #[serde(rename = "$top", skip_serializing_if = "Option::is_none")]
+    pub top: Option<u32>,
+    #[serde(rename = "$skip", skip_serializing_if = "Option::is_none")]
+    pub skip: Option<u32>,
+    #[serde(rename = "$select", skip_serializing_if = "Option::is_none")]
+    pub select: Option<String>,
+    #[serde(rename = "$count", skip_serializing_if = "Option::is_none")]
+    pub count: Option<bool>,
+}
+
+/// OData query options parsed against the properties of an [`ODataEntity`].
+///
+/// Supported query options:
+/// - `$filter` - comparisons (`eq`, `ne`, `gt`, `ge`, `lt`, `le`, `in`), `and`, `or`, `not`, parentheses
+///   and the string functions `contains`, `startswith` and `endswith`
+/// - `$orderby` - a comma separated list of properties with an optional `asc` or `desc`
+/// - `$top` and `$skip` - the maximum number of records to return and the number of records to skip
+/// - `$select` - a comma separated list of properties to include in the response
+/// - `$count` - include the total number of matching records in the response
+///
+/// Parsing fails on any syntax error or unknown property.
+/// When used as a query extractor, the endpoint responds with `400 Bad Request`.
+#[derive(Debug)]
+pub struct ODataQuery<T> {
+    params: ODataQueryParams,
+    filter: Option<FilterNode>,
+    orderby: Vec<(ColumnRef, Order)>,
+    page: Option<PaginationInput>,
+    select: Option<Vec<String>>,
+    entity: PhantomData<T>,
+}
+
+impl<T: ODataEntity> ODataQuery<T> {
+    pub fn parse(params: ODataQueryParams) -> Result<Self, ODataError> {
+        let properties = T::odata_properties();
+        let filter = match params.filter.as_deref() {
+            Some(filter) => Some(FilterParser::new(filter, &properties)?.parse()?),
+            None => None,
+        };
+        let orderby = match params.orderby.as_deref() {
+            Some(orderby) => parse_orderby(orderby, &properties)?,
+            None => Vec::new(),
+        };
+        let select = match params.select.as_deref() {
+            Some(select) => parse_select(select, &properties)?,
+            None => None,
+        };
+        // without `$top`, all records are listed and `$skip` is applied to the response
+        let page = match params.top {
+            None => None,
+            Some(0) => Err(ODataError::new("$top must be greater than 0"))?,
+            Some(top) => Some(page_window(params.skip.unwrap_or(0), top)),
+        };
+        Ok(Self {
+            params,
+            filter,
+            orderby,
+            page,
+            select,
+            entity: PhantomData,
+        })
+    }
+
+    /// The filter from `$filter`
+    pub fn filter(&self) -> Filter<T> {
+        match &self.filter {
+            Some(node) => FilterExpr::new(node.to_expr()).into(),
+            None => Filter::empty(),
+        }
+    }
+
+    /// The sort from `$orderby`, or `None` if not provided
+    pub fn sort(&self) -> Option<Sort<T>> {
+        if self.orderby.is_empty() {
+            return None;
+        }
+        let exprs = self.orderby.iter().map(|(col, order)| {
+            let col = SortCol::new(col.clone());
+            match order {
+                Order::Desc => col.desc(),
+                _ => col.asc(),
+            }
+        });
+        Some(Sort::new(exprs))
+    }
+}
+
+impl<T> ODataQuery<T> {
+    pub fn params(&self) -> &ODataQueryParams {
+        &self.params
+    }
+
+    /// The page that contains the records of `$skip` and `$top`, or `None` to list all records without `$top`.
+    ///
+    /// When `$skip` is not a multiple of `$top`, the page starts before `$skip` or has more than `$top` records.
+    /// [`ODataQuery::response`] only keeps the records of `$skip` and `$top`.
+    pub fn page_input(&self) -> Option<PaginationInput> {
+        self.page.clone()
+    }
+
+    /// Build the OData response for a page returned by this query.
+    ///
+    /// `path` is the collection path used for `@odata.nextLink`.
+    pub fn response<U>(&self, page: Page<U>, path: &str) -> ODataPage<U> {
+        let skip = self.params.skip.unwrap_or(0);
+        let page_offset = self.page.as_ref().map(PaginationInput::offset).unwrap_or(0);
+        let value = page
+            .data
+            .into_iter()
+            .skip(skip.saturating_sub(page_offset) as usize)
+            .take(self.params.top.map(|top| top as usize).unwrap_or(usize::MAX))
+            .collect();
+        let next_link = self
+            .params
+            .top
+            .map(|top| skip.saturating_add(top))
+            .filter(|next_offset| *next_offset < page.total_records)
+            .and_then(|next_offset| {
+                let params = ODataQueryParams {
+                    skip: Some(next_offset),
+                    ..self.params.clone()
+                };
+                crate::uri::to_query_string(&params).ok()
+            })
+            .map(|query| format!("{}?{}", path, query));
+        ODataPage {
+            value,
+            count: Some(page.total_records).filter(|_| self.params.count.unwrap_or(false)),
+            next_link,
+            select: self.select.clone(),
+        }
+    }
+}
+
+impl<T> ODataQuery<T> {
+    /// Like [`ODataQuery::response`] for a page of entities selected with `?fields=...`.
+    /// The fields are kept in `@odata.nextLink`.
+    pub fn partial_response(&self, page: Page<Partial<T>>, fields: &Fields<T>, path: &str) -> ODataPage<Partial<T>> {
+        let mut resp = self.response(page, path);
+        if let Some(next_link) = resp.next_link.as_mut() {
+            let fields = FieldsQuery {
+                fields: Some(fields.names().join(",")),
+            };
+            if let Ok(query) = crate::uri::to_query_string(&fields) {
+                next_link.push('&');
+                next_link.push_str(&query);
+            }
+        }
+        resp
+    }
+}
+
+impl<'de, T: ODataEntity> Deserialize<'de> for ODataQuery<T> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let params = ODataQueryParams::deserialize(deserializer)?;
+        Self::parse(params).map_err(serde::de::Error::custom)
+    }
+}
+
+/// An OData collection response.
+///
+/// ```json
+/// { "@odata.count": 42, "value": [...], "@odata.nextLink": "/books?$top=10&$skip=10" }
+/// ```
+#[derive(Debug)]
+pub struct ODataPage<T> {
+    pub value: Vec<T>,
+    pub count: Option<u32>,
+    pub next_link: Option<String>,
+    select: Option<Vec<String>>,
+}
+
+impl<T> ODataPage<T> {
+    pub fn new(value: Vec<T>) -> Self {
+        Self {
+            value,
+            count: None,
+            next_link: None,
+            select: None,
+        }
+    }
+}
+
+impl<T: Serialize> Serialize for ODataPage<T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(None)?;
+        if let Some(count) = self.count {
+            map.serialize_entry("@odata.count", &count)?;
+        }
+        match &self.select {
+            None => map.serialize_entry("value", &self.value)?,
+            Some(select) => {
+                let mut items = Vec::with_capacity(self.value.len());
+                for item in &self.value {
+                    let item = match serde_json::to_value(item).map_err(S::Error::custom)? {
+                        serde_json::Value::Object(mut obj) => {
+                            obj.retain(|k, _| select.contains(k));
+                            serde_json::Value::Object(obj)
+                        }
+                        other => other,
+                    };
+                    items.push(item);
+                }
+                map.serialize_entry("value", &items)?;
+            }
+        }
+        if let Some(next_link) = &self.next_link {
+            map.serialize_entry("@odata.nextLink", next_link)?;
+        }
+        map.end()
+    }
+}
+
+fn find_property<'a>(properties: &'a [ODataProperty], name: &str) -> Result<&'a ODataProperty, ODataError> {
+    properties
+        .iter()
+        .find(|p| p.name == name)
+        .ok_or_else(|| ODataError::new(format!("Unknown property `{}`", name)))
+}
+
+fn find_column<'a>(properties: &'a [ODataProperty], name: &str) -> Result<&'a (ColumnRef, DecodeFn), ODataError> {
+    find_property(properties, name)?
+        .column
+        .as_ref()
+        .ok_or_else(|| ODataError::new(format!("Property `{}` can only be used in $select", name)))
+}
+
+fn parse_orderby(orderby: &str, properties: &[ODataProperty]) -> Result<Vec<(ColumnRef, Order)>, ODataError> {
+    orderby
+        .split(',')
+        .map(|item| {
+            let words = item.split_whitespace().collect::<Vec<_>>();
+            let (name, order) = match words.as_slice() {
+                [name] => (*name, Order::Asc),
+                [name, "asc"] => (*name, Order::Asc),
+                [name, "desc"] => (*name, Order::Desc),
+                _ => Err(ODataError::new(format!("Invalid $orderby item `{}`", item.trim())))?,
+            };
+            let (col, _) = find_column(properties, name)?;
+            Ok((col.clone(), order))
+        })
+        .collect()
+}
+
+fn parse_select(select: &str, properties: &[ODataProperty]) -> Result<Option<Vec<String>>, ODataError> {
+    let names = select.split(',').map(|s| s.trim()).collect::<Vec<_>>();
+    if names.contains(&"*") {
+        return Ok(None);
+    }
+    names
+        .into_iter()
+        .map(|name| find_property(properties, name).map(|p| p.name.to_string()))
+        .collect::<Result<Vec<_>, _>>()
+        .map(Some)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    LParen,
+    RParen,
+    Comma,
+    Str(String),
+    Word(String),
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, ODataError> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {}
+            '(' => tokens.push(Token::LParen),
+            ')' => tokens.push(Token::RParen),
+            ',' => tokens.push(Token::Comma),
+            '\'' => {
+                let mut s = String::new();
+                loop {
+                    match chars.next() {
+                        Some('\'') if chars.peek() == Some(&'\'') => {
+                            chars.next();
+                            s.push('\'');
+                        }
+                        Some('\'') => break,
+                        Some(c) => s.push(c),
+                        None => Err(ODataError::new("Unterminated string literal"))?,
+                    }
+                }
+                tokens.push(Token::Str(s));
+            }
+            c => {
+                let mut word = c.to_string();
+                while let Some(c) = chars.next_if(|c| !c.is_whitespace() && !matches!(c, '(' | ')' | ',' | '\'')) {
+                    word.push(c);
+                }
+                tokens.push(Token::Word(word));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+/// A parsed `$filter` expression with the properties resolved to columns
+#[derive(Debug, Clone)]
+enum FilterNode {
+    And(Box<FilterNode>, Box<FilterNode>),
+    Or(Box<FilterNode>, Box<FilterNode>),
+    Not(Box<FilterNode>),
+    Compare(ColumnRef, BinOper, sea_query::Value),
+    IsNull(ColumnRef),
+    IsNotNull(ColumnRef),
+    In(ColumnRef, Vec<sea_query::Value>),
+    Like(ColumnRef, String),
+}
+
+impl FilterNode {
+    fn to_expr(&self) -> SimpleExpr {
+        let col = |col: &ColumnRef| Expr::col(col.clone());
+        match self {
+            FilterNode::And(lhs, rhs) => lhs.to_expr().and(rhs.to_expr()),
+            FilterNode::Or(lhs, rhs) => lhs.to_expr().or(rhs.to_expr()),
+            FilterNode::Not(node) => node.to_expr().not(),
+            FilterNode::Compare(c, op, value) => col(c).binary(*op, value.clone()),
+            FilterNode::IsNull(c) => col(c).is_null(),
+            FilterNode::IsNotNull(c) => col(c).is_not_null(),
+            FilterNode::In(c, values) => col(c).is_in(values.clone()),
+            FilterNode::Like(c, pattern) => col(c).like(LikeExpr::new(pattern).escape('\\')),
+        }
+    }
+}
+
+/// A recursive descent parser for `$filter` expressions
+struct FilterParser<'a> {
+    tokens: Vec<Token>,
+    pos: usize,
+    properties: &'a [ODataProperty],
+}
+
+impl<'a> FilterParser<'a> {
+    fn new(input: &str, properties: &'a [ODataProperty]) -> Result<Self, ODataError> {
+        Ok(Self {
+            tokens: tokenize(input)?,
+            pos: 0,
+            properties,
+        })
+    }
+
+    fn parse(mut self) -> Result<FilterNode, ODataError> {
+        let node = self.parse_or()?;
+        match self.next() {
+            None => Ok(node),
+            Some(token) => Err(unexpected(Some(token))),
+        }
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn next_if_word(&mut self, word: &str) -> bool {
+        if matches!(self.peek(), Some(Token::Word(w)) if w == word) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), ODataError> {
+        match self.next() {
+            Some(token) if token == expected => Ok(()),
+            token => Err(unexpected(token)),
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<FilterNode, ODataError> {
+        let mut node = self.parse_and()?;
+        while self.next_if_word("or") {
+            node = FilterNode::Or(Box::new(node), Box::new(self.parse_and()?));
+        }
+        Ok(node)
+    }
+
+    fn parse_and(&mut self) -> Result<FilterNode, ODataError> {
+        let mut node = self.parse_unary()?;
+        while self.next_if_word("and") {
+            node = FilterNode::And(Box::new(node), Box::new(self.parse_unary()?));
+        }
+        Ok(node)
+    }
+
+    fn parse_unary(&mut self) -> Result<FilterNode, ODataError> {
+        if self.next_if_word("not") {
+            Ok(FilterNode::Not(Box::new(self.parse_unary()?)))
+        } else {
+            self.parse_primary()
+        }
+    }
+
+    fn parse_primary(&mut self) -> Result<FilterNode, ODataError> {
+        match self.next() {
+            Some(Token::LParen) => {
+                let node = self.parse_or()?;
+                self.expect(Token::RParen)?;
+                Ok(node)
+            }
+            Some(Token::Word(word)) if self.peek() == Some(&Token::LParen) => self.parse_function(&word),
+            Some(Token::Word(word)) => self.parse_comparison(&word),
+            token => Err(unexpected(token)),
+        }
+    }
+
+    fn parse_property(&self, name: &str) -> Result<(ColumnRef, DecodeFn), ODataError> {
+        let (col, decode) = find_column(self.properties, name)?;
+        Ok((col.clone(), *decode))
+    }
+
+    fn parse_function(&mut self, function: &str) -> Result<FilterNode, ODataError> {
+        self.expect(Token::LParen)?;
+        let col = match self.next() {
+            Some(Token::Word(name)) => self.parse_property(&name)?.0,
+            token => Err(unexpected(token))?,
+        };
+        self.expect(Token::Comma)?;
+        let value = match self.next() {
+            Some(Token::Str(s)) => escape_like(&s),
+            token => Err(unexpected(token))?,
+        };
+        self.expect(Token::RParen)?;
+        let pattern = match function {
+            "contains" => format!("%{}%", value),
+            "startswith" => format!("{}%", value),
+            "endswith" => format!("%{}", value),
+            _ => Err(ODataError::new(format!("Unsupported function `{}`", function)))?,
+        };
+        Ok(FilterNode::Like(col, pattern))
+    }
+
+    fn parse_comparison(&mut self, name: &str) -> Result<FilterNode, ODataError> {
+        let (col, decode) = self.parse_property(name)?;
+        let op = match self.next() {
+            Some(Token::Word(op)) => op,
+            token => Err(unexpected(token))?,
+        };
+        if op == "in" {
+            self.expect(Token::LParen)?;
+            let mut values = Vec::new();
+            loop {
+                let literal = self.parse_literal()?;
+                values.push(decode_literal(name, decode, literal)?);
+                match self.next() {
+                    Some(Token::Comma) => continue,
+                    Some(Token::RParen) => break,
+                    token => Err(unexpected(token))?,
+                }
+            }
+            return Ok(FilterNode::In(col, values));
+        }
+        let literal = self.parse_literal()?;
+        if literal.is_null() {
+            return match op.as_str() {
+                "eq" => Ok(FilterNode::IsNull(col)),
+                "ne" => Ok(FilterNode::IsNotNull(col)),
+                _ => Err(ODataError::new(format!("Operator `{}` cannot be used with null", op))),
+            };
+        }
+        let op = match op.as_str() {
+            "eq" => BinOper::Equal,
+            "ne" => BinOper::NotEqual,
+            "gt" => BinOper::GreaterThan,
+            "ge" => BinOper::GreaterThanOrEqual,
+            "lt" => BinOper::SmallerThan,
+            "le" => BinOper::SmallerThanOrEqual,
+            _ => Err(ODataError::new(format!("Unsupported operator `{}`", op)))?,
+        };
+        Ok(FilterNode::Compare(col, op, decode_literal(name, decode, literal)?))
+    }
+
+    fn parse_literal(&mut self) -> Result<serde_json::Value, ODataError> {
+        match self.next() {
+            Some(Token::Str(s)) => Ok(serde_json::Value::String(s)),
+            Some(Token::Word(word)) => Ok(match word.as_str() {
+                "null" => serde_json::Value::Null,
+                "true" => serde_json::Value::Bool(true),
+                "false" => serde_json::Value::Bool(false),
+                _ => {
+                    if let Ok(i) = word.parse::<i64>() {
+                        serde_json::Value::from(i)
+                    } else if let Some(n) = word.parse::<f64>().ok().and_then(serde_json::Number::from_f64) {
+                        serde_json::Value::Number(n)
+                    } else {
+                        // date, time and guid literals are not quoted
+                        serde_json::Value::String(word)
+                    }
+                }
+            }),
+            token => Err(unexpected(token)),
+        }
+    }
+}
+
+fn decode_literal(name: &str, decode: DecodeFn, literal: serde_json::Value) -> Result<sea_query::Value, ODataError> {
+    decode(literal).map_err(|e| ODataError::new(format!("Invalid value for property `{}`: {}", name, e)))
+}
+
+fn escape_like(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+}
+
+fn unexpected(token: Option<Token>) -> ODataError {
+    match token {
+        None => ODataError::new("Unexpected end of $filter"),
+        Some(Token::LParen) => ODataError::new("Unexpected `(` in $filter"),
+        Some(Token::RParen) => ODataError::new("Unexpected `)` in $filter"),
+        Some(Token::Comma) => ODataError::new("Unexpected `,` in $filter"),
+        Some(Token::Str(s)) => ODataError::new(format!("Unexpected '{}' in $filter", s)),
+        Some(Token::Word(w)) => ODataError::new(format!("Unexpected `{}` in $filter", w)),
+    }
+}
+
+#[cfg(feature = "openapi")]
+mod openapi {
+    use std::borrow::Cow;
+
+    use utoipa::openapi::schema::{ArrayBuilder, ObjectBuilder, Type};
+    use utoipa::openapi::{Ref, RefOr, Schema};
+    use utoipa::{PartialSchema, ToSchema};
+
+    use super::{ODataEntity, ODataPage, ODataQuery};
+
+    impl<T: ODataEntity> PartialSchema for ODataQuery<T> {
+        fn schema() -> RefOr<Schema> {
+            let properties = T::odata_properties();
+            let names = properties.iter().map(|p| p.name()).collect::<Vec<_>>().join(", ");
+            let column_names = properties
+                .iter()
+                .filter(|p| p.column.is_some())
+                .map(|p| p.name())
+                .collect::<Vec<_>>()
+                .join(", ");
+            ObjectBuilder::new()
+                .property(
+                    "$filter",
+                    ObjectBuilder::new().schema_type(Type::String).description(Some(format!(
+                        "Filter expression, e.g. `name eq 'value' and count gt 10`. Properties: {}",
+                        column_names
+                    ))),
+                )
+                .property(
+                    "$orderby",
+                    ObjectBuilder::new().schema_type(Type::String).description(Some(format!(
+                        "Comma separated properties with optional `asc` or `desc`. Properties: {}",
+                        column_names
+                    ))),
+                )
+                .property(
+                    "$top",
+                    ObjectBuilder::new()
+                        .schema_type(Type::Integer)
+                        .minimum(Some(1))
+                        .description(Some("The maximum number of records to return")),
+                )
+                .property(
+                    "$skip",
+                    ObjectBuilder::new()
+                        .schema_type(Type::Integer)
+                        .minimum(Some(0))
+                        .description(Some("The number of records to skip")),
+                )
+                .property(
+                    "$select",
+                    ObjectBuilder::new().schema_type(Type::String).description(Some(format!(
+                        "Comma separated properties to include in the response. Properties: {}",
+                        names
+                    ))),
+                )
+                .property(
+                    "$count",
+                    ObjectBuilder::new()
+                        .schema_type(Type::Boolean)
+                        .description(Some("Include the total number of matching records as `@odata.count`")),
+                )
+                .into()
+        }
+    }
+
+    impl<T: ODataEntity + ToSchema> ToSchema for ODataQuery<T> {
+        fn name() -> Cow<'static, str> {
+            Cow::Owned(format!("{}ODataQuery", T::name()))
+        }
+    }
+
+    impl<T: ToSchema> PartialSchema for ODataPage<T> {
+        fn schema() -> RefOr<Schema> {
+            ObjectBuilder::new()
+                .property(
+                    "@odata.count",
+                    ObjectBuilder::new()
+                        .schema_type(Type::Integer)
+                        .description(Some("The total number of matching records when `$count=true`")),
+                )
+                .property("value", ArrayBuilder::new().items(Ref::from_schema_name(T::name())))
+                .required("value")
+                .property(
+                    "@odata.nextLink",
+                    ObjectBuilder::new()
+                        .schema_type(Type::String)
+                        .description(Some("A link to the next page, if any")),
+                )
+                .into()
+        }
+    }
+
+    impl<T: ToSchema> ToSchema for ODataPage<T> {
+        fn name() -> Cow<'static, str> {
+            Cow::Owned(format!("{}ODataPage", T::name()))
+        }
+
+        fn schemas(schemas: &mut Vec<(String, RefOr<Schema>)>) {
+            T::schemas(schemas);
+        }
+    }
+}
@@ -5,7 +5,11 @@ use utoipa::openapi::{Components, Content, HttpMethod, OpenApi, OpenApiBuilder,
 use utoipa::{PartialSchema, ToSchema};
 
 use crate::Entity;
-use crate::router::{EntityCollectionApi, ErrorResponse, Routable};
+use crate::fields::SelectFields;
+use crate::history::{HistoryEntity, HistoryRecord};
+use crate::router::{
+    AllOperations, CrudOperation, CrudOptions, EntityCollectionApi, ErrorResponse, ReadOnly, Routable,
+};
 
 const APPLICATION_JSON: &str = "application/json";
 
@@ -30,15 +34,163 @@ pub trait DeleteRouterDoc {
     fn delete_endpoint_doc(tag: Option<&str>) -> OpenApi;
 }
 
+/// The documentation of [`HistoryRouter::history_endpoint`](crate::router::HistoryRouter::history_endpoint)
+pub trait HistoryRouterDoc {
+    fn history_endpoint_doc(tag: Option<&str>) -> OpenApi;
+}
+
+/// The documentation of the endpoints of [`CrudRouter::crud_router`](crate::router::CrudRouter::crud_router)
+/// with the same [`CrudOptions`]
+///
+/// # Example
+///
+/// ```
+/// # use lazybe::macros::Entity;
+/// # use lazybe::openapi::CrudRouterDoc;
+/// # use lazybe::router::{CrudOperation, CrudOptions};
+/// # use serde::{Deserialize, Serialize};
+/// # use utoipa::ToSchema;
+/// #[derive(Serialize, Deserialize, ToSchema, Entity)]
+/// #[lazybe(table = "todo", endpoint = "/todos", derive_to_schema)]
+/// pub struct Todo {
+///     #[lazybe(primary_key)]
+///     pub id: i32,
+///     pub title: String,
+/// }
+///
+/// let opts = CrudOptions::new()
+///     .operations([CrudOperation::Get, CrudOperation::List])
+///     .tag("Todo")
+///     .operation_id(CrudOperation::Get, "getTodo");
+/// let openapi = Todo::crud_doc(&opts);
+///
+/// let todo = &openapi.paths.paths["/todos/{id}"];
+/// assert_eq!(
+///     todo.get.as_ref().unwrap().operation_id.as_deref(),
+///     Some("getTodo")
+/// );
+/// assert!(todo.patch.is_none() && todo.delete.is_none());
+/// assert!(openapi.paths.paths["/todos"].post.is_none());
+/// ```
+pub trait CrudRouterDoc: Sized {
+    fn crud_doc<Ops>(opts: &CrudOptions<Ops>) -> OpenApi
+    where
+        Ops: CrudEndpointDocs<Self>;
+}
+
+/// The documentation of the endpoints of a set of operations,
+/// like [`CrudEndpoints`](crate::router::CrudEndpoints) with the doc traits
+pub trait CrudEndpointDocs<T> {
+    /// The documentation of an operation, or `None` when the operation is not in the set
+    fn endpoint_doc(operation: CrudOperation, tag: Option<&str>) -> Option<OpenApi>;
+}
+
+impl<T> CrudEndpointDocs<T> for AllOperations
+where
+    T: GetRouterDoc + ListRouterDoc + CreateRouterDoc + UpdateRouterDoc + DeleteRouterDoc,
+{
+    fn endpoint_doc(operation: CrudOperation, tag: Option<&str>) -> Option<OpenApi> {
+        let doc = match operation {
+            CrudOperation::Get => T::get_endpoint_doc(tag),
+            CrudOperation::List => T::list_endpoint_doc(tag),
+            CrudOperation::Create => T::create_endpoint_doc(tag),
+            CrudOperation::Update => T::update_endpoint_doc(tag),
+            CrudOperation::Replace => T::replace_endpoint_doc(tag),
+            CrudOperation::Delete => T::delete_endpoint_doc(tag),
+        };
+        Some(doc)
+    }
+}
+
+impl<T> CrudEndpointDocs<T> for ReadOnly
+where
+    T: GetRouterDoc + ListRouterDoc,
+{
+    fn endpoint_doc(operation: CrudOperation, tag: Option<&str>) -> Option<OpenApi> {
+        match operation {
+            CrudOperation::Get => Some(T::get_endpoint_doc(tag)),
+            CrudOperation::List => Some(T::list_endpoint_doc(tag)),
+            _ => None,
+        }
+    }
+}
+
+impl<T> CrudRouterDoc for T
+where
+    T: Routable,
+{
+    fn crud_doc<Ops>(opts: &CrudOptions<Ops>) -> OpenApi
+    where
+        Ops: CrudEndpointDocs<T>,
+    {
+        let tag = opts.tag_name();
+        let openapi = opts
+            .selected_operations()
+            .filter_map(|operation| Some((operation, Ops::endpoint_doc(operation, tag)?)))
+            .map(|(operation, mut doc)| {
+                let (path, method) = match operation {
+                    CrudOperation::Get => (T::entity_path(), HttpMethod::Get),
+                    CrudOperation::List => (T::entity_collection_path(), HttpMethod::Get),
+                    CrudOperation::Create => (T::entity_collection_path(), HttpMethod::Post),
+                    CrudOperation::Update => (T::entity_path(), HttpMethod::Patch),
+                    CrudOperation::Replace => (T::entity_path(), HttpMethod::Put),
+                    CrudOperation::Delete => (T::entity_path(), HttpMethod::Delete),
+                };
+                if let Some(operation_id) = opts.operation_id_of(operation) {
+                    let doc_operation = doc.paths.paths.get_mut(path).and_then(|item| match method {
+                        HttpMethod::Get => item.get.as_mut(),
+                        HttpMethod::Post => item.post.as_mut(),
+                        HttpMethod::Patch => item.patch.as_mut(),
+                        HttpMethod::Put => item.put.as_mut(),
+                        HttpMethod::Delete => item.delete.as_mut(),
+                        _ => None,
+                    });
+                    if let Some(doc_operation) = doc_operation {
+                        doc_operation.operation_id = Some(operation_id.to_string());
+                    }
+                }
+                doc
+            })
+            .fold(OpenApiBuilder::new().build(), |openapi, doc| openapi.merge_from(doc));
+        match opts.path_prefix() {
+            Some(prefix) => prefix_paths(openapi, prefix),
+            None => openapi,
+        }
+    }
+}
+
+/// Prefix every documented path, e.g. for endpoints nested under `/api/v1` with [`axum::Router::nest`]
+///
+/// # Example
+///
+/// ```
+/// # use lazybe::openapi::prefix_paths;
+/// # use utoipa::openapi::{OpenApiBuilder, PathItem, Paths};
+/// let openapi = OpenApiBuilder::new()
+///     .paths(Paths::builder().path("/todos", PathItem::default()))
+///     .build();
+/// let openapi = prefix_paths(openapi, "/api/v1");
+/// assert!(openapi.paths.paths.contains_key("/api/v1/todos"));
+/// ```
+pub fn prefix_paths(mut openapi: OpenApi, prefix: &str) -> OpenApi {
+    let prefix = prefix.trim_end_matches('/');
+    openapi.paths.paths = std::mem::take(&mut openapi.paths.paths)
+        .into_iter()
+        .map(|(path, item)| (format!("{}{}", prefix, path), item))
+        .collect();
+    openapi
+}
+
 impl<T> GetRouterDoc for T
 where
-    T: Entity + Routable + ToSchema,
+    T: Entity + SelectFields + Routable + ToSchema,
 {
     fn get_endpoint_doc(tag: Option<&str>) -> OpenApi {
         let operation = {
             let mut builder = Operation::builder()
                 .summary(Some(format!("Get {} by ID", <T as ToSchema>::name())))
                 .parameter(Parameter::new("id"))
+                .fields_param::<T>()
                 .json_response::<T>(StatusCode::OK, "Entity retrieved successfully")
                 .error_response(StatusCode::BAD_REQUEST)
                 .error_response(StatusCode::NOT_FOUND)
@@ -68,7 +220,7 @@ where
 
 impl<T> ListRouterDoc for T
 where
-    T: Entity + EntityCollectionApi + Routable + ToSchema,
+    T: Entity + SelectFields + EntityCollectionApi + Routable + ToSchema,
     <T as EntityCollectionApi>::Resp: ToSchema,
     <T as EntityCollectionApi>::Query: ToSchema,
 {
@@ -77,6 +229,7 @@ where
             let mut builder = Operation::builder()
                 .summary(Some(format!("List {}", <T as ToSchema>::name())))
                 .query_object_param::<<T as EntityCollectionApi>::Query>()
+                .fields_param::<T>()
                 .json_response::<<T as EntityCollectionApi>::Resp>(StatusCode::OK, "Entities retrieved successfully")
                 .error_response(StatusCode::BAD_REQUEST)
                 .error_response(StatusCode::INTERNAL_SERVER_ERROR);
@@ -117,6 +270,8 @@ where
                 .json_request::<<T as Entity>::Create>()
                 .json_response::<T>(StatusCode::CREATED, "Entity created successfully")
                 .error_response(StatusCode::BAD_REQUEST)
+                .error_response(StatusCode::CONFLICT)
+                .error_response(StatusCode::UNPROCESSABLE_ENTITY)
                 .error_response(StatusCode::INTERNAL_SERVER_ERROR);
             if let Some(tag) = tag {
                 builder = builder.tag(tag);
@@ -154,6 +309,8 @@ where
                 .json_response::<T>(StatusCode::OK, "Entity updated successfully")
                 .error_response(StatusCode::BAD_REQUEST)
                 .error_response(StatusCode::NOT_FOUND)
+                .error_response(StatusCode::CONFLICT)
+                .error_response(StatusCode::UNPROCESSABLE_ENTITY)
                 .error_response(StatusCode::INTERNAL_SERVER_ERROR);
             if let Some(tag) = tag {
                 builder = builder.tag(tag);
@@ -184,6 +341,8 @@ where
                 .json_response::<T>(StatusCode::OK, "Entity replaced successfully")
                 .error_response(StatusCode::BAD_REQUEST)
                 .error_response(StatusCode::NOT_FOUND)
+                .error_response(StatusCode::CONFLICT)
+                .error_response(StatusCode::UNPROCESSABLE_ENTITY)
                 .error_response(StatusCode::INTERNAL_SERVER_ERROR);
             if let Some(tag) = tag {
                 builder = builder.tag(tag);
@@ -217,6 +376,8 @@ where
                 .parameter(Parameter::new("id"))
                 .response("200", Response::new("Entity deleted successfully"))
                 .error_response(StatusCode::BAD_REQUEST)
+                .error_response(StatusCode::NOT_FOUND)
+                .error_response(StatusCode::CONFLICT)
                 .error_response(StatusCode::INTERNAL_SERVER_ERROR);
             if let Some(tag) = tag {
                 builder = builder.tag(tag);
@@ -233,8 +394,42 @@ where
     }
 }
 
+impl<T> HistoryRouterDoc for T
+where
+    T: HistoryEntity + Routable + ToSchema,
+{
+    fn history_endpoint_doc(tag: Option<&str>) -> OpenApi {
+        let operation = {
+            let mut builder = Operation::builder()
+                .summary(Some(format!("Get the history of {} by ID", <T as ToSchema>::name())))
+                .parameter(Parameter::new("id"))
+                .json_response::<Vec<HistoryRecord>>(StatusCode::OK, "History retrieved successfully")
+                .error_response(StatusCode::BAD_REQUEST)
+                .error_response(StatusCode::INTERNAL_SERVER_ERROR);
+            if let Some(tag) = tag {
+                builder = builder.tag(tag);
+            }
+            builder.build()
+        };
+
+        let path = format!("{}/history", <T as Routable>::entity_path());
+        let paths = Paths::builder()
+            .path(path, PathItem::new(HttpMethod::Get, operation))
+            .build();
+
+        let components = {
+            let mut schemas = Vec::new();
+            <HistoryRecord as ToSchema>::schemas(&mut schemas);
+            Components::builder().schemas_from_iter(schemas).build()
+        };
+
+        OpenApiBuilder::new().paths(paths).components(Some(components)).build()
+    }
+}
+
 trait OperationBuilderExt {
     fn query_object_param<T: ToSchema>(self) -> OperationBuilder;
+    fn fields_param<T: SelectFields>(self) -> OperationBuilder;
     fn json_request<T: ToSchema>(self) -> OperationBuilder;
     fn json_response<T: ToSchema>(self, status: StatusCode, desc: &str) -> OperationBuilder;
     fn error_response(self, status: StatusCode) -> OperationBuilder;
@@ -254,6 +449,25 @@ impl OperationBuilderExt for OperationBuilder {
         result
     }
 
+    fn fields_param<T: SelectFields>(self) -> OperationBuilder {
+        let fields = <T as SelectFields>::field_columns()
+            .into_iter()
+            .map(|(field, _)| field)
+            .collect::<Vec<_>>()
+            .join(", ");
+        self.parameter(
+            Parameter::builder()
+                .parameter_in(ParameterIn::Query)
+                .name("fields")
+                .description(Some(format!(
+                    "Comma separated fields to include in the response. Unknown fields are rejected. Fields: {}",
+                    fields
+                )))
+                .schema(Some(String::schema()))
+                .build(),
+        )
+    }
+
     fn json_request<T: ToSchema>(self) -> OperationBuilder {
         self.request_body(Some(
             RequestBody::builder()
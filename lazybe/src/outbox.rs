@@ -0,0 +1,566 @@
+use std::fmt::Display;
+use std::marker::PhantomData;
+use std::ops::DerefMut;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use sea_query::{
+    Alias, ColumnDef, ColumnType, Cond, DynIden, Expr, Iden, InsertStatement, IntoIden, LockBehavior, LockType, Order,
+    Query, Table, TableCreateStatement, TableRef,
+};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use sqlx::{Database, Executor, FromRow, IntoArguments, Pool};
+
+use crate::db::DbCtx;
+use crate::{Entity, TableEntity};
+
+/// An entity whose changes are published as [`EntityEvent`]s through the outbox, derived by `#[lazybe(outbox)]`.
+///
+/// Every create, update and delete operation of [`entity::ops`](crate::entity::ops) writes its event
+/// to the outbox table in the same transaction as the change, so an event is stored if and only if the change is committed.
+/// The stored events are delivered by an [`OutboxWorker`].
+pub trait OutboxEntity: TableEntity + Serialize {
+    /// The payload of the events of the entity. Defaults to the entity serialized to JSON.
+    ///
+    /// An error fails the change, so that no change is made without its event.
+    fn outbox_payload(&self) -> Result<serde_json::Value, serde_json::Error> {
+        serde_json::to_value(self)
+    }
+}
+
+/// The outbox of an entity, provided by [`TableEntity::outbox`] for an [`OutboxEntity`]
+pub struct Outbox<T> {
+    entity: PhantomData<fn() -> T>,
+    payload: fn(&T) -> Result<serde_json::Value, serde_json::Error>,
+}
+
+impl<T: OutboxEntity> Outbox<T> {
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        Self {
+            entity: PhantomData,
+            payload: T::outbox_payload,
+        }
+    }
+}
+
+/// A change of an entity with the entity after the change, or before the change when it is deleted
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", content = "payload", rename_all = "lowercase")]
+pub enum EntityEvent<T> {
+    Created(T),
+    Updated(T),
+    Deleted(T),
+}
+
+impl<T> EntityEvent<T> {
+    /// The name of the event as stored in the `event` column
+    pub fn name(&self) -> &'static str {
+        match self {
+            EntityEvent::Created(_) => "created",
+            EntityEvent::Updated(_) => "updated",
+            EntityEvent::Deleted(_) => "deleted",
+        }
+    }
+
+    pub fn payload(&self) -> &T {
+        match self {
+            EntityEvent::Created(payload) | EntityEvent::Updated(payload) | EntityEvent::Deleted(payload) => payload,
+        }
+    }
+
+    pub fn into_payload(self) -> T {
+        match self {
+            EntityEvent::Created(payload) | EntityEvent::Updated(payload) | EntityEvent::Deleted(payload) => payload,
+        }
+    }
+
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> EntityEvent<U> {
+        match self {
+            EntityEvent::Created(payload) => EntityEvent::Created(f(payload)),
+            EntityEvent::Updated(payload) => EntityEvent::Updated(f(payload)),
+            EntityEvent::Deleted(payload) => EntityEvent::Deleted(f(payload)),
+        }
+    }
+}
+
+/// An event stored in the outbox
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OutboxMessage {
+    pub id: i64,
+    /// The name of the changed entity, see [`Entity::entity_name`]
+    pub entity: String,
+    /// The event with its JSON payload
+    pub event: EntityEvent<serde_json::Value>,
+    pub created_at: DateTime<Utc>,
+    /// The number of failed deliveries
+    pub attempts: i32,
+    /// The error of the last failed delivery
+    pub last_error: Option<String>,
+}
+
+impl OutboxMessage {
+    /// Whether the message is an event of the entity `T`
+    pub fn is_of<T: Entity>(&self) -> bool {
+        self.entity == T::entity_name()
+    }
+
+    /// Decode the payload of the event, e.g. to the entity that was changed
+    pub fn decode<T: DeserializeOwned>(&self) -> Result<EntityEvent<T>, serde_json::Error> {
+        let event = self.event.clone();
+        let payload = serde_json::from_value(event.payload().clone())?;
+        Ok(event.map(|_| payload))
+    }
+}
+
+#[derive(Iden)]
+enum OutboxColumn {
+    Id,
+    Entity,
+    Event,
+    Payload,
+    CreatedAt,
+    DispatchedAt,
+    Attempts,
+    LastError,
+    NextAttemptAt,
+    ParkedAt,
+}
+
+/// The outbox table, which is resolved by [`DbCtx::table_ref`] like any entity table
+pub fn table() -> DynIden {
+    Alias::new("lazybe_outbox").into_iden()
+}
+
+/// The `CREATE TABLE` statement of the outbox table.
+///
+/// An event is pending until `dispatched_at` is set, or until it is parked with `parked_at` after too many failed deliveries.
+/// It is not delivered before `next_attempt_at`, which is set when it is claimed by a worker or its delivery fails.
+///
+/// # Example
+///
+/// ```
+/// # use lazybe::outbox;
+/// # use sea_query::PostgresQueryBuilder;
+/// assert_eq!(
+///     outbox::create_table().to_string(PostgresQueryBuilder),
+///     [
+///         r#"CREATE TABLE "lazybe_outbox" ( "id" bigserial NOT NULL PRIMARY KEY, "entity" text NOT NULL,"#,
+///         r#""event" text NOT NULL, "payload" jsonb NOT NULL, "created_at" timestamp with time zone NOT NULL,"#,
+///         r#""dispatched_at" timestamp with time zone NULL, "attempts" integer NOT NULL DEFAULT 0, "last_error" text NULL,"#,
+///         r#""next_attempt_at" timestamp with time zone NULL, "parked_at" timestamp with time zone NULL )"#,
+///     ]
+///     .join(" ")
+/// );
+/// ```
+pub fn create_table() -> TableCreateStatement {
+    Table::create()
+        .table(table())
+        .col(
+            ColumnDef::new_with_type(OutboxColumn::Id, ColumnType::BigInteger)
+                .not_null()
+                .primary_key()
+                .auto_increment(),
+        )
+        .col(ColumnDef::new_with_type(OutboxColumn::Entity, ColumnType::Text).not_null())
+        .col(ColumnDef::new_with_type(OutboxColumn::Event, ColumnType::Text).not_null())
+        .col(ColumnDef::new_with_type(OutboxColumn::Payload, ColumnType::JsonBinary).not_null())
+        .col(ColumnDef::new_with_type(OutboxColumn::CreatedAt, ColumnType::TimestampWithTimeZone).not_null())
+        .col(ColumnDef::new_with_type(OutboxColumn::DispatchedAt, ColumnType::TimestampWithTimeZone).null())
+        .col(
+            ColumnDef::new_with_type(OutboxColumn::Attempts, ColumnType::Integer)
+                .not_null()
+                .default(0),
+        )
+        .col(ColumnDef::new_with_type(OutboxColumn::LastError, ColumnType::Text).null())
+        .col(ColumnDef::new_with_type(OutboxColumn::NextAttemptAt, ColumnType::TimestampWithTimeZone).null())
+        .col(ColumnDef::new_with_type(OutboxColumn::ParkedAt, ColumnType::TimestampWithTimeZone).null())
+        .to_owned()
+}
+
+/// Writes the events of the changes made with a context to the outbox
+pub(crate) struct OutboxWriter<T> {
+    outbox: Outbox<T>,
+    table: TableRef,
+}
+
+impl<T: TableEntity> OutboxWriter<T> {
+    /// The writer of the events of `T`, or `None` when the changes of `T` are not published
+    pub(crate) fn new<Ctx: DbCtx<Db>, Db>(ctx: &Ctx) -> Option<Self> {
+        Some(Self {
+            outbox: <T as TableEntity>::outbox()?,
+            table: ctx.table_ref(table()),
+        })
+    }
+
+    /// The statement storing an event. Fails when the payload cannot be serialized.
+    pub(crate) fn insert_statement(&self, event: EntityEvent<&T>) -> Result<InsertStatement, sqlx::Error> {
+        let name = event.name();
+        let payload = (self.outbox.payload)(event.into_payload()).map_err(|e| sqlx::Error::Encode(Box::new(e)))?;
+        Ok(Query::insert()
+            .into_table(self.table.clone())
+            .columns([
+                OutboxColumn::Entity,
+                OutboxColumn::Event,
+                OutboxColumn::Payload,
+                OutboxColumn::CreatedAt,
+            ])
+            .values_panic([T::entity_name().into(), name.into(), payload.into(), Utc::now().into()])
+            .to_owned())
+    }
+}
+
+/// Delivers the events of the outbox to other services, e.g. by publishing them to a message broker
+///
+/// # Example
+///
+/// ```
+/// # use lazybe::outbox::{OutboxDispatcher, OutboxMessage};
+/// /// Prints the events instead of publishing them
+/// struct PrintDispatcher;
+///
+/// impl OutboxDispatcher for PrintDispatcher {
+///     type Error = std::convert::Infallible;
+///
+///     async fn dispatch(&self, message: &OutboxMessage) -> Result<(), Self::Error> {
+///         println!(
+///             "{} {}: {}",
+///             message.entity,
+///             message.event.name(),
+///             message.event.payload()
+///         );
+///         Ok(())
+///     }
+/// }
+/// ```
+pub trait OutboxDispatcher {
+    type Error: Display;
+
+    /// Deliver a message. The message is retried after a backoff when the delivery fails,
+    /// and it can be delivered more than once, so it should be idempotent for the receiver, e.g. by the message ID.
+    fn dispatch(&self, message: &OutboxMessage) -> impl Future<Output = Result<(), Self::Error>> + Send;
+}
+
+/// Polls the outbox and delivers the pending events with an [`OutboxDispatcher`].
+///
+/// A poll claims a batch of pending events in a short transaction, then delivers them after the commit
+/// and marks each of them as delivered or failed, so no locks are held while the dispatcher waits for a receiver.
+/// A claimed event is not claimed again before the [claim timeout](OutboxWorker::claim_timeout),
+/// after which the events of a worker that stopped in the middle of a poll are claimed by the next poll.
+/// An event is therefore delivered at least once.
+///
+/// When a delivery fails, the failure is recorded on the event and the event is retried after an exponential
+/// [backoff](OutboxWorker::backoff). After the [maximum number of attempts](OutboxWorker::max_attempts),
+/// the event is parked with its last error and is only retried after [`OutboxWorker::requeue_parked`].
+///
+/// # Order
+///
+/// The events of a poll are delivered in the order they were stored, but the order is given up in two cases
+/// so that the outbox keeps flowing:
+///
+/// - A failed event is retried after its backoff, while the events stored after it are delivered in the meantime.
+///   Otherwise a single event that cannot be delivered would block all the others.
+/// - Several workers can poll the same outbox. A poll skips the events claimed by another poll
+///   (with `SKIP LOCKED` on Postgres and MySQL), so the batches of different workers are delivered concurrently.
+///
+/// Receivers that depend on the order should detect stale events themselves,
+/// e.g. by comparing the [`OutboxMessage::id`] with the last one received for the same record.
+///
+/// # Example
+///
+/// ```
+/// # use lazybe::db::DbOps;
+/// # use lazybe::db::sqlite::SqliteDbCtx;
+/// # use lazybe::macros::Entity;
+/// # use lazybe::outbox::{self, EntityEvent, OutboxDispatcher, OutboxMessage, OutboxWorker};
+/// # use lazybe::schema::TableSchema;
+/// # use sea_query::SqliteQueryBuilder;
+/// # use serde::{Deserialize, Serialize};
+/// # use sqlx::{Executor, SqlitePool};
+/// #[derive(Serialize, Deserialize, Entity)]
+/// #[lazybe(table = "book", outbox)]
+/// pub struct Book {
+///     #[lazybe(primary_key)]
+///     pub id: i32,
+///     pub title: String,
+/// }
+///
+/// struct PrintDispatcher;
+///
+/// impl OutboxDispatcher for PrintDispatcher {
+///     type Error = std::convert::Infallible;
+///
+///     async fn dispatch(&self, message: &OutboxMessage) -> Result<(), Self::Error> {
+///         if let Ok(EntityEvent::Created(book)) = message.decode::<Book>() {
+///             println!("created {}", book.title);
+///         }
+///         Ok(())
+///     }
+/// }
+///
+/// # #[tokio::main]
+/// # async fn main() -> anyhow::Result<()> {
+/// let pool = SqlitePool::connect("sqlite::memory:").await?;
+/// pool.execute(Book::create_table().to_string(SqliteQueryBuilder).as_str())
+///     .await?;
+/// pool.execute(
+///     outbox::create_table()
+///         .to_string(SqliteQueryBuilder)
+///         .as_str(),
+/// )
+/// .await?;
+///
+/// // the event is stored with the book
+/// let input = CreateBook {
+///     title: "Dune".to_string(),
+/// };
+/// SqliteDbCtx.create::<Book>(&pool, input).await?;
+///
+/// let worker = OutboxWorker::new(SqliteDbCtx, pool, PrintDispatcher);
+/// assert_eq!(worker.dispatch_pending().await?, 1);
+///
+/// // or keep polling in the background
+/// tokio::spawn(worker.run());
+/// # Ok(())
+/// # }
+/// ```
+pub struct OutboxWorker<Ctx, Db: Database, D> {
+    ctx: Ctx,
+    pool: Pool<Db>,
+    dispatcher: D,
+    batch_size: u64,
+    poll_interval: Duration,
+    max_attempts: i32,
+    backoff: (Duration, Duration),
+    claim_timeout: Duration,
+}
+
+impl<Ctx, Db, D> OutboxWorker<Ctx, Db, D>
+where
+    Ctx: DbCtx<Db> + Send + Sync,
+    Db: Database,
+    D: OutboxDispatcher + Send + Sync,
+    for<'q> <Db as Database>::Arguments<'q>: IntoArguments<'q, Db>,
+    for<'c> &'c mut <Db as Database>::Connection: Executor<'c, Database = Db>,
+    row::OutboxRow: for<'r> FromRow<'r, Db::Row> + Send + Unpin,
+{
+    /// A worker delivering up to 100 events per poll and polling every second when the outbox is drained.
+    /// An event is parked after 10 failed deliveries, which are retried after 1 second doubling up to 1 hour,
+    /// and a claimed event is claimed again after 5 minutes.
+    pub fn new(ctx: Ctx, pool: Pool<Db>, dispatcher: D) -> Self {
+        Self {
+            ctx,
+            pool,
+            dispatcher,
+            batch_size: 100,
+            poll_interval: Duration::from_secs(1),
+            max_attempts: 10,
+            backoff: (Duration::from_secs(1), Duration::from_secs(60 * 60)),
+            claim_timeout: Duration::from_secs(5 * 60),
+        }
+    }
+
+    /// The maximum number of events delivered per poll
+    pub fn batch_size(self, batch_size: u64) -> Self {
+        Self {
+            batch_size: batch_size.max(1),
+            ..self
+        }
+    }
+
+    /// The delay before the next poll when the previous poll did not fill a batch or failed
+    pub fn poll_interval(self, poll_interval: Duration) -> Self {
+        Self { poll_interval, ..self }
+    }
+
+    /// The number of failed deliveries after which an event is parked
+    pub fn max_attempts(self, max_attempts: u32) -> Self {
+        Self {
+            max_attempts: i32::try_from(max_attempts.max(1)).unwrap_or(i32::MAX),
+            ..self
+        }
+    }
+
+    /// The delay before retrying a failed delivery, which starts at `initial` and doubles with every failure up to `max`
+    pub fn backoff(self, initial: Duration, max: Duration) -> Self {
+        Self {
+            backoff: (initial, max.max(initial)),
+            ..self
+        }
+    }
+
+    /// The time that a poll has to deliver the events it claims, after which they are claimed by another poll.
+    /// It should be longer than the delivery of a batch takes, otherwise the events are delivered twice.
+    pub fn claim_timeout(self, claim_timeout: Duration) -> Self {
+        Self { claim_timeout, ..self }
+    }
+
+    /// Deliver a batch of pending events and mark the delivered ones. Returns the number of delivered events.
+    pub async fn dispatch_pending(&self) -> Result<usize, sqlx::Error> {
+        let table = self.ctx.table_ref(table());
+        let mut delivered = 0;
+        for row in self.claim().await? {
+            let (id, attempts, entity) = (row.id, row.attempts, row.entity.clone());
+            // an event that cannot be read is a failed delivery, so that it is parked instead of failing every poll
+            let result = match OutboxMessage::try_from(row) {
+                Ok(message) => self.dispatcher.dispatch(&message).await.map_err(|e| e.to_string()),
+                Err(e) => Err(e.to_string()),
+            };
+            let now = Utc::now();
+            let mut update = Query::update();
+            update
+                .table(table.clone())
+                .and_where(Expr::col(OutboxColumn::Id).eq(id));
+            match result {
+                Ok(()) => {
+                    update.value(OutboxColumn::DispatchedAt, now);
+                    delivered += 1;
+                }
+                Err(e) => {
+                    let attempts = attempts.saturating_add(1);
+                    tracing::warn!(id, entity, attempts, "Failed to dispatch an outbox event: {}", e);
+                    update
+                        .value(OutboxColumn::Attempts, attempts)
+                        .value(OutboxColumn::LastError, e);
+                    if attempts >= self.max_attempts {
+                        tracing::error!(
+                            id,
+                            entity,
+                            attempts,
+                            "Parked an outbox event after too many failed deliveries"
+                        );
+                        update.value(OutboxColumn::ParkedAt, now);
+                    } else {
+                        update.value(OutboxColumn::NextAttemptAt, later(now, self.backoff_delay(attempts)));
+                    }
+                }
+            };
+            let update = update.to_string(self.ctx.query_builder());
+            sqlx::query(&update).execute(&self.pool).await?;
+        }
+        Ok(delivered)
+    }
+
+    /// Retry the parked events from scratch, e.g. after the receiver that rejected them is fixed
+    pub async fn requeue_parked(&self) -> Result<(), sqlx::Error> {
+        let update = Query::update()
+            .table(self.ctx.table_ref(table()))
+            .value(OutboxColumn::Attempts, 0)
+            .value(OutboxColumn::NextAttemptAt, None::<DateTime<Utc>>)
+            .value(OutboxColumn::ParkedAt, None::<DateTime<Utc>>)
+            .and_where(Expr::col(OutboxColumn::ParkedAt).is_not_null())
+            .to_string(self.ctx.query_builder());
+        sqlx::query(&update).execute(&self.pool).await?;
+        Ok(())
+    }
+
+    /// Keep delivering the pending events. A database error is reported with an `ERROR` event and retried by the next poll.
+    pub async fn run(self) {
+        loop {
+            match self.dispatch_pending().await {
+                Ok(delivered) if delivered as u64 == self.batch_size => continue,
+                Ok(_) => {}
+                Err(e) => tracing::error!("Failed to dispatch outbox events: {}", e),
+            }
+            tokio::time::sleep(self.poll_interval).await;
+        }
+    }
+
+    /// Claim a batch of the events that are due in the order they were stored.
+    /// The claim is committed before the events are delivered, which releases their locks.
+    async fn claim(&self) -> Result<Vec<row::OutboxRow>, sqlx::Error> {
+        let table = self.ctx.table_ref(table());
+        let now = Utc::now();
+        let select_query = Query::select()
+            .columns([
+                OutboxColumn::Id,
+                OutboxColumn::Entity,
+                OutboxColumn::Event,
+                OutboxColumn::Payload,
+                OutboxColumn::CreatedAt,
+                OutboxColumn::Attempts,
+                OutboxColumn::LastError,
+            ])
+            .from(table.clone())
+            .and_where(Expr::col(OutboxColumn::DispatchedAt).is_null())
+            .and_where(Expr::col(OutboxColumn::ParkedAt).is_null())
+            .cond_where(
+                Cond::any()
+                    .add(Expr::col(OutboxColumn::NextAttemptAt).is_null())
+                    .add(Expr::col(OutboxColumn::NextAttemptAt).lte(now)),
+            )
+            .order_by(OutboxColumn::Id, Order::Asc)
+            .limit(self.batch_size)
+            .lock_with_behavior(LockType::Update, LockBehavior::SkipLocked)
+            .to_string(self.ctx.query_builder());
+
+        let mut tx = self.pool.begin().await?;
+        let rows: Vec<row::OutboxRow> = sqlx::query_as(&select_query).fetch_all(tx.deref_mut()).await?;
+        if !rows.is_empty() {
+            let claim_query = Query::update()
+                .table(table)
+                .value(OutboxColumn::NextAttemptAt, later(now, self.claim_timeout))
+                .and_where(Expr::col(OutboxColumn::Id).is_in(rows.iter().map(|row| row.id)))
+                .to_string(self.ctx.query_builder());
+            sqlx::query(&claim_query).execute(tx.deref_mut()).await?;
+        }
+        tx.commit().await?;
+        Ok(rows)
+    }
+
+    /// The delay before retrying an event that failed `attempts` times
+    fn backoff_delay(&self, attempts: i32) -> Duration {
+        let (initial, max) = self.backoff;
+        let doublings = u32::try_from(attempts.saturating_sub(1)).unwrap_or_default().min(31);
+        initial.saturating_mul(1 << doublings).min(max)
+    }
+}
+
+/// The time after a delay, saturating at the maximum time
+fn later(now: DateTime<Utc>, delay: Duration) -> DateTime<Utc> {
+    chrono::TimeDelta::from_std(delay)
+        .ok()
+        .and_then(|delay| now.checked_add_signed(delay))
+        .unwrap_or(DateTime::<Utc>::MAX_UTC)
+}
+
+pub(crate) mod row {
+    use chrono::{DateTime, Utc};
+    use sqlx::types::Json;
+
+    use super::{EntityEvent, OutboxMessage};
+
+    #[derive(sqlx::FromRow)]
+    pub struct OutboxRow {
+        pub id: i64,
+        pub entity: String,
+        pub event: String,
+        pub payload: Json<serde_json::Value>,
+        pub created_at: DateTime<Utc>,
+        pub attempts: i32,
+        pub last_error: Option<String>,
+    }
+
+    impl TryFrom<OutboxRow> for OutboxMessage {
+        type Error = sqlx::Error;
+
+        fn try_from(row: OutboxRow) -> Result<Self, Self::Error> {
+            let payload = row.payload.0;
+            let event = match row.event.as_str() {
+                "created" => EntityEvent::Created(payload),
+                "updated" => EntityEvent::Updated(payload),
+                "deleted" => EntityEvent::Deleted(payload),
+                other => Err(sqlx::Error::Decode(format!("unknown outbox event `{}`", other).into()))?,
+            };
+            Ok(OutboxMessage {
+                id: row.id,
+                entity: row.entity,
+                event,
+                created_at: row.created_at,
+                attempts: row.attempts,
+                last_error: row.last_error,
+            })
+        }
+    }
+}
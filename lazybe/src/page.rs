@@ -1,3 +1,5 @@
+use serde::Serialize;
+
 #[derive(Debug, Clone)]
 pub struct PaginationInput {
     /// 0-index page number (0 is the first page)
@@ -17,7 +19,7 @@ impl PaginationInput {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct Page<T> {
     /// 0-index page number (0 is the first page)
     pub page: u32,
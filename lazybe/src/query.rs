@@ -1,20 +1,84 @@
-use crate::TableEntity;
 use crate::filter::Filter;
+use crate::{Entity, TableEntity};
 
 pub trait GetQuery: TableEntity {
     fn get_query(id: Self::Pk) -> sea_query::SelectStatement;
 }
 
 pub trait ListQuery: TableEntity {
+    /// Select the records matching the filter.
+    /// The joins required by the filter are added when the query is executed.
     fn list_query(filter: Filter<Self>) -> sea_query::SelectStatement;
 }
 
+/// The values written to a record besides the input, provided by the [`DbCtx`](crate::db::DbCtx) of an operation
+#[derive(Debug, Clone, Default)]
+pub struct Stamp {
+    /// The value of the `#[lazybe(tenant)]` field, see [`DbCtx::tenant`](crate::db::DbCtx::tenant)
+    pub tenant: Option<sea_query::Value>,
+    /// The value of the `#[lazybe(created_by)]` and `#[lazybe(updated_by)]` fields,
+    /// see [`DbCtx::actor`](crate::db::DbCtx::actor)
+    pub actor: Option<sea_query::Value>,
+}
+
+impl Stamp {
+    /// The tenant of a created `T`, or a configuration error if the stamp has no tenant
+    pub fn create_tenant<T: Entity>(&self) -> Result<sea_query::Value, sqlx::Error> {
+        self.tenant
+            .clone()
+            .ok_or_else(|| missing::<T>("created", "a tenant", "DbCtx::tenant"))
+    }
+
+    /// The actor of a created `T` with a field that is not optional, or a configuration error if the stamp has no actor
+    pub fn create_actor<T: Entity>(&self) -> Result<sea_query::Value, sqlx::Error> {
+        self.actor
+            .clone()
+            .ok_or_else(|| missing::<T>("created", "an actor", "DbCtx::actor"))
+    }
+
+    /// The actor of an updated `T` with a field that is not optional, or a configuration error if the stamp has no actor
+    pub fn update_actor<T: Entity>(&self) -> Result<sea_query::Value, sqlx::Error> {
+        self.actor
+            .clone()
+            .ok_or_else(|| missing::<T>("updated", "an actor", "DbCtx::actor"))
+    }
+}
+
+fn missing<T: Entity>(op: &str, value: &str, see: &str) -> sqlx::Error {
+    sqlx::Error::Configuration(
+        format!(
+            "`{}` cannot be {} without {}, see `{}`",
+            T::entity_name(),
+            op,
+            value,
+            see
+        )
+        .into(),
+    )
+}
+
+/// The statements of a create operation.
+/// Building them for an entity with a tenant column fails if the stamp has no tenant,
+/// and for an entity with a `created_by` or `updated_by` field that is not optional if the stamp has no actor.
 pub trait CreateQuery: TableEntity {
-    fn create_query(input: Self::Create) -> sea_query::InsertStatement;
+    fn create_query(input: Self::Create, stamp: &Stamp) -> Result<sea_query::InsertStatement, sqlx::Error>;
+
+    /// An insert statement without `RETURNING` and a query that reads the inserted record back.
+    /// This is used for databases that do not support `RETURNING` (e.g. MySQL).
+    fn create_then_get_query(
+        input: Self::Create,
+        stamp: &Stamp,
+    ) -> Result<(sea_query::InsertStatement, sea_query::SelectStatement), sqlx::Error>;
 }
 
+/// The statement of an update operation.
+/// Building it for an entity with an `updated_by` field that is not optional fails if the stamp has no actor.
 pub trait UpdateQuery: TableEntity {
-    fn update_query(id: Self::Pk, input: Self::Update) -> sea_query::UpdateStatement;
+    fn update_query(
+        id: Self::Pk,
+        input: Self::Update,
+        stamp: &Stamp,
+    ) -> Result<sea_query::UpdateStatement, sqlx::Error>;
 }
 
 pub trait DeleteQuery: TableEntity {
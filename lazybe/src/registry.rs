@@ -0,0 +1,468 @@
+use std::any::{Any, TypeId};
+
+use axum::Router;
+#[doc(hidden)]
+pub use inventory;
+#[cfg(feature = "openapi")]
+use utoipa::openapi::{OpenApi, OpenApiBuilder};
+
+/// An entity registered with the `register` attribute of the [`Entity`](crate::macros::Entity)
+/// or [`EntityEndpoint`](crate::macros::EntityEndpoint) macro.
+///
+/// Every endpoint that the entity supports is mounted by [`router`] and documented by [`openapi`].
+/// An [`Entity`](crate::macros::Entity) supports every CRUD endpoint, so the derive fails to compile
+/// when a router trait is not implemented for the state, e.g. a bound of the database is missing.
+/// An [`EntityEndpoint`](crate::macros::EntityEndpoint) only has the endpoints of the operations it implements,
+/// e.g. only the create endpoint when it implements [`CreateEntity`](crate::entity::ops::CreateEntity).
+///
+/// # Example
+///
+/// ```
+/// use lazybe::axum::Router;
+/// use lazybe::db::sqlite::SqliteDbCtx;
+/// use lazybe::macros::Entity;
+/// use lazybe::registry;
+/// use lazybe::router::RouteConfig;
+/// use serde::{Deserialize, Serialize};
+/// use sqlx::{Pool, Sqlite, SqlitePool};
+///
+/// #[derive(Serialize, Deserialize, Entity)]
+/// #[lazybe(table = "todo", endpoint = "/todos", register = "AppState")]
+/// pub struct Todo {
+///     #[lazybe(primary_key)]
+///     pub id: i32,
+///     pub title: String,
+/// }
+///
+/// #[derive(Serialize, Deserialize, Entity)]
+/// #[lazybe(table = "tag", endpoint = "/tags", register = "AppState")]
+/// pub struct Tag {
+///     #[lazybe(primary_key)]
+///     pub id: i32,
+///     pub name: String,
+/// }
+///
+/// #[derive(Clone)]
+/// struct AppState {
+///     pool: SqlitePool,
+/// }
+///
+/// impl RouteConfig for AppState {
+///     type Ctx = SqliteDbCtx;
+///     type Db = Sqlite;
+///
+///     fn db_ctx(&self) -> (Self::Ctx, Pool<Self::Db>) {
+///         (SqliteDbCtx, self.pool.clone())
+///     }
+/// }
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let pool = SqlitePool::connect("sqlite::memory:").await?;
+///
+///     // The endpoints of both Todo and Tag
+///     let router: Router = registry::router::<AppState>().with_state(AppState { pool });
+///
+///     let names = registry::entities().map(|e| e.name()).collect::<Vec<_>>();
+///     assert_eq!(names, ["Tag", "Todo"]);
+///     Ok(())
+/// }
+/// ```
+pub struct EntityRegistration {
+    name: &'static str,
+    state: fn() -> TypeId,
+    router: fn() -> Box<dyn Any>,
+    #[cfg(feature = "openapi")]
+    doc: Option<fn(Option<&str>) -> OpenApi>,
+}
+
+inventory::collect!(EntityRegistration);
+
+impl EntityRegistration {
+    #[doc(hidden)]
+    pub const fn new(name: &'static str, state: fn() -> TypeId, router: fn() -> Box<dyn Any>) -> Self {
+        Self {
+            name,
+            state,
+            router,
+            #[cfg(feature = "openapi")]
+            doc: None,
+        }
+    }
+
+    #[cfg(feature = "openapi")]
+    #[doc(hidden)]
+    pub const fn with_doc(self, doc: fn(Option<&str>) -> OpenApi) -> Self {
+        Self { doc: Some(doc), ..self }
+    }
+
+    /// The name of the entity
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Whether the endpoints of the entity are mounted on a router with the state `S`
+    pub fn is_registered_for<S: 'static>(&self) -> bool {
+        (self.state)() == TypeId::of::<S>()
+    }
+}
+
+/// All registered entities ordered by their names
+pub fn entities() -> impl Iterator<Item = &'static EntityRegistration> {
+    let mut entities = inventory::iter::<EntityRegistration>.into_iter().collect::<Vec<_>>();
+    entities.sort_by_key(|e| e.name);
+    entities.into_iter()
+}
+
+/// A router with the endpoints of every entity registered for the state `S`
+pub fn router<S>() -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    entities()
+        .filter(|e| e.is_registered_for::<S>())
+        .filter_map(|e| (e.router)().downcast::<Router<S>>().ok())
+        .fold(Router::new(), |router, entity_router| router.merge(*entity_router))
+}
+
+/// The OpenAPI documentation of the endpoints of every entity registered for the state `S`.
+/// The operations are tagged with the entity name.
+///
+/// The endpoints of an entity are documented when the entity implements [`ToSchema`](utoipa::ToSchema).
+/// A warning is logged for each mounted endpoint that cannot be documented.
+/// When the [`router`] is nested under a prefix, document the same prefix with
+/// [`prefix_paths`](crate::openapi::prefix_paths).
+#[cfg(feature = "openapi")]
+#[doc(cfg(feature = "openapi"))]
+pub fn openapi<S: 'static>() -> OpenApi {
+    entities()
+        .filter(|e| e.is_registered_for::<S>())
+        .filter_map(|e| e.doc.map(|doc| doc(Some(e.name))))
+        .fold(OpenApiBuilder::new().build(), |openapi, doc| openapi.merge_from(doc))
+}
+
+/// Register an entity for a state. This is used by the `register` attribute of the derive macros.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __register_entity {
+    ($entity:ident, $state:ty) => {
+        const _: () = {
+            #[allow(unused_imports)]
+            use $crate::registry::__private::*;
+
+            fn state() -> ::std::any::TypeId {
+                ::std::any::TypeId::of::<$state>()
+            }
+
+            fn router() -> ::std::boxed::Box<dyn ::std::any::Any> {
+                let probe = &&Probe::<$entity, $state>::new();
+                let routers = [
+                    probe.get_routers(),
+                    probe.list_routers(),
+                    probe.create_routers(),
+                    probe.update_routers(),
+                    probe.delete_routers(),
+                    probe.history_routers(),
+                ];
+                if routers.iter().all(|r| r.is_empty()) {
+                    warn_no_endpoints::<$entity, $state>();
+                }
+                let router = routers
+                    .into_iter()
+                    .flatten()
+                    .fold($crate::axum::Router::<$state>::new(), |router, r| router.merge(r));
+                ::std::boxed::Box::new(router)
+            }
+
+            $crate::__register_entity_doc!($entity, $state);
+
+            $crate::registry::inventory::submit! {
+                __with_doc($crate::registry::EntityRegistration::new(stringify!($entity), state, router))
+            }
+        };
+    };
+    ($entity:ident, $state:ty, crud) => {
+        $crate::__register_entity!($entity, $state);
+
+        const _: () = {
+            use $crate::registry::__private::AssertCrudRouters;
+
+            let _ = <$entity as AssertCrudRouters<$state>>::ASSERT;
+        };
+    };
+}
+
+#[cfg(feature = "openapi")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __register_entity_doc {
+    ($entity:ident, $state:ty) => {
+        fn doc(tag: ::std::option::Option<&str>) -> $crate::registry::__private::OpenApi {
+            let probe = &&&Probe::<$entity, $state>::new();
+            let docs = [
+                probe.get_docs(tag),
+                probe.list_docs(tag),
+                probe.create_docs(tag),
+                probe.update_docs(tag),
+                probe.delete_docs(tag),
+                probe.history_docs(tag),
+            ];
+            docs.into_iter().flatten().fold(
+                $crate::registry::__private::OpenApiBuilder::new().build(),
+                |openapi, doc| openapi.merge_from(doc),
+            )
+        }
+
+        const fn __with_doc(
+            registration: $crate::registry::EntityRegistration,
+        ) -> $crate::registry::EntityRegistration {
+            registration.with_doc(doc)
+        }
+    };
+}
+
+#[cfg(not(feature = "openapi"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __register_entity_doc {
+    ($entity:ident, $state:ty) => {
+        const fn __with_doc(
+            registration: $crate::registry::EntityRegistration,
+        ) -> $crate::registry::EntityRegistration {
+            registration
+        }
+    };
+}
+
+/// Selects the endpoints an entity supports by autoref specialization.
+/// Called on `&&Probe`, a method of `&Probe` applies when the entity implements the router trait,
+/// otherwise the method of `Probe` returns nothing.
+#[doc(hidden)]
+pub mod __private {
+    use std::any::type_name;
+    use std::marker::PhantomData;
+
+    use axum::Router;
+
+    use crate::router::{CreateRouter, DeleteRouter, GetRouter, HistoryRouter, ListRouter, RouteConfig, UpdateRouter};
+
+    pub struct Probe<T, S>(PhantomData<fn() -> (T, S)>);
+
+    impl<T, S> Probe<T, S> {
+        #[allow(clippy::new_without_default)]
+        pub fn new() -> Self {
+            Self(PhantomData)
+        }
+    }
+
+    /// Implemented by an entity with the router of every CRUD operation.
+    /// An entity derived with `Entity` is asserted to implement it when it is registered.
+    pub trait AssertCrudRouters<S> {
+        const ASSERT: () = ();
+    }
+
+    impl<T, S> AssertCrudRouters<S> for T
+    where
+        S: RouteConfig,
+        T: GetRouter<S, S::Db>
+            + ListRouter<S, S::Db>
+            + CreateRouter<S, S::Db>
+            + UpdateRouter<S, S::Db>
+            + DeleteRouter<S, S::Db>,
+    {
+    }
+
+    pub fn warn_no_endpoints<T, S>() {
+        tracing::warn!(
+            entity = type_name::<T>(),
+            state = type_name::<S>(),
+            "The registered entity has no endpoints since it does not implement any router trait for the state"
+        );
+    }
+
+    macro_rules! probe_router {
+        ($router_trait:ident, $via:ident, $fallback:ident, $method:ident, [$($endpoint:ident),+]) => {
+            pub trait $via<S> {
+                fn $method(&self) -> Vec<Router<S>>;
+            }
+
+            impl<T, S> $via<S> for &Probe<T, S>
+            where
+                T: $router_trait<S, <S as RouteConfig>::Db>,
+                S: RouteConfig,
+            {
+                fn $method(&self) -> Vec<Router<S>> {
+                    vec![$(<T as $router_trait<S, <S as RouteConfig>::Db>>::$endpoint()),+]
+                }
+            }
+
+            pub trait $fallback<S> {
+                fn $method(&self) -> Vec<Router<S>> {
+                    Vec::new()
+                }
+            }
+
+            impl<T, S> $fallback<S> for Probe<T, S> {}
+        };
+    }
+
+    probe_router!(GetRouter, ViaGetRouter, NoGetRouter, get_routers, [get_endpoint]);
+    probe_router!(ListRouter, ViaListRouter, NoListRouter, list_routers, [list_endpoint]);
+    probe_router!(
+        CreateRouter,
+        ViaCreateRouter,
+        NoCreateRouter,
+        create_routers,
+        [create_endpoint]
+    );
+    probe_router!(
+        UpdateRouter,
+        ViaUpdateRouter,
+        NoUpdateRouter,
+        update_routers,
+        [update_endpoint, replace_endpoint]
+    );
+    probe_router!(
+        DeleteRouter,
+        ViaDeleteRouter,
+        NoDeleteRouter,
+        delete_routers,
+        [delete_endpoint]
+    );
+    probe_router!(
+        HistoryRouter,
+        ViaHistoryRouter,
+        NoHistoryRouter,
+        history_routers,
+        [history_endpoint]
+    );
+
+    #[cfg(feature = "openapi")]
+    pub use self::doc::*;
+
+    #[cfg(feature = "openapi")]
+    mod doc {
+        use std::any::type_name;
+
+        pub use utoipa::openapi::{OpenApi, OpenApiBuilder};
+
+        use super::Probe;
+        use crate::openapi::{
+            CreateRouterDoc, DeleteRouterDoc, GetRouterDoc, HistoryRouterDoc, ListRouterDoc, UpdateRouterDoc,
+        };
+        use crate::router::{
+            CreateRouter, DeleteRouter, GetRouter, HistoryRouter, ListRouter, RouteConfig, UpdateRouter,
+        };
+
+        // only the endpoints that are mounted are documented, called on `&&&Probe`.
+        // A mounted endpoint without the doc trait is warned about.
+        macro_rules! probe_doc {
+            (
+                $doc_trait:ident,
+                $router_trait:ident,
+                $via:ident,
+                $undocumented:ident,
+                $fallback:ident,
+                $method:ident,
+                [$($endpoint:ident),+]
+            ) => {
+                pub trait $via {
+                    fn $method(&self, tag: Option<&str>) -> Vec<OpenApi>;
+                }
+
+                impl<T, S> $via for &&Probe<T, S>
+                where
+                    T: $doc_trait + $router_trait<S, <S as RouteConfig>::Db>,
+                    S: RouteConfig,
+                {
+                    fn $method(&self, tag: Option<&str>) -> Vec<OpenApi> {
+                        vec![$(<T as $doc_trait>::$endpoint(tag)),+]
+                    }
+                }
+
+                pub trait $undocumented {
+                    fn $method(&self, tag: Option<&str>) -> Vec<OpenApi>;
+                }
+
+                impl<T, S> $undocumented for &Probe<T, S>
+                where
+                    T: $router_trait<S, <S as RouteConfig>::Db>,
+                    S: RouteConfig,
+                {
+                    fn $method(&self, _tag: Option<&str>) -> Vec<OpenApi> {
+                        tracing::warn!(
+                            entity = type_name::<T>(),
+                            endpoints = stringify!($($endpoint),+),
+                            "The endpoints are not documented since the entity does not implement {}, e.g. it is not `ToSchema`",
+                            stringify!($doc_trait)
+                        );
+                        Vec::new()
+                    }
+                }
+
+                pub trait $fallback {
+                    fn $method(&self, _tag: Option<&str>) -> Vec<OpenApi> {
+                        Vec::new()
+                    }
+                }
+
+                impl<T, S> $fallback for Probe<T, S> {}
+            };
+        }
+
+        probe_doc!(
+            GetRouterDoc,
+            GetRouter,
+            ViaGetRouterDoc,
+            UndocumentedGetRouter,
+            NoGetRouterDoc,
+            get_docs,
+            [get_endpoint_doc]
+        );
+        probe_doc!(
+            ListRouterDoc,
+            ListRouter,
+            ViaListRouterDoc,
+            UndocumentedListRouter,
+            NoListRouterDoc,
+            list_docs,
+            [list_endpoint_doc]
+        );
+        probe_doc!(
+            CreateRouterDoc,
+            CreateRouter,
+            ViaCreateRouterDoc,
+            UndocumentedCreateRouter,
+            NoCreateRouterDoc,
+            create_docs,
+            [create_endpoint_doc]
+        );
+        probe_doc!(
+            UpdateRouterDoc,
+            UpdateRouter,
+            ViaUpdateRouterDoc,
+            UndocumentedUpdateRouter,
+            NoUpdateRouterDoc,
+            update_docs,
+            [update_endpoint_doc, replace_endpoint_doc]
+        );
+        probe_doc!(
+            DeleteRouterDoc,
+            DeleteRouter,
+            ViaDeleteRouterDoc,
+            UndocumentedDeleteRouter,
+            NoDeleteRouterDoc,
+            delete_docs,
+            [delete_endpoint_doc]
+        );
+        probe_doc!(
+            HistoryRouterDoc,
+            HistoryRouter,
+            ViaHistoryRouterDoc,
+            UndocumentedHistoryRouter,
+            NoHistoryRouterDoc,
+            history_docs,
+            [history_endpoint_doc]
+        );
+    }
+}
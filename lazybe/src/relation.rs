@@ -0,0 +1,136 @@
+use sea_query::{Alias, Cond, DynIden, Expr, IntoIden, JoinType, SelectStatement, TableRef};
+
+use crate::TableEntity;
+use crate::db::DbCtx;
+
+/// An entity that can be joined from another entity referencing its primary key.
+///
+/// This trait is derived by the [`Entity`](crate::macros::Entity) macro.
+/// A field with the `references = "..."` attribute uses it to expose the filter and sort columns
+/// of the referenced entity on the referencing entity (e.g. `BookFilter::author().country()`).
+pub trait Related: TableEntity {
+    /// A sibling type for creating filters on a joined entity from the `Src` entity
+    type JoinFilter<Src>;
+
+    /// A sibling type for creating sorts on a joined entity from the `Src` entity
+    type JoinSort<Src>;
+
+    /// The table where the entity is stored
+    fn join_table() -> DynIden;
+
+    /// The primary key column that a foreign key is joined on
+    fn join_key() -> DynIden;
+
+    fn join_filter<Src>(path: JoinPath) -> Self::JoinFilter<Src>;
+
+    fn join_sort<Src>(path: JoinPath) -> Self::JoinSort<Src>;
+}
+
+/// A `LEFT JOIN` of a related table on a foreign key.
+#[derive(Debug, Clone)]
+pub struct Join {
+    table: DynIden,
+    alias: String,
+    from: (DynIden, DynIden),
+    key: DynIden,
+    tenant: Option<DynIden>,
+}
+
+impl Join {
+    /// The alias of the joined table. Joins with the same alias are the same join.
+    pub fn alias(&self) -> &str {
+        &self.alias
+    }
+
+    /// Add this join to a select statement, without scoping the joined table to a tenant
+    pub fn apply(&self, stmt: &mut SelectStatement) {
+        let alias = Alias::new(&self.alias).into_iden();
+        stmt.join_as(
+            JoinType::LeftJoin,
+            TableRef::Table(self.table.clone()),
+            alias.clone(),
+            Expr::col(self.from.clone()).equals((alias, self.key.clone())),
+        );
+    }
+
+    /// Add this join to a select statement of an operation in the context.
+    /// The joined table is resolved with [`DbCtx::table_ref`], and the joined records of an entity
+    /// with a tenant column are restricted to [`DbCtx::tenant`], so that no records of another tenant are matched.
+    pub fn apply_in<Ctx: DbCtx<Db>, Db>(&self, stmt: &mut SelectStatement, ctx: &Ctx) {
+        let alias = Alias::new(&self.alias).into_iden();
+        let mut on = Cond::all().add(Expr::col(self.from.clone()).equals((alias.clone(), self.key.clone())));
+        if let Some(column) = &self.tenant {
+            on = on.add(match ctx.tenant() {
+                Some(tenant) => Expr::col((alias.clone(), column.clone())).eq(tenant),
+                None => Expr::val(1).eq(0),
+            });
+        }
+        stmt.join_as(JoinType::LeftJoin, ctx.table_ref(self.table.clone()), alias, on);
+    }
+}
+
+impl PartialEq for Join {
+    fn eq(&self, other: &Self) -> bool {
+        self.alias == other.alias
+    }
+}
+
+/// A chain of joins starting from the table of the queried entity.
+#[derive(Debug, Clone)]
+pub struct JoinPath {
+    alias: DynIden,
+    joins: Vec<Join>,
+}
+
+impl JoinPath {
+    /// Start a path from the table of the queried entity
+    pub fn new(table: DynIden) -> Self {
+        Self {
+            alias: table,
+            joins: Vec::new(),
+        }
+    }
+
+    /// Join the entity `T` by the foreign key column `fk` on the current end of the path.
+    ///
+    /// The joined table is aliased by the relation names along the path (e.g. `book__author`),
+    /// so the same relation is joined only once and the same table can be joined through different relations.
+    pub fn join<T: Related>(self, relation: &str, fk: DynIden) -> Self {
+        let alias = format!("{}__{}", self.alias.to_string(), relation);
+        let join = Join {
+            table: T::join_table(),
+            alias: alias.clone(),
+            from: (self.alias, fk),
+            key: T::join_key(),
+            tenant: T::tenant_column(),
+        };
+        let mut joins = self.joins;
+        joins.push(join);
+        Self {
+            alias: Alias::new(alias).into_iden(),
+            joins,
+        }
+    }
+
+    /// The alias of the table at the end of the path
+    pub fn alias(&self) -> DynIden {
+        self.alias.clone()
+    }
+
+    pub fn joins(&self) -> &[Join] {
+        &self.joins
+    }
+
+    pub fn into_joins(self) -> Vec<Join> {
+        self.joins
+    }
+}
+
+/// Append joins to a list, skipping the ones that are already present
+pub(crate) fn merge_joins(joins: &mut Vec<Join>, other: impl IntoIterator<Item = Join>) {
+    for join in other {
+        if !joins.contains(&join) {
+            joins.push(join);
+        }
+    }
+}
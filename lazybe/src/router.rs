@@ -1,15 +1,30 @@
-use axum::http::{Method, StatusCode};
+use std::convert::Infallible;
+use std::marker::PhantomData;
+use std::pin::Pin;
+
+use axum::extract::{FromRequestParts, MatchedPath, OriginalUri, Path, Query, State};
+use axum::http::request::Parts;
+use axum::http::{Extensions, Method, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{delete, get, patch, post, put};
 use axum::{Json, Router};
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use sqlx::{Database, Pool};
 use uuid::Uuid;
 
-use crate::Entity;
-use crate::db::DbOps;
+use crate::db::{
+    ActorDbCtx, DbCtx, DbOps, IsolationLevel, TenantDbCtx, TransactionError, TransactionOptions, with_transaction,
+};
+use crate::entity::ops::{
+    CreateEntity, DeleteEntity, GetEntity, GetHistory, GetPartialEntity, ListEntity, ListPartialEntity, UpdateEntity,
+};
+use crate::fields::{Fields, Partial, SelectFields};
 use crate::filter::Filter;
+use crate::history::HistoryRecord;
 use crate::page::{Page, PaginationInput};
 use crate::sort::Sort;
+use crate::{Entity, Error};
 
 /// A subset of properties outlined in
 /// <https://www.rfc-editor.org/rfc/rfc9457#name-members-of-a-problem-detail>
@@ -58,6 +73,9 @@ impl ErrorResponse {
 /// sorting, filtering, and pagination logic.
 ///
 /// ```
+/// # use axum::Json;
+/// # use axum::response::{IntoResponse, Response};
+/// # use lazybe::fields::{Fields, Partial};
 /// # use lazybe::filter::Filter;
 /// # use lazybe::macros::Entity;
 /// # use lazybe::page::{Page, PaginationInput};
@@ -88,6 +106,15 @@ impl ErrorResponse {
 ///         page.data
 ///     }
 ///
+///     fn partial_page_response(
+///         page: Page<Partial<Self>>,
+///         _input: &Self::Query,
+///         _fields: &Fields<Self>,
+///         _path: &str,
+///     ) -> Response {
+///         Json(page.data).into_response()
+///     }
+///
 ///     fn page_input(input: &Self::Query) -> Option<PaginationInput> {
 ///         Some(PaginationInput {
 ///             // API uses 1-index, lazybe uses 0-index
@@ -120,6 +147,25 @@ pub trait EntityCollectionApi: Sized {
     /// Defines how a page should be translated into a collection response.
     fn page_response(page: Page<Self>) -> Self::Resp;
 
+    /// Defines how a page should be translated into a collection response with access to the query parameters,
+    /// e.g. to link to the next page. Defaults to [`EntityCollectionApi::page_response`].
+    ///
+    /// The `path` is the path of the collection as requested, including the prefix it is mounted under.
+    #[allow(unused_variables)]
+    fn page_response_with_query(page: Page<Self>, input: &Self::Query, path: &str) -> Self::Resp {
+        Self::page_response(page)
+    }
+
+    /// Defines how a page of partial entities, selected with `?fields=...`, should be translated into a response.
+    /// It should use the same envelope as [`EntityCollectionApi::page_response`],
+    /// so that the shape of the response does not depend on `?fields=...`.
+    fn partial_page_response(
+        page: Page<Partial<Self>>,
+        input: &Self::Query,
+        fields: &Fields<Self>,
+        path: &str,
+    ) -> Response;
+
     /// Construct a pagination input from query parameters.
     /// Return `None` to list all records.
     fn page_input(input: &Self::Query) -> Option<PaginationInput>;
@@ -193,12 +239,43 @@ pub trait ValidationHook: Entity {
     }
 }
 
+/// A collection query without any parameters. Unknown query parameters are ignored.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct EmptyQuery {}
+
+/// A query parameter for selecting a subset of fields on get and list endpoints (e.g. `?fields=id,title`)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct FieldsQuery {
+    /// Comma separated fields to include in the response
+    pub fields: Option<String>,
+}
+
+impl FieldsQuery {
+    /// Parse the selected fields, or `None` to return whole entities
+    fn parse<T: SelectFields>(&self) -> Result<Option<Fields<T>>, (StatusCode, Json<ErrorResponse>)> {
+        self.fields.as_deref().map(Fields::parse).transpose().map_err(|e| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse::new("Invalid fields").with_detail(&e.to_string())),
+            )
+        })
+    }
+}
+
 /// Describes a URL path for an [`Entity`]
 pub trait Routable {
     /// A URL path for an entity with its ID (e.g. `/books/{id}`)
     fn entity_path() -> &'static str;
     /// A URL path for a collection of entity (e.g. `/books`)
     fn entity_collection_path() -> &'static str;
+
+    /// The isolation level of the transactions of the create, update and delete endpoints.
+    /// Defaults to `None`, which uses the isolation level of [`RouteConfig::transaction_options`].
+    fn isolation_level() -> Option<IsolationLevel> {
+        None
+    }
 }
 
 pub trait GetRouter<S, Db> {
@@ -222,12 +299,682 @@ pub trait DeleteRouter<S, Db> {
     fn delete_endpoint() -> Router<S>;
 }
 
+/// The `GET /{collection}/{id}/history` endpoint of a `#[lazybe(history)]` entity.
+/// It is not part of [`CrudRouter`], so merge it with the other endpoints.
+///
+/// # Example
+///
+/// ```
+/// use lazybe::axum::Router;
+/// use lazybe::db::sqlite::SqliteDbCtx;
+/// use lazybe::macros::Entity;
+/// use lazybe::router::{CrudOptions, CrudRouter, HistoryRouter, RouteConfig};
+/// use serde::{Deserialize, Serialize};
+/// use sqlx::{Pool, Sqlite, SqlitePool};
+///
+/// #[derive(Serialize, Deserialize, Entity)]
+/// #[lazybe(table = "todo", endpoint = "/todos", history)]
+/// pub struct Todo {
+///     #[lazybe(primary_key)]
+///     pub id: i32,
+///     pub title: String,
+/// }
+///
+/// #[derive(Clone)]
+/// struct AppState {
+///     pool: SqlitePool,
+/// }
+///
+/// impl RouteConfig for AppState {
+///     type Ctx = SqliteDbCtx;
+///     type Db = Sqlite;
+///
+///     fn db_ctx(&self) -> (Self::Ctx, Pool<Self::Db>) {
+///         (SqliteDbCtx, self.pool.clone())
+///     }
+/// }
+///
+/// let router: Router<AppState> = Router::new()
+///     .merge(Todo::crud_router(&CrudOptions::new()))
+///     .merge(Todo::history_endpoint());
+/// ```
+pub trait HistoryRouter<S, Db> {
+    fn history_endpoint() -> Router<S>;
+}
+
+/// An endpoint of [`CrudRouter`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CrudOperation {
+    /// `GET /{collection}/{id}`
+    Get,
+    /// `GET /{collection}`
+    List,
+    /// `POST /{collection}`
+    Create,
+    /// `PATCH /{collection}/{id}`
+    Update,
+    /// `PUT /{collection}/{id}`
+    Replace,
+    /// `DELETE /{collection}/{id}`
+    Delete,
+}
+
+impl CrudOperation {
+    pub const ALL: [CrudOperation; 6] = [
+        CrudOperation::Get,
+        CrudOperation::List,
+        CrudOperation::Create,
+        CrudOperation::Update,
+        CrudOperation::Replace,
+        CrudOperation::Delete,
+    ];
+}
+
+/// Selects the endpoints of [`CrudRouter::crud_router`] and how they are documented by
+/// [`CrudRouterDoc::crud_doc`](crate::openapi::CrudRouterDoc::crud_doc).
+/// Passing the same options to both keeps the router and the documentation in sync.
+///
+/// By default, every [`CrudOperation`] is selected without a tag, operation IDs, or a path prefix.
+///
+/// The type parameter is the set of operations the entity must support, see [`CrudEndpoints`].
+/// Selected operations outside of the set are left out.
+#[derive(Debug, Clone)]
+pub struct CrudOptions<Ops = AllOperations> {
+    operations: Vec<CrudOperation>,
+    tag: Option<String>,
+    operation_ids: Vec<(CrudOperation, String)>,
+    prefix: Option<String>,
+    supported: PhantomData<fn() -> Ops>,
+}
+
+impl<Ops> Default for CrudOptions<Ops> {
+    fn default() -> Self {
+        Self {
+            operations: CrudOperation::ALL.to_vec(),
+            tag: None,
+            operation_ids: Vec::new(),
+            prefix: None,
+            supported: PhantomData,
+        }
+    }
+}
+
+impl CrudOptions {
+    /// Options for an entity that supports every operation
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl CrudOptions<ReadOnly> {
+    /// Options for an entity that only supports [`CrudOperation::Get`] and [`CrudOperation::List`]
+    pub fn read_only() -> Self {
+        Self::default()
+    }
+}
+
+impl<Ops> CrudOptions<Ops> {
+    /// Select only these operations
+    pub fn operations(self, operations: impl IntoIterator<Item = CrudOperation>) -> Self {
+        Self {
+            operations: operations.into_iter().collect(),
+            ..self
+        }
+    }
+
+    /// Leave out an operation
+    pub fn without(self, operation: CrudOperation) -> Self {
+        let operations = self.operations.iter().copied().filter(|op| *op != operation).collect();
+        Self { operations, ..self }
+    }
+
+    /// The tag of every documented operation
+    pub fn tag(self, tag: &str) -> Self {
+        Self {
+            tag: Some(tag.to_string()),
+            ..self
+        }
+    }
+
+    /// The `operationId` of a documented operation, e.g. `getTodo`
+    pub fn operation_id(mut self, operation: CrudOperation, operation_id: &str) -> Self {
+        self.operation_ids.retain(|(op, _)| *op != operation);
+        self.operation_ids.push((operation, operation_id.to_string()));
+        self
+    }
+
+    /// Mount the endpoints under a path prefix, e.g. `/api/v1`.
+    /// The documented paths get the same prefix.
+    ///
+    /// # Example
+    ///
+    /// An entity served by two API versions, where only the second one supports deletion.
+    ///
+    /// ```
+    /// # use lazybe::macros::Entity;
+    /// # use lazybe::openapi::CrudRouterDoc;
+    /// # use lazybe::router::{CrudOperation, CrudOptions};
+    /// # use serde::{Deserialize, Serialize};
+    /// # use utoipa::ToSchema;
+    /// #[derive(Serialize, Deserialize, ToSchema, Entity)]
+    /// #[lazybe(table = "todo", endpoint = "/todos", derive_to_schema)]
+    /// pub struct Todo {
+    ///     #[lazybe(primary_key)]
+    ///     pub id: i32,
+    ///     pub title: String,
+    /// }
+    ///
+    /// let v1 = CrudOptions::new()
+    ///     .prefix("/api/v1")
+    ///     .without(CrudOperation::Delete);
+    /// let v2 = CrudOptions::new().prefix("/api/v2");
+    ///
+    /// // `Todo::crud_router(&v1).merge(Todo::crud_router(&v2))` serves both versions
+    /// let openapi = Todo::crud_doc(&v1).merge_from(Todo::crud_doc(&v2));
+    ///
+    /// let paths = openapi.paths.paths.keys().collect::<Vec<_>>();
+    /// assert_eq!(
+    ///     paths,
+    ///     [
+    ///         "/api/v1/todos",
+    ///         "/api/v1/todos/{id}",
+    ///         "/api/v2/todos",
+    ///         "/api/v2/todos/{id}"
+    ///     ]
+    /// );
+    /// assert!(openapi.paths.paths["/api/v1/todos/{id}"].delete.is_none());
+    /// assert!(openapi.paths.paths["/api/v2/todos/{id}"].delete.is_some());
+    /// ```
+    pub fn prefix(self, prefix: &str) -> Self {
+        let prefix = prefix.trim_matches('/');
+        Self {
+            prefix: Some(format!("/{}", prefix)).filter(|_| !prefix.is_empty()),
+            ..self
+        }
+    }
+
+    /// The selected operations in the order of [`CrudOperation::ALL`]
+    pub(crate) fn selected_operations(&self) -> impl Iterator<Item = CrudOperation> + '_ {
+        CrudOperation::ALL.into_iter().filter(|op| self.operations.contains(op))
+    }
+
+    pub(crate) fn tag_name(&self) -> Option<&str> {
+        self.tag.as_deref()
+    }
+
+    pub(crate) fn path_prefix(&self) -> Option<&str> {
+        self.prefix.as_deref()
+    }
+
+    pub(crate) fn operation_id_of(&self, operation: CrudOperation) -> Option<&str> {
+        self.operation_ids
+            .iter()
+            .find(|(op, _)| *op == operation)
+            .map(|(_, operation_id)| operation_id.as_str())
+    }
+}
+
+/// Every [`CrudOperation`], which requires all router traits
+#[derive(Debug, Clone, Copy)]
+pub struct AllOperations;
+
+/// The [`CrudOperation::Get`] and [`CrudOperation::List`] operations, which require [`GetRouter`] and [`ListRouter`]
+#[derive(Debug, Clone, Copy)]
+pub struct ReadOnly;
+
+/// A set of operations of [`CrudOptions`] with the router traits that an entity `T` must implement.
+///
+/// Implement it for a type of your own to support another set of operations, e.g. get, list and create.
+///
+/// ```
+/// # use lazybe::axum::Router;
+/// # use lazybe::router::{CreateRouter, CrudEndpoints, CrudOperation, GetRouter, ListRouter};
+/// pub struct AppendOnly;
+///
+/// impl<T, S, Db> CrudEndpoints<T, S, Db> for AppendOnly
+/// where
+///     T: GetRouter<S, Db> + ListRouter<S, Db> + CreateRouter<S, Db>,
+/// {
+///     fn endpoint(operation: CrudOperation) -> Option<Router<S>> {
+///         match operation {
+///             CrudOperation::Get => Some(T::get_endpoint()),
+///             CrudOperation::List => Some(T::list_endpoint()),
+///             CrudOperation::Create => Some(T::create_endpoint()),
+///             _ => None,
+///         }
+///     }
+/// }
+/// ```
+pub trait CrudEndpoints<T, S, Db> {
+    /// The endpoint of an operation, or `None` when the operation is not in the set
+    fn endpoint(operation: CrudOperation) -> Option<Router<S>>;
+}
+
+impl<T, S, Db> CrudEndpoints<T, S, Db> for AllOperations
+where
+    T: GetRouter<S, Db> + ListRouter<S, Db> + CreateRouter<S, Db> + UpdateRouter<S, Db> + DeleteRouter<S, Db>,
+{
+    fn endpoint(operation: CrudOperation) -> Option<Router<S>> {
+        let endpoint = match operation {
+            CrudOperation::Get => T::get_endpoint(),
+            CrudOperation::List => T::list_endpoint(),
+            CrudOperation::Create => T::create_endpoint(),
+            CrudOperation::Update => T::update_endpoint(),
+            CrudOperation::Replace => T::replace_endpoint(),
+            CrudOperation::Delete => T::delete_endpoint(),
+        };
+        Some(endpoint)
+    }
+}
+
+impl<T, S, Db> CrudEndpoints<T, S, Db> for ReadOnly
+where
+    T: GetRouter<S, Db> + ListRouter<S, Db>,
+{
+    fn endpoint(operation: CrudOperation) -> Option<Router<S>> {
+        match operation {
+            CrudOperation::Get => Some(T::get_endpoint()),
+            CrudOperation::List => Some(T::list_endpoint()),
+            _ => None,
+        }
+    }
+}
+
+/// All CRUD endpoints of an entity in a single router
+///
+/// # Example
+///
+/// ```
+/// # use lazybe::axum::Router;
+/// # use lazybe::db::sqlite::SqliteDbCtx;
+/// # use lazybe::macros::Entity;
+/// # use lazybe::router::{CrudOperation, CrudOptions, CrudRouter, RouteConfig};
+/// # use serde::{Deserialize, Serialize};
+/// # use sqlx::{Pool, Sqlite, SqlitePool};
+/// #[derive(Serialize, Deserialize, Entity)]
+/// #[lazybe(table = "todo", endpoint = "/todos")]
+/// pub struct Todo {
+///     #[lazybe(primary_key)]
+///     pub id: i32,
+///     pub title: String,
+/// }
+///
+/// #[derive(Clone)]
+/// struct AppState {
+///     pool: SqlitePool,
+/// }
+///
+/// impl RouteConfig for AppState {
+///     type Ctx = SqliteDbCtx;
+///     type Db = Sqlite;
+///
+///     fn db_ctx(&self) -> (Self::Ctx, Pool<Self::Db>) {
+///         (SqliteDbCtx, self.pool.clone())
+///     }
+/// }
+///
+/// // Todos cannot be replaced or deleted
+/// let opts = CrudOptions::new()
+///     .without(CrudOperation::Replace)
+///     .without(CrudOperation::Delete)
+///     .tag("Todo")
+///     .operation_id(CrudOperation::Get, "getTodo");
+///
+/// let router: Router<AppState> = Todo::crud_router(&opts);
+/// // With the openapi feature, `Todo::crud_doc(&opts)` documents the same endpoints
+/// ```
+///
+/// An entity that only supports some operations selects them by the type of the options,
+/// e.g. an entity that only implements [`GetEntity`] and [`ListEntity`] is mounted with [`CrudOptions::read_only`].
+pub trait CrudRouter<S, Db>: Sized {
+    fn crud_router<Ops>(opts: &CrudOptions<Ops>) -> Router<S>
+    where
+        Ops: CrudEndpoints<Self, S, Db>;
+}
+
+impl<T, S, Db> CrudRouter<S, Db> for T
+where
+    S: Clone + Send + Sync + 'static,
+{
+    fn crud_router<Ops>(opts: &CrudOptions<Ops>) -> Router<S>
+    where
+        Ops: CrudEndpoints<T, S, Db>,
+    {
+        let router = opts
+            .selected_operations()
+            .filter_map(Ops::endpoint)
+            .fold(Router::new(), |router, endpoint| router.merge(endpoint));
+        match opts.path_prefix() {
+            Some(prefix) => Router::new().nest(prefix, router),
+            None => router,
+        }
+    }
+}
+
 /// Describes how to extract a configuration from a shared [`axum`] state.
+///
+/// The router traits are implemented for any database and context that support the entity operations,
+/// so the context is not limited to the ones provided by lazybe.
+///
+/// # Example
+///
+/// A context that wraps [`SqliteDbCtx`](crate::db::sqlite::SqliteDbCtx) with additional request information.
+///
+/// ```
+/// # use lazybe::db::DbCtx;
+/// # use lazybe::db::sqlite::SqliteDbCtx;
+/// # use lazybe::router::RouteConfig;
+/// # use sqlx::{Pool, Sqlite, SqlitePool};
+/// #[derive(Clone)]
+/// struct AppCtx {
+///     request_id: Option<String>,
+/// }
+///
+/// impl DbCtx<Sqlite> for AppCtx {
+///     type Qb = <SqliteDbCtx as DbCtx<Sqlite>>::Qb;
+///
+///     fn query_builder(&self) -> Self::Qb {
+///         SqliteDbCtx.query_builder()
+///     }
+/// }
+///
+/// #[derive(Clone)]
+/// struct AppState {
+///     pool: SqlitePool,
+/// }
+///
+/// impl RouteConfig for AppState {
+///     type Ctx = AppCtx;
+///     type Db = Sqlite;
+///
+///     fn db_ctx(&self) -> (Self::Ctx, Pool<Self::Db>) {
+///         (AppCtx { request_id: None }, self.pool.clone())
+///     }
+/// }
+/// ```
+///
+/// A state that serves the get and list endpoints from a read replica,
+/// and from the primary within 5 seconds after a write by any request.
+///
+/// ```
+/// # use std::time::Duration;
+/// # use lazybe::db::ReplicatedPool;
+/// # use lazybe::db::sqlite::SqliteDbCtx;
+/// # use lazybe::router::RouteConfig;
+/// # use sqlx::{Pool, Sqlite, SqlitePool};
+/// #[derive(Clone)]
+/// struct AppState {
+///     pools: ReplicatedPool<Sqlite>,
+/// }
+///
+/// impl AppState {
+///     fn new(primary: SqlitePool, replica: SqlitePool) -> Self {
+///         let pools =
+///             ReplicatedPool::new(primary, replica).read_after_write(Duration::from_secs(5));
+///         Self { pools }
+///     }
+/// }
+///
+/// impl RouteConfig for AppState {
+///     type Ctx = SqliteDbCtx;
+///     type Db = Sqlite;
+///
+///     fn db_ctx(&self) -> (Self::Ctx, Pool<Self::Db>) {
+///         (SqliteDbCtx, self.pools.primary().clone())
+///     }
+///
+///     fn read_db_ctx(&self) -> (Self::Ctx, Pool<Self::Db>) {
+///         (SqliteDbCtx, self.pools.read().clone())
+///     }
+///
+///     fn after_write(&self) {
+///         self.pools.mark_write();
+///     }
+/// }
+/// ```
 pub trait RouteConfig {
-    type Ctx: DbOps<Self::Db>;
+    type Ctx: DbCtx<Self::Db> + DbOps<Self::Db>;
     type Db: Database;
 
     fn db_ctx(&self) -> (Self::Ctx, Pool<Self::Db>);
+
+    /// A context and a pool for read-only endpoints (get and list), e.g. connected to a read replica.
+    /// Defaults to [`RouteConfig::db_ctx`].
+    fn read_db_ctx(&self) -> (Self::Ctx, Pool<Self::Db>) {
+        self.db_ctx()
+    }
+
+    /// Called after a create, update or delete endpoint has committed a transaction,
+    /// e.g. to read from the primary for a while with [`ReplicatedPool::mark_write`](crate::db::ReplicatedPool::mark_write).
+    fn after_write(&self) {}
+
+    /// How the create, update and delete endpoints begin and retry their transactions.
+    /// The isolation level is overridden by [`Routable::isolation_level`] of the entity.
+    fn transaction_options(&self) -> TransactionOptions {
+        TransactionOptions::default()
+    }
+
+    /// The tenant of a request, e.g. from a header or the claims of a token, usually read by an extractor.
+    /// Every endpoint scopes its context to the tenant with [`TenantDbCtx`], see [`DbCtx::tenant`].
+    /// Return an error response to reject the request.
+    ///
+    /// Defaults to no tenant.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use lazybe::axum::extract::FromRequestParts;
+    /// # use lazybe::axum::http::StatusCode;
+    /// # use lazybe::axum::http::request::Parts;
+    /// # use lazybe::axum::response::{IntoResponse, Response};
+    /// # use lazybe::db::sqlite::SqliteDbCtx;
+    /// # use lazybe::router::RouteConfig;
+    /// # use sqlx::{Pool, Sqlite, SqlitePool};
+    /// struct TenantId(i32);
+    ///
+    /// impl<S: Sync> FromRequestParts<S> for TenantId {
+    ///     type Rejection = StatusCode;
+    ///
+    ///     async fn from_request_parts(parts: &mut Parts, _: &S) -> Result<Self, Self::Rejection> {
+    ///         parts
+    ///             .headers
+    ///             .get("x-tenant-id")
+    ///             .and_then(|value| value.to_str().ok()?.parse().ok())
+    ///             .map(TenantId)
+    ///             .ok_or(StatusCode::UNAUTHORIZED)
+    ///     }
+    /// }
+    ///
+    /// #[derive(Clone)]
+    /// struct AppState {
+    ///     pool: SqlitePool,
+    /// }
+    ///
+    /// impl RouteConfig for AppState {
+    ///     type Ctx = SqliteDbCtx;
+    ///     type Db = Sqlite;
+    ///
+    ///     fn db_ctx(&self) -> (Self::Ctx, Pool<Self::Db>) {
+    ///         (SqliteDbCtx, self.pool.clone())
+    ///     }
+    ///
+    ///     async fn tenant(&self, parts: &mut Parts) -> Result<Option<sea_query::Value>, Response> {
+    ///         let TenantId(tenant_id) = TenantId::from_request_parts(parts, self)
+    ///             .await
+    ///             .map_err(|e| e.into_response())?;
+    ///         Ok(Some(tenant_id.into()))
+    ///     }
+    /// }
+    /// ```
+    #[allow(unused_variables)]
+    fn tenant(&self, parts: &mut Parts) -> impl Future<Output = Result<Option<sea_query::Value>, Response>> + Send {
+        async { Ok(None) }
+    }
+
+    /// The actor of a request, e.g. a user inserted into the request extensions by an authentication middleware.
+    /// The create, update and delete endpoints act for the actor with [`ActorDbCtx`], see [`DbCtx::actor`].
+    ///
+    /// Defaults to no actor.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use lazybe::axum::http::Extensions;
+    /// # use lazybe::db::sqlite::SqliteDbCtx;
+    /// # use lazybe::router::RouteConfig;
+    /// # use sqlx::{Pool, Sqlite, SqlitePool};
+    /// /// Inserted by an authentication middleware
+    /// #[derive(Clone)]
+    /// struct User {
+    ///     id: i32,
+    /// }
+    ///
+    /// #[derive(Clone)]
+    /// struct AppState {
+    ///     pool: SqlitePool,
+    /// }
+    ///
+    /// impl RouteConfig for AppState {
+    ///     type Ctx = SqliteDbCtx;
+    ///     type Db = Sqlite;
+    ///
+    ///     fn db_ctx(&self) -> (Self::Ctx, Pool<Self::Db>) {
+    ///         (SqliteDbCtx, self.pool.clone())
+    ///     }
+    ///
+    ///     fn actor(&self, extensions: &Extensions) -> Option<sea_query::Value> {
+    ///         extensions.get::<User>().map(|user| user.id.into())
+    ///     }
+    /// }
+    /// ```
+    #[allow(unused_variables)]
+    fn actor(&self, extensions: &Extensions) -> Option<sea_query::Value> {
+        None
+    }
+}
+
+/// The actor of a request, see [`RouteConfig::actor`]
+struct RequestActor(Option<sea_query::Value>);
+
+impl<S> FromRequestParts<S> for RequestActor
+where
+    S: RouteConfig + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        Ok(RequestActor(state.actor(&parts.extensions)))
+    }
+}
+
+/// The tenant of a request, see [`RouteConfig::tenant`]
+struct RequestTenant(Option<sea_query::Value>);
+
+impl<S> FromRequestParts<S> for RequestTenant
+where
+    S: RouteConfig + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        state.tenant(parts).await.map(RequestTenant)
+    }
+}
+
+/// The route of a request including the prefix of a nested router, e.g. `/api/v1/todos/{id}`,
+/// or the path of the request when it was not routed
+struct RequestRoute(String);
+
+impl<S> FromRequestParts<S> for RequestRoute
+where
+    S: Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _: &S) -> Result<Self, Self::Rejection> {
+        let route = match parts.extensions.get::<MatchedPath>() {
+            Some(matched) => matched.as_str().to_string(),
+            None => parts.uri.path().to_string(),
+        };
+        Ok(RequestRoute(route))
+    }
+}
+
+/// The transaction options of the endpoints of an entity
+fn transaction_options<T: Routable, S: RouteConfig>(state: &S) -> TransactionOptions {
+    let options = state.transaction_options();
+    match <T as Routable>::isolation_level() {
+        Some(isolation_level) => options.isolation_level(isolation_level),
+        None => options,
+    }
+}
+
+/// An error of an endpoint transaction, which is retried when the database error [`is_retryable`](crate::db::is_retryable)
+enum EndpointError {
+    Db(Error),
+    Response((StatusCode, Json<ErrorResponse>)),
+}
+
+impl From<sqlx::Error> for EndpointError {
+    fn from(value: sqlx::Error) -> Self {
+        Self::Db(value.into())
+    }
+}
+
+impl TransactionError for EndpointError {
+    fn as_db_error(&self) -> Option<&sqlx::Error> {
+        match self {
+            EndpointError::Db(e) => Some(e.db_error()),
+            EndpointError::Response(_) => None,
+        }
+    }
+}
+
+/// The response of a constraint violation, which is caused by the request rather than a server failure.
+/// Returns `None` for any other error.
+fn violation_response(error: &Error, method: &Method) -> Option<(StatusCode, Json<ErrorResponse>)> {
+    let violation = error.violation()?;
+    let (status, title, detail) = match error {
+        Error::UniqueViolation(_) => (
+            StatusCode::CONFLICT,
+            "Conflict",
+            "An entity with the same value already exists",
+        ),
+        Error::ForeignKeyViolation(_) if method == Method::DELETE => (
+            StatusCode::CONFLICT,
+            "Conflict",
+            "The entity is still referenced by other entities",
+        ),
+        Error::ForeignKeyViolation(_) => (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "Invalid reference",
+            "A referenced entity does not exist",
+        ),
+        Error::NotNullViolation(_) => (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "Missing value",
+            "A required value is missing",
+        ),
+        Error::CheckViolation(_) => (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "Invalid value",
+            "A value does not satisfy a check constraint",
+        ),
+        Error::Database(_) => return None,
+    };
+    let detail = match (violation.constraint(), violation.columns()) {
+        (Some(constraint), _) => format!("{} (constraint `{}`).", detail, constraint),
+        (None, []) => format!("{}.", detail),
+        (None, columns) => format!("{} (column `{}`).", detail, columns.join("`, `")),
+    };
+    Some((status, Json(ErrorResponse::new(title).with_detail(&detail))))
+}
+
+/// Erase the type of a database operation future.
+/// This works around <https://github.com/rust-lang/rust/issues/100013> where the handler future
+/// of a generic handler cannot be proven [`Send`] when it holds the operation future.
+fn boxed<'a, O>(fut: impl Future<Output = O> + Send + 'a) -> Pin<Box<dyn Future<Output = O> + Send + 'a>> {
+    Box::pin(fut)
 }
 
 trait ResultExt<T> {
@@ -240,6 +987,36 @@ trait ResultExt<T> {
     ) -> Result<T, (StatusCode, Json<ErrorResponse>)>;
 }
 
+trait EndpointResultExt<T> {
+    /// Map a constraint violation to a client error and any other database error to `500 Internal Server Error`
+    fn map_db_err<U: Entity>(
+        self,
+        method: &Method,
+        url: &str,
+        msg: &str,
+        id: Option<&<U as Entity>::Pk>,
+    ) -> Result<T, (StatusCode, Json<ErrorResponse>)>;
+}
+
+impl<T> EndpointResultExt<T> for Result<T, EndpointError> {
+    fn map_db_err<U: Entity>(
+        self,
+        method: &Method,
+        url: &str,
+        msg: &str,
+        id: Option<&<U as Entity>::Pk>,
+    ) -> Result<T, (StatusCode, Json<ErrorResponse>)> {
+        match self {
+            Ok(value) => Ok(value),
+            Err(EndpointError::Db(e)) => match violation_response(&e, method) {
+                Some(resp) => Err(resp),
+                None => Err(e).map_err_500::<U>(method, url, msg, id),
+            },
+            Err(EndpointError::Response(resp)) => Err(resp),
+        }
+    }
+}
+
 impl<T, E: std::error::Error> ResultExt<T> for Result<T, E> {
     fn map_err_500<U: Entity>(
         self,
@@ -264,312 +1041,381 @@ impl<T, E: std::error::Error> ResultExt<T> for Result<T, E> {
     }
 }
 
-#[cfg(feature = "sqlite")]
-#[doc(cfg(feature = "sqlite"))]
-pub mod sqlite {
-    super::macros::axum_route_impl_imports!();
-    super::macros::axum_route_impl!(sqlx::Sqlite, crate::db::sqlite::SqliteDbCtx);
+impl<T, S, Db> GetRouter<S, Db> for T
+where
+    T: GetEntity<Db> + GetPartialEntity<Db> + SelectFields + Routable + Serialize + Send + 'static,
+    Db: Database,
+    S: RouteConfig<Db = Db> + Clone + Send + Sync + 'static,
+    <S as RouteConfig>::Ctx: Send + Sync,
+    <T as Entity>::Pk: DeserializeOwned + Send,
+{
+    fn get_endpoint() -> Router<S> {
+        let route = <T as Routable>::entity_path();
+        Router::new().route(route, get(get_endpoint_impl::<T, S, Db>))
+    }
 }
 
-#[cfg(feature = "postgres")]
-#[doc(cfg(feature = "postgres"))]
-pub mod postgres {
-    super::macros::axum_route_impl_imports!();
-    super::macros::axum_route_impl!(sqlx::Postgres, crate::db::postgres::PostgresDbCtx);
+async fn get_endpoint_impl<T, S, Db>(
+    Path(id): Path<<T as Entity>::Pk>,
+    State(state): State<S>,
+    RequestRoute(route): RequestRoute,
+    RequestTenant(tenant): RequestTenant,
+    Query(fields): Query<FieldsQuery>,
+) -> Result<Response, (StatusCode, Json<ErrorResponse>)>
+where
+    T: GetEntity<Db> + GetPartialEntity<Db> + SelectFields + Routable + Serialize + Send + 'static,
+    Db: Database,
+    S: RouteConfig<Db = Db> + Clone + Send + Sync + 'static,
+    <S as RouteConfig>::Ctx: Send + Sync,
+    <T as Entity>::Pk: DeserializeOwned + Send,
+{
+    let fields = fields.parse::<T>()?;
+    let (ctx, pool) = state.read_db_ctx();
+    let ctx = TenantDbCtx::new(ctx, tenant);
+    let method = Method::GET;
+    let url = route.as_str();
+    // a single read does not need an explicit transaction
+    let result = match fields {
+        Some(fields) => boxed(ctx.get_partial::<T>(&pool, id.clone(), fields))
+            .await
+            .map_err_500::<T>(&method, url, "Failed to get an entity from database", Some(&id))?
+            .map(|partial| Json(partial).into_response()),
+        None => boxed(ctx.get::<T>(&pool, id.clone()))
+            .await
+            .map_err_500::<T>(&method, url, "Failed to get an entity from database", Some(&id))?
+            .map(|entity| Json(entity).into_response()),
+    }
+    .ok_or((
+        StatusCode::NOT_FOUND,
+        Json(ErrorResponse::new("Not found").with_detail(&format!("An entity with id {:?} was not found.", id))),
+    ))?;
+    Ok(result)
 }
 
-mod macros {
-    macro_rules! axum_route_impl_imports {
-        () => {
-            use axum::extract::{Path, Query, State};
-            use axum::http::{Method, StatusCode};
-            use axum::routing::{delete, get, patch, post, put};
-            use axum::{Json, Router};
-            use serde::Serialize;
-            use serde::de::DeserializeOwned;
-
-            use super::{
-                CreateRouter, DeleteRouter, EntityCollectionApi, ErrorResponse, GetRouter, ListRouter, ResultExt,
-                Routable, RouteConfig, UpdateRouter, ValidationHook,
-            };
-            use crate::Entity;
-            use crate::db::DbOps;
-            use crate::entity::ops::{CreateEntity, DeleteEntity, GetEntity, ListEntity, UpdateEntity};
-        };
+impl<T, S, Db> ListRouter<S, Db> for T
+where
+    T: ListEntity<Db>
+        + ListPartialEntity<Db>
+        + SelectFields
+        + EntityCollectionApi
+        + Routable
+        + Serialize
+        + Send
+        + 'static,
+    Db: Database,
+    S: RouteConfig<Db = Db> + Clone + Send + Sync + 'static,
+    <S as RouteConfig>::Ctx: Send + Sync,
+    <T as EntityCollectionApi>::Query: Send,
+{
+    fn list_endpoint() -> Router<S> {
+        let route = <T as Routable>::entity_collection_path();
+        Router::new().route(route, get(list_endpoint_impl::<T, S, Db>))
     }
+}
 
-    macro_rules! axum_route_impl {
-        ($db_ty:ty, $ctx_ty:ty) => {
-            type DbImpl = $db_ty;
-            type CtxImpl = $ctx_ty;
-
-            impl<T, S> GetRouter<S, DbImpl> for T
-            where
-                T: GetEntity<DbImpl> + Routable + Serialize + Send + 'static,
-                S: RouteConfig<Ctx = CtxImpl, Db = DbImpl> + Clone + Send + Sync + 'static,
-                <T as Entity>::Pk: DeserializeOwned + Send,
-            {
-                fn get_endpoint() -> Router<S> {
-                    let route = <T as Routable>::entity_path();
-                    Router::new().route(route, get(get_endpoint_impl::<T, S>))
-                }
-            }
+async fn list_endpoint_impl<T, S, Db>(
+    State(state): State<S>,
+    RequestRoute(route): RequestRoute,
+    RequestTenant(tenant): RequestTenant,
+    Query(query): Query<<T as EntityCollectionApi>::Query>,
+    Query(fields): Query<FieldsQuery>,
+    OriginalUri(uri): OriginalUri,
+) -> Result<Response, (StatusCode, Json<ErrorResponse>)>
+where
+    T: ListEntity<Db>
+        + ListPartialEntity<Db>
+        + SelectFields
+        + EntityCollectionApi
+        + Routable
+        + Serialize
+        + Send
+        + 'static,
+    Db: Database,
+    S: RouteConfig<Db = Db> + Clone + Send + Sync + 'static,
+    <S as RouteConfig>::Ctx: Send + Sync,
+    <T as EntityCollectionApi>::Query: Send,
+{
+    let fields = fields.parse::<T>()?;
+    let (ctx, pool) = state.read_db_ctx();
+    let ctx = TenantDbCtx::new(ctx, tenant);
+    let method = Method::GET;
+    let url = route.as_str();
+    // the data and count queries are read-only, so they run on a pooled connection without an explicit transaction
+    let page_input = <T as EntityCollectionApi>::page_input(&query);
+    let filter_input = <T as EntityCollectionApi>::filter_input(&query);
+    let sort_input = <T as EntityCollectionApi>::sort_input(&query);
+    let response = match fields {
+        Some(fields) => {
+            let result = boxed(ctx.list_partial::<T>(&pool, filter_input, sort_input, page_input, fields.clone()))
+                .await
+                .map_err_500::<T>(&method, url, "Failed to list entities from database", None)?;
+            <T as EntityCollectionApi>::partial_page_response(result, &query, &fields, uri.path())
+        }
+        None => {
+            let result = boxed(ctx.list::<T>(&pool, filter_input, sort_input, page_input))
+                .await
+                .map_err_500::<T>(&method, url, "Failed to list entities from database", None)?;
+            Json(<T as EntityCollectionApi>::page_response_with_query(
+                result,
+                &query,
+                uri.path(),
+            ))
+            .into_response()
+        }
+    };
+    Ok(response)
+}
 
-            async fn get_endpoint_impl<T, S>(
-                Path(id): Path<<T as Entity>::Pk>,
-                State(state): State<S>,
-            ) -> Result<Json<T>, (StatusCode, Json<ErrorResponse>)>
-            where
-                T: GetEntity<DbImpl> + Routable + Serialize + Send + 'static,
-                S: RouteConfig<Ctx = CtxImpl, Db = DbImpl> + Clone + Send + 'static,
-                <T as Entity>::Pk: DeserializeOwned + Send,
-            {
-                let (ctx, pool) = state.db_ctx();
-                let method = Method::GET;
-                let url = <T as Routable>::entity_path();
-                let mut tx = pool.begin().await.map_err_500::<T>(
-                    &method,
-                    url,
-                    "Failed to acquire a database transaction",
-                    None,
-                )?;
-                let result = ctx
-                    .get::<T>(&mut tx, id.clone())
-                    .await
-                    .map_err_500::<T>(&method, url, "Failed to get an entity from database", Some(&id))?
-                    .ok_or((
-                        StatusCode::NOT_FOUND,
-                        Json(
-                            ErrorResponse::new("Not found")
-                                .with_detail(&format!("An entity with id {:?} was not found.", id)),
-                        ),
-                    ))?;
-                tx.commit()
-                    .await
-                    .map_err_500::<T>(&method, url, "Failed to commit a transaction", None)?;
-                Ok(Json(result))
-            }
+impl<T, S, Db> CreateRouter<S, Db> for T
+where
+    T: CreateEntity<Db> + ValidationHook + Routable + Serialize + Send + 'static,
+    Db: Database,
+    S: RouteConfig<Db = Db> + Clone + Send + Sync + 'static,
+    <S as RouteConfig>::Ctx: Send + Sync,
+    <T as Entity>::Create: DeserializeOwned + Clone + Send,
+{
+    fn create_endpoint() -> Router<S> {
+        let route = <T as Routable>::entity_collection_path();
+        Router::new().route(route, post(create_endpoint_impl::<T, S, Db>))
+    }
+}
 
-            impl<T, S> ListRouter<S, DbImpl> for T
-            where
-                T: ListEntity<DbImpl> + EntityCollectionApi + Routable + Serialize + Send + 'static,
-                S: RouteConfig<Ctx = CtxImpl, Db = DbImpl> + Clone + Send + Sync + 'static,
-                <T as EntityCollectionApi>::Query: Send,
-            {
-                fn list_endpoint() -> Router<S> {
-                    let route = <T as Routable>::entity_collection_path();
-                    Router::new().route(route, get(list_endpoint_impl::<T, S>))
-                }
-            }
+async fn create_endpoint_impl<T, S, Db>(
+    State(state): State<S>,
+    RequestRoute(route): RequestRoute,
+    RequestTenant(tenant): RequestTenant,
+    RequestActor(actor): RequestActor,
+    Json(input): Json<<T as Entity>::Create>,
+) -> Result<(StatusCode, Json<T>), (StatusCode, Json<ErrorResponse>)>
+where
+    T: CreateEntity<Db> + ValidationHook + Routable + Serialize + Send + 'static,
+    Db: Database,
+    S: RouteConfig<Db = Db> + Clone + Send + Sync + 'static,
+    <S as RouteConfig>::Ctx: Send + Sync,
+    <T as Entity>::Create: DeserializeOwned + Clone + Send,
+{
+    let (ctx, pool) = state.db_ctx();
+    let ctx = ActorDbCtx::new(TenantDbCtx::new(ctx, tenant), actor);
+    let method = Method::POST;
+    let url = route.as_str();
+    let options = transaction_options::<T, S>(&state);
 
-            async fn list_endpoint_impl<T, S>(
-                State(state): State<S>,
-                Query(query): Query<<T as EntityCollectionApi>::Query>,
-            ) -> Result<Json<<T as EntityCollectionApi>::Resp>, (StatusCode, Json<ErrorResponse>)>
-            where
-                T: ListEntity<DbImpl> + EntityCollectionApi + Routable + Serialize + Send + 'static,
-                S: RouteConfig<Ctx = CtxImpl, Db = DbImpl> + Clone + Send + 'static,
-                <T as EntityCollectionApi>::Query: Send,
-            {
-                let (ctx, pool) = state.db_ctx();
-                let method = Method::GET;
-                let url = <T as Routable>::entity_collection_path();
-                let mut tx = pool.begin().await.map_err_500::<T>(
-                    &method,
-                    url,
-                    "Failed to acquire a database transaction",
-                    None,
-                )?;
-                let page_input = <T as EntityCollectionApi>::page_input(&query);
-                let filter_input = <T as EntityCollectionApi>::filter_input(&query);
-                let sort_input = <T as EntityCollectionApi>::sort_input(&query);
-                let result = ctx
-                    .list::<T>(&mut tx, filter_input, sort_input, page_input)
-                    .await
-                    .map_err_500::<T>(&Method::GET, url, "Failed to list entities from database", None)?;
-                tx.commit()
-                    .await
-                    .map_err_500::<T>(&method, url, "Failed to commit a transaction", None)?;
-                let page_resp = <T as EntityCollectionApi>::page_response(result);
-                Ok(Json(page_resp))
-            }
+    <T as ValidationHook>::before_create(&input).map_err(|e| (StatusCode::BAD_REQUEST, Json(e)))?;
+    let result = boxed(with_transaction(&ctx, &pool, &options, move |ctx, tx| {
+        let input = input.clone();
+        Box::pin(async move {
+            let result = boxed(ctx.create::<T>(&mut *tx, input)).await?;
+            <T as ValidationHook>::after_create(&result)
+                .map_err(|e| EndpointError::Response((StatusCode::BAD_REQUEST, Json(e))))?;
+            Ok::<_, EndpointError>(result)
+        })
+    }))
+    .await
+    .map_db_err::<T>(&method, url, "Failed to create an entity in database", None)?;
+    state.after_write();
+    Ok((StatusCode::CREATED, Json(result)))
+}
 
-            impl<T, S> CreateRouter<S, DbImpl> for T
-            where
-                T: CreateEntity<DbImpl> + ValidationHook + Routable + Serialize + Send + 'static,
-                S: RouteConfig<Ctx = CtxImpl, Db = DbImpl> + Clone + Send + Sync + 'static,
-                <T as Entity>::Create: DeserializeOwned + Send,
-            {
-                fn create_endpoint() -> Router<S> {
-                    let route = <T as Routable>::entity_collection_path();
-                    Router::new().route(route, post(create_endpoint_impl::<T, S>))
-                }
-            }
+impl<T, S, Db> DeleteRouter<S, Db> for T
+where
+    T: DeleteEntity<Db> + Routable + Serialize + Send + 'static,
+    Db: Database,
+    S: RouteConfig<Db = Db> + Clone + Send + Sync + 'static,
+    <S as RouteConfig>::Ctx: Send + Sync,
+    <T as Entity>::Pk: DeserializeOwned + Send,
+{
+    fn delete_endpoint() -> Router<S> {
+        let route = <T as Routable>::entity_path();
+        Router::new().route(route, delete(delete_endpoint_impl::<T, S, Db>))
+    }
+}
 
-            async fn create_endpoint_impl<T, S>(
-                State(state): State<S>,
-                Json(input): Json<<T as Entity>::Create>,
-            ) -> Result<(StatusCode, Json<T>), (StatusCode, Json<ErrorResponse>)>
-            where
-                T: CreateEntity<DbImpl> + ValidationHook + Routable + Serialize + Send + 'static,
-                S: RouteConfig<Ctx = CtxImpl, Db = DbImpl> + Clone + Send + Sync + 'static,
-                <T as Entity>::Create: DeserializeOwned + Send,
-            {
-                let (ctx, pool) = state.db_ctx();
-                let method = Method::POST;
-                let url = <T as Routable>::entity_collection_path();
-                let mut tx = pool.begin().await.map_err_500::<T>(
-                    &method,
-                    url,
-                    "Failed to acquire a database transaction",
-                    None,
-                )?;
-
-                <T as ValidationHook>::before_create(&input).map_err(|e| (StatusCode::BAD_REQUEST, Json(e)))?;
-                let result = ctx.create::<T>(&mut tx, input).await.map_err_500::<T>(
-                    &method,
-                    url,
-                    "Failed to create an entity in database",
-                    None,
-                )?;
-                <T as ValidationHook>::after_create(&result).map_err(|e| (StatusCode::BAD_REQUEST, Json(e)))?;
-
-                tx.commit()
-                    .await
-                    .map_err_500::<T>(&method, url, "Failed to commit a transaction", None)?;
-                Ok((StatusCode::CREATED, Json(result)))
+async fn delete_endpoint_impl<T, S, Db>(
+    Path(id): Path<<T as Entity>::Pk>,
+    State(state): State<S>,
+    RequestRoute(route): RequestRoute,
+    RequestTenant(tenant): RequestTenant,
+    RequestActor(actor): RequestActor,
+) -> Result<Json<()>, (StatusCode, Json<ErrorResponse>)>
+where
+    T: DeleteEntity<Db> + Routable + Serialize + Send + 'static,
+    Db: Database,
+    S: RouteConfig<Db = Db> + Clone + Send + Sync + 'static,
+    <S as RouteConfig>::Ctx: Send + Sync,
+    <T as Entity>::Pk: DeserializeOwned + Send,
+{
+    let (ctx, pool) = state.db_ctx();
+    let ctx = ActorDbCtx::new(TenantDbCtx::new(ctx, tenant), actor);
+    let method = Method::DELETE;
+    let url = route.as_str();
+    let options = transaction_options::<T, S>(&state);
+    let pk = id.clone();
+    boxed(with_transaction(&ctx, &pool, &options, move |ctx, tx| {
+        let id = pk.clone();
+        Box::pin(async move {
+            if !boxed(ctx.delete::<T>(&mut *tx, id.clone())).await? {
+                return Err(EndpointError::Response((
+                    StatusCode::NOT_FOUND,
+                    Json(
+                        ErrorResponse::new("Not found")
+                            .with_detail(&format!("An entity with id {:?} was not found.", id)),
+                    ),
+                )));
             }
+            Ok::<_, EndpointError>(())
+        })
+    }))
+    .await
+    .map_db_err::<T>(&method, url, "Failed to delete an entity from database", Some(&id))?;
+    state.after_write();
+    Ok(Json(()))
+}
 
-            impl<T, S> DeleteRouter<S, DbImpl> for T
-            where
-                T: DeleteEntity<DbImpl> + Routable + Serialize + Send + 'static,
-                S: RouteConfig<Ctx = CtxImpl, Db = DbImpl> + Clone + Send + Sync + 'static,
-                <T as Entity>::Pk: DeserializeOwned + Send,
-            {
-                fn delete_endpoint() -> Router<S> {
-                    let route = <T as Routable>::entity_path();
-                    Router::new().route(route, delete(delete_endpoint_impl::<T, S>))
-                }
-            }
+impl<T, S, Db> UpdateRouter<S, Db> for T
+where
+    T: UpdateEntity<Db> + ValidationHook + Routable + Serialize + Send + 'static,
+    Db: Database,
+    S: RouteConfig<Db = Db> + Clone + Send + Sync + 'static,
+    <S as RouteConfig>::Ctx: Send + Sync,
+    <T as Entity>::Pk: DeserializeOwned + Send,
+    <T as Entity>::Update: DeserializeOwned + Clone + Send,
+    <T as Entity>::Replace: DeserializeOwned + Send,
+{
+    fn replace_endpoint() -> Router<S> {
+        let route = <T as Routable>::entity_path();
+        Router::new().route(route, put(replace_endpoint_impl::<T, S, Db>))
+    }
 
-            async fn delete_endpoint_impl<T, S>(
-                Path(id): Path<<T as Entity>::Pk>,
-                State(state): State<S>,
-            ) -> Result<Json<()>, (StatusCode, Json<ErrorResponse>)>
-            where
-                T: DeleteEntity<DbImpl> + Routable + Serialize + Send + 'static,
-                S: RouteConfig<Ctx = CtxImpl, Db = DbImpl> + Clone + Send + 'static,
-                <T as Entity>::Pk: DeserializeOwned + Send,
-            {
-                let (ctx, pool) = state.db_ctx();
-                let method = Method::DELETE;
-                let url = <T as Routable>::entity_path();
-                let mut tx = pool.begin().await.map_err_500::<T>(
-                    &method,
-                    url,
-                    "Failed to acquire a database transaction",
-                    None,
-                )?;
-                ctx.delete::<T>(&mut tx, id.clone()).await.map_err_500::<T>(
-                    &method,
-                    url,
-                    "Failed to delete an entity from database",
-                    Some(&id),
-                )?;
-                tx.commit()
-                    .await
-                    .map_err_500::<T>(&method, url, "Failed to commit a transaction", None)?;
-                Ok(Json(()))
-            }
+    fn update_endpoint() -> Router<S> {
+        let route = <T as Routable>::entity_path();
+        Router::new().route(route, patch(update_endpoint_impl::<T, S, Db>))
+    }
+}
 
-            impl<T, S> UpdateRouter<S, DbImpl> for T
-            where
-                T: UpdateEntity<DbImpl> + ValidationHook + Routable + Serialize + Send + 'static,
-                S: RouteConfig<Ctx = CtxImpl, Db = DbImpl> + Clone + Send + Sync + 'static,
-                <T as Entity>::Pk: DeserializeOwned + Send,
-                <T as Entity>::Update: DeserializeOwned + Send,
-                <T as Entity>::Replace: DeserializeOwned + Send,
-            {
-                fn replace_endpoint() -> Router<S> {
-                    let route = <T as Routable>::entity_path();
-                    Router::new().route(route, put(replace_endpoint_impl::<T, S>))
-                }
-
-                fn update_endpoint() -> Router<S> {
-                    let route = <T as Routable>::entity_path();
-                    Router::new().route(route, patch(update_endpoint_impl::<T, S>))
-                }
-            }
+async fn update_endpoint_impl<T, S, Db>(
+    Path(id): Path<<T as Entity>::Pk>,
+    State(state): State<S>,
+    RequestRoute(route): RequestRoute,
+    RequestTenant(tenant): RequestTenant,
+    RequestActor(actor): RequestActor,
+    Json(input): Json<<T as Entity>::Update>,
+) -> Result<Json<T>, (StatusCode, Json<ErrorResponse>)>
+where
+    T: UpdateEntity<Db> + ValidationHook + Routable + Serialize + Send + 'static,
+    Db: Database,
+    S: RouteConfig<Db = Db> + Clone + Send + Sync + 'static,
+    <S as RouteConfig>::Ctx: Send + Sync,
+    <T as Entity>::Pk: DeserializeOwned + Send,
+    <T as Entity>::Update: DeserializeOwned + Clone + Send,
+{
+    update_endpoint_logic(Method::PATCH, &route, id, state, tenant, actor, input).await
+}
 
-            async fn update_endpoint_impl<T, S>(
-                Path(id): Path<<T as Entity>::Pk>,
-                State(state): State<S>,
-                Json(input): Json<<T as Entity>::Update>,
-            ) -> Result<Json<T>, (StatusCode, Json<ErrorResponse>)>
-            where
-                T: UpdateEntity<DbImpl> + ValidationHook + Routable + Serialize + Send + 'static,
-                S: RouteConfig<Ctx = CtxImpl, Db = DbImpl> + Clone + Send + 'static,
-                <T as Entity>::Pk: DeserializeOwned + Send,
-                <T as Entity>::Update: DeserializeOwned + Send,
-            {
-                update_endpoint_logic(Method::PATCH, id, state, input).await
-            }
+async fn replace_endpoint_impl<T, S, Db>(
+    Path(id): Path<<T as Entity>::Pk>,
+    State(state): State<S>,
+    RequestRoute(route): RequestRoute,
+    RequestTenant(tenant): RequestTenant,
+    RequestActor(actor): RequestActor,
+    Json(input): Json<<T as Entity>::Replace>,
+) -> Result<Json<T>, (StatusCode, Json<ErrorResponse>)>
+where
+    T: UpdateEntity<Db> + ValidationHook + Routable + Serialize + Send + 'static,
+    Db: Database,
+    S: RouteConfig<Db = Db> + Clone + Send + Sync + 'static,
+    <S as RouteConfig>::Ctx: Send + Sync,
+    <T as Entity>::Pk: DeserializeOwned + Send,
+    <T as Entity>::Replace: DeserializeOwned,
+    <T as Entity>::Update: Clone + Send,
+{
+    update_endpoint_logic(Method::PUT, &route, id, state, tenant, actor, input.into()).await
+}
 
-            async fn replace_endpoint_impl<T, S>(
-                Path(id): Path<<T as Entity>::Pk>,
-                State(state): State<S>,
-                Json(input): Json<<T as Entity>::Replace>,
-            ) -> Result<Json<T>, (StatusCode, Json<ErrorResponse>)>
-            where
-                T: UpdateEntity<DbImpl> + ValidationHook + Routable + Serialize + Send + 'static,
-                S: RouteConfig<Ctx = CtxImpl, Db = DbImpl> + Clone + Send + 'static,
-                <T as Entity>::Pk: DeserializeOwned + Send,
-                <T as Entity>::Replace: DeserializeOwned,
-                <T as Entity>::Update: Send,
-            {
-                update_endpoint_logic(Method::PUT, id, state, input.into()).await
-            }
+async fn update_endpoint_logic<T, S, Db>(
+    method: Method,
+    url: &str,
+    id: <T as Entity>::Pk,
+    state: S,
+    tenant: Option<sea_query::Value>,
+    actor: Option<sea_query::Value>,
+    input: <T as Entity>::Update,
+) -> Result<Json<T>, (StatusCode, Json<ErrorResponse>)>
+where
+    T: UpdateEntity<Db> + ValidationHook + Routable + Serialize + Send + 'static,
+    Db: Database,
+    S: RouteConfig<Db = Db> + Clone + Send + Sync + 'static,
+    <S as RouteConfig>::Ctx: Send + Sync,
+    <T as Entity>::Pk: Send,
+    <T as Entity>::Update: Clone + Send,
+{
+    let (ctx, pool) = state.db_ctx();
+    let ctx = ActorDbCtx::new(TenantDbCtx::new(ctx, tenant), actor);
+    let options = transaction_options::<T, S>(&state);
 
-            async fn update_endpoint_logic<T, S>(
-                method: Method,
-                id: <T as Entity>::Pk,
-                state: S,
-                input: <T as Entity>::Update,
-            ) -> Result<Json<T>, (StatusCode, Json<ErrorResponse>)>
-            where
-                T: UpdateEntity<DbImpl> + ValidationHook + Routable + Serialize + Send + 'static,
-                S: RouteConfig<Ctx = CtxImpl, Db = DbImpl> + Clone + Send + 'static,
-                <T as Entity>::Pk: Send,
-                <T as Entity>::Update: Send,
-            {
-                let (ctx, pool) = state.db_ctx();
-                let url = <T as Routable>::entity_path();
-                let mut tx =
-                    pool.begin()
-                        .await
-                        .map_err_500::<T>(&method, url, "Failed to acquire a transaction", Some(&id))?;
-
-                <T as ValidationHook>::before_update(&id, &input).map_err(|e| (StatusCode::BAD_REQUEST, Json(e)))?;
-                let result = ctx
-                    .update::<T>(&mut tx, id.clone(), input)
-                    .await
-                    .map_err_500::<T>(&method, url, "Failed to update an entity in database", Some(&id))?
-                    .ok_or((
+    <T as ValidationHook>::before_update(&id, &input).map_err(|e| (StatusCode::BAD_REQUEST, Json(e)))?;
+    let pk = id.clone();
+    let result = boxed(with_transaction(&ctx, &pool, &options, move |ctx, tx| {
+        let id = pk.clone();
+        let input = input.clone();
+        Box::pin(async move {
+            let result = boxed(ctx.update::<T>(&mut *tx, id.clone(), input))
+                .await?
+                .ok_or_else(|| {
+                    EndpointError::Response((
                         StatusCode::NOT_FOUND,
                         Json(
                             ErrorResponse::new("Not found")
                                 .with_detail(&format!("An entity with id {:?} was not found.", id)),
                         ),
-                    ))?;
-                <T as ValidationHook>::after_update(&result).map_err(|e| (StatusCode::BAD_REQUEST, Json(e)))?;
+                    ))
+                })?;
+            <T as ValidationHook>::after_update(&result)
+                .map_err(|e| EndpointError::Response((StatusCode::BAD_REQUEST, Json(e))))?;
+            Ok::<_, EndpointError>(result)
+        })
+    }))
+    .await
+    .map_db_err::<T>(&method, url, "Failed to update an entity in database", Some(&id))?;
+    state.after_write();
+    Ok(Json(result))
+}
 
-                tx.commit()
-                    .await
-                    .map_err_500::<T>(&method, url, "Failed to commit a transaction", Some(&id))?;
-                Ok(Json(result))
-            }
-        };
+impl<T, S, Db> HistoryRouter<S, Db> for T
+where
+    T: GetHistory<Db> + Routable + Send + 'static,
+    Db: Database,
+    S: RouteConfig<Db = Db> + Clone + Send + Sync + 'static,
+    <S as RouteConfig>::Ctx: Send + Sync,
+    <T as Entity>::Pk: DeserializeOwned + Send,
+{
+    fn history_endpoint() -> Router<S> {
+        let route = format!("{}/history", <T as Routable>::entity_path());
+        Router::new().route(&route, get(history_endpoint_impl::<T, S, Db>))
     }
+}
 
-    pub(super) use {axum_route_impl, axum_route_impl_imports};
+async fn history_endpoint_impl<T, S, Db>(
+    Path(id): Path<<T as Entity>::Pk>,
+    State(state): State<S>,
+    RequestRoute(route): RequestRoute,
+    RequestTenant(tenant): RequestTenant,
+) -> Result<Json<Vec<HistoryRecord>>, (StatusCode, Json<ErrorResponse>)>
+where
+    T: GetHistory<Db> + Routable + Send + 'static,
+    Db: Database,
+    S: RouteConfig<Db = Db> + Clone + Send + Sync + 'static,
+    <S as RouteConfig>::Ctx: Send + Sync,
+    <T as Entity>::Pk: DeserializeOwned + Send,
+{
+    let (ctx, pool) = state.read_db_ctx();
+    let ctx = TenantDbCtx::new(ctx, tenant);
+    let method = Method::GET;
+    let url = route.as_str();
+    let records = boxed(ctx.get_history::<T>(&pool, id.clone())).await.map_err_500::<T>(
+        &method,
+        url,
+        "Failed to get the history of an entity from database",
+        Some(&id),
+    )?;
+    Ok(Json(records))
 }
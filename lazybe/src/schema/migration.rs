@@ -0,0 +1,495 @@
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+
+use sea_query::{Alias, ColumnSpec, Iden, QueryBuilder, TableBuilder, TableCreateStatement};
+use serde::{Deserialize, Serialize};
+
+use super::{TableSchema, TypeKind};
+use crate::db::Dialect;
+use crate::outbox;
+
+/// The tables of entities as they are created on a database, which can be saved to a file
+/// and compared with a later snapshot to generate a [`Migration`].
+///
+/// A snapshot is taken for one database since the column definitions are rendered in its dialect.
+///
+/// # Example
+///
+/// ```
+/// # use lazybe::macros::Entity;
+/// # use lazybe::schema::migration::SchemaSnapshot;
+/// # use sea_query::PostgresQueryBuilder;
+/// mod v1 {
+///     # use lazybe::macros::Entity;
+///     #[derive(Entity)]
+///     #[lazybe(table = "book")]
+///     pub struct Book {
+///         #[lazybe(primary_key)]
+///         pub id: i32,
+///         pub title: String,
+///         pub author: String,
+///     }
+/// }
+///
+/// mod v2 {
+///     # use lazybe::macros::Entity;
+///     #[derive(Entity)]
+///     #[lazybe(table = "book")]
+///     pub struct Book {
+///         #[lazybe(primary_key)]
+///         pub id: i32,
+///         #[lazybe(renamed_from = "title")]
+///         pub name: String,
+///         pub author: Option<String>,
+///         pub pages: Option<i32>,
+///     }
+/// }
+///
+/// let old = SchemaSnapshot::builder(PostgresQueryBuilder)
+///     .entity::<v1::Book>()
+///     .build();
+/// let new = SchemaSnapshot::builder(PostgresQueryBuilder)
+///     .entity::<v2::Book>()
+///     .build();
+///
+/// let migration = old.migration_to(&new, &PostgresQueryBuilder);
+/// assert_eq!(
+///     migration.statements(),
+///     [
+///         r#"ALTER TABLE "book" RENAME COLUMN "title" TO "name""#,
+///         r#"ALTER TABLE "book" ADD COLUMN "pages" integer NULL"#,
+///         r#"ALTER TABLE "book" ALTER COLUMN "author" DROP NOT NULL"#,
+///     ]
+/// );
+///
+/// // migration.write("migrations", 20250101000000, "rename book title")?;
+/// // new.write("schema.json")?;
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SchemaSnapshot {
+    tables: Vec<TableSnapshot>,
+}
+
+/// A table in a [`SchemaSnapshot`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TableSnapshot {
+    name: String,
+    columns: Vec<ColumnSnapshot>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    statements: Vec<String>,
+}
+
+/// A column in a [`SchemaSnapshot`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ColumnSnapshot {
+    name: String,
+    sql_type: String,
+    nullable: bool,
+    definition: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    renamed_from: Option<String>,
+}
+
+/// Takes a [`SchemaSnapshot`] of entities with the column definitions rendered by a query builder
+pub struct SchemaSnapshotBuilder<Qb> {
+    qb: Qb,
+    tables: Vec<TableSnapshot>,
+}
+
+impl SchemaSnapshot {
+    /// A snapshot without any table, e.g. to generate the migration that creates all tables
+    pub fn empty() -> Self {
+        Self::default()
+    }
+
+    pub fn builder<Qb>(qb: Qb) -> SchemaSnapshotBuilder<Qb>
+    where
+        Qb: QueryBuilder + TableBuilder,
+    {
+        SchemaSnapshotBuilder { qb, tables: Vec::new() }
+    }
+
+    pub fn tables(&self) -> &[TableSnapshot] {
+        &self.tables
+    }
+
+    /// Read a snapshot from a JSON file
+    pub fn read(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = fs::File::open(path)?;
+        Ok(serde_json::from_reader(io::BufReader::new(file))?)
+    }
+
+    /// Write the snapshot to a JSON file
+    pub fn write(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        fs::write(path, json + "\n")
+    }
+
+    /// The migration that changes the tables of this snapshot to the tables of a newer snapshot.
+    ///
+    /// - Tables are created and dropped
+    /// - Columns are added, dropped and renamed from their `renamed_from` name
+    /// - Columns are changed to the new type and nullability
+    /// - The statements of a table, e.g. indexes and triggers, are run when they are new
+    ///
+    /// The existing records get a zero value, e.g. `0`, `''` or `FALSE`, for an added `NOT NULL` column
+    /// without a default. A column without a zero value, e.g. a UUID, is preceded by a comment
+    /// to set its value in the migration.
+    ///
+    /// Databases that cannot alter a column, such as SQLite, rebuild the table instead:
+    /// the records are copied to a new table that replaces the old one,
+    /// and the statements of the table are run again since the indexes and triggers are dropped with the old table.
+    /// Indexes and triggers that are not in the snapshot are lost.
+    pub fn migration_to<Qb>(&self, new: &SchemaSnapshot, qb: &Qb) -> Migration
+    where
+        Qb: QueryBuilder + Dialect,
+    {
+        let mut statements = Vec::new();
+        for new_table in &new.tables {
+            match self.tables.iter().find(|t| t.name == new_table.name) {
+                Some(old_table) => statements.extend(alter_table(old_table, new_table, qb)),
+                None => {
+                    statements.push(create_table(&new_table.name, &new_table.columns, qb));
+                    statements.extend(new_table.statements.iter().cloned());
+                }
+            }
+        }
+        for old_table in &self.tables {
+            if !new.tables.iter().any(|t| t.name == old_table.name) {
+                statements.push(format!("DROP TABLE {}", quoted(&old_table.name, qb)));
+            }
+        }
+        Migration { statements }
+    }
+}
+
+impl<Qb> SchemaSnapshotBuilder<Qb>
+where
+    Qb: QueryBuilder + TableBuilder,
+{
+    /// Add the table of the entity, and its history table when it is a `#[lazybe(history)]` entity
+    pub fn entity<T: TableSchema>(self) -> Self {
+        let builder = self.table(T::table().to_string(), T::create_table(), &T::renamed_columns());
+        match T::history() {
+            Some(history) => builder.table(history.table().to_string(), history.create_table(), &[]),
+            None => builder,
+        }
+    }
+
+    /// Add the outbox table, see [`outbox::create_table`]
+    pub fn outbox(self) -> Self {
+        self.table(outbox::table().to_string(), outbox::create_table(), &[])
+    }
+
+    /// Add a statement that creates an index or a trigger of the table of an entity that is already added,
+    /// so that the statement is run when the table is created or rebuilt by a migration.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the entity is not added to the snapshot.
+    pub fn statement<T: TableSchema>(mut self, sql: impl Into<String>) -> Self {
+        let name = T::table().to_string();
+        let table = self
+            .tables
+            .iter_mut()
+            .find(|t| t.name == name)
+            .unwrap_or_else(|| panic!("the table `{}` is not in the snapshot, add its entity first", name));
+        table.statements.push(sql.into());
+        self
+    }
+
+    fn table(mut self, name: String, create_table: TableCreateStatement, renamed_columns: &[(&str, &str)]) -> Self {
+        let columns = create_table
+            .get_columns()
+            .iter()
+            .map(|column_def| {
+                let name = column_def.get_column_name();
+                let mut sql_type = String::new();
+                if let Some(column_type) = column_def.get_column_type() {
+                    self.qb.prepare_column_type(column_type, &mut sql_type);
+                }
+                let mut definition = String::new();
+                self.qb.prepare_column_def(column_def, &mut definition);
+                ColumnSnapshot {
+                    nullable: column_def
+                        .get_column_spec()
+                        .iter()
+                        .any(|spec| matches!(spec, ColumnSpec::Null)),
+                    renamed_from: renamed_columns
+                        .iter()
+                        .find(|(_, current)| *current == name)
+                        .map(|(previous, _)| previous.to_string()),
+                    name,
+                    sql_type,
+                    definition,
+                }
+            })
+            .collect();
+        self.tables.push(TableSnapshot {
+            name,
+            columns,
+            statements: Vec::new(),
+        });
+        self
+    }
+
+    pub fn build(self) -> SchemaSnapshot {
+        SchemaSnapshot { tables: self.tables }
+    }
+}
+
+impl TableSnapshot {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn columns(&self) -> &[ColumnSnapshot] {
+        &self.columns
+    }
+
+    /// The statements that create the indexes and triggers of the table
+    pub fn statements(&self) -> &[String] {
+        &self.statements
+    }
+}
+
+impl ColumnSnapshot {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The column type rendered in the dialect of the snapshot
+    pub fn sql_type(&self) -> &str {
+        &self.sql_type
+    }
+
+    pub fn nullable(&self) -> bool {
+        self.nullable
+    }
+
+    /// The column definition of a `CREATE TABLE` statement rendered in the dialect of the snapshot
+    pub fn definition(&self) -> &str {
+        &self.definition
+    }
+
+    pub fn has_default(&self) -> bool {
+        self.definition.contains(" DEFAULT ")
+    }
+
+    /// The value of the existing records when a `NOT NULL` column without a default is added
+    fn backfill_value(&self) -> Option<&'static str> {
+        if self.nullable || self.has_default() {
+            return None;
+        }
+        TypeKind::parse(&self.sql_type)?.zero_value()
+    }
+
+    /// Whether the existing records have no value when the column is added
+    fn lacks_value(&self) -> bool {
+        !self.nullable && !self.has_default() && self.backfill_value().is_none()
+    }
+}
+
+/// Statements changing the tables between two [`SchemaSnapshot`]s
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Migration {
+    statements: Vec<String>,
+}
+
+impl Migration {
+    /// Whether the snapshots have the same tables
+    pub fn is_empty(&self) -> bool {
+        self.statements.is_empty()
+    }
+
+    pub fn statements(&self) -> &[String] {
+        &self.statements
+    }
+
+    /// Write the migration to `{version}_{description}.sql` in the migrations directory,
+    /// which is the file name of a migration applied by [`sqlx::migrate!`].
+    /// The version is usually the current timestamp, e.g. `20250101120000`.
+    pub fn write(&self, dir: impl AsRef<Path>, version: i64, description: &str) -> io::Result<PathBuf> {
+        let dir = dir.as_ref();
+        let description = description.trim().replace(' ', "_");
+        let path = dir.join(format!("{}_{}.sql", version, description));
+        fs::create_dir_all(dir)?;
+        fs::write(&path, self.to_string())?;
+        Ok(path)
+    }
+}
+
+impl Display for Migration {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for statement in &self.statements {
+            writeln!(f, "{};", statement)?;
+        }
+        Ok(())
+    }
+}
+
+fn alter_table<Qb>(old: &TableSnapshot, new: &TableSnapshot, qb: &Qb) -> Vec<String>
+where
+    Qb: QueryBuilder + Dialect,
+{
+    let table = quoted(&new.name, qb);
+    // each new column with the old column it is kept or renamed from
+    let kept: Vec<(&ColumnSnapshot, &ColumnSnapshot)> = new
+        .columns
+        .iter()
+        .filter_map(|new_col| {
+            let old_col = old.columns.iter().find(|c| c.name == new_col.name).or_else(|| {
+                let renamed_from = new_col.renamed_from.as_ref()?;
+                old.columns.iter().find(|c| &c.name == renamed_from)
+            })?;
+            Some((old_col, new_col))
+        })
+        .collect();
+    let added: Vec<&ColumnSnapshot> = new
+        .columns
+        .iter()
+        .filter(|c| !kept.iter().any(|(_, new_col)| new_col.name == c.name))
+        .collect();
+    let dropped = old
+        .columns
+        .iter()
+        .filter(|c| !kept.iter().any(|(old_col, _)| old_col.name == c.name));
+    let changed = kept
+        .iter()
+        .filter(|(old_col, new_col)| old_col.sql_type != new_col.sql_type || old_col.nullable != new_col.nullable);
+
+    let mut alter_columns = Vec::new();
+    for (old_col, new_col) in changed {
+        match qb.alter_column_statements(&new.name, old_col, new_col) {
+            Some(statements) => alter_columns.extend(statements),
+            None => return rebuild_table(new, &kept, &added, qb),
+        }
+    }
+    let mut add_columns = Vec::new();
+    for col in &added {
+        match qb.add_column_statements(&new.name, col, col.backfill_value()) {
+            Some(mut statements) => {
+                if let Some(first) = statements.first_mut().filter(|_| col.lacks_value()) {
+                    *first = format!("{}\n{}", lacks_value_comment(&new.name, &[col]), first);
+                }
+                add_columns.extend(statements);
+            }
+            None => return rebuild_table(new, &kept, &added, qb),
+        }
+    }
+
+    let mut statements = Vec::new();
+    for (old_col, new_col) in &kept {
+        if old_col.name != new_col.name {
+            statements.push(format!(
+                "ALTER TABLE {} RENAME COLUMN {} TO {}",
+                table,
+                quoted(&old_col.name, qb),
+                quoted(&new_col.name, qb)
+            ));
+        }
+    }
+    for col in dropped {
+        statements.push(format!("ALTER TABLE {} DROP COLUMN {}", table, quoted(&col.name, qb)));
+    }
+    statements.extend(add_columns);
+    statements.extend(alter_columns);
+    statements.extend(new.statements.iter().filter(|s| !old.statements.contains(s)).cloned());
+    statements
+}
+
+/// Copy the records to a new table with the new columns which then replaces the old table
+fn rebuild_table<Qb>(
+    table: &TableSnapshot,
+    kept: &[(&ColumnSnapshot, &ColumnSnapshot)],
+    added: &[&ColumnSnapshot],
+    qb: &Qb,
+) -> Vec<String>
+where
+    Qb: QueryBuilder,
+{
+    let new_table = format!("_lazybe_new_{}", table.name);
+    let backfilled: Vec<(&str, &str)> = added
+        .iter()
+        .filter_map(|col| Some((col.name.as_str(), col.backfill_value()?)))
+        .collect();
+    let columns = kept
+        .iter()
+        .map(|(_, new_col)| quoted(&new_col.name, qb))
+        .chain(backfilled.iter().map(|(name, _)| quoted(name, qb)));
+    let values = kept
+        .iter()
+        .map(|(old_col, _)| quoted(&old_col.name, qb))
+        .chain(backfilled.iter().map(|(_, value)| value.to_string()));
+    let mut insert = format!(
+        "INSERT INTO {} ({}) SELECT {} FROM {}",
+        quoted(&new_table, qb),
+        columns.collect::<Vec<_>>().join(", "),
+        values.collect::<Vec<_>>().join(", "),
+        quoted(&table.name, qb)
+    );
+    let lacking: Vec<&ColumnSnapshot> = added.iter().copied().filter(|col| col.lacks_value()).collect();
+    if !lacking.is_empty() {
+        insert = format!("{}\n{}", lacks_value_comment(&table.name, &lacking), insert);
+    }
+    let mut statements = vec![
+        create_table(&new_table, &table.columns, qb),
+        insert,
+        format!("DROP TABLE {}", quoted(&table.name, qb)),
+        format!(
+            "ALTER TABLE {} RENAME TO {}",
+            quoted(&new_table, qb),
+            quoted(&table.name, qb)
+        ),
+    ];
+    statements.extend(table.statements.iter().cloned());
+    statements
+}
+
+/// A comment to set the value of the existing records for added `NOT NULL` columns without a zero value
+fn lacks_value_comment(table: &str, columns: &[&ColumnSnapshot]) -> String {
+    let names = columns.iter().map(|c| c.name.as_str()).collect::<Vec<_>>().join(", ");
+    format!(
+        "-- TODO: the existing records of {} have no value for the NOT NULL columns: {}",
+        table, names
+    )
+}
+
+fn create_table<Qb>(table: &str, columns: &[ColumnSnapshot], qb: &Qb) -> String
+where
+    Qb: QueryBuilder,
+{
+    let definitions = columns.iter().map(|c| c.definition.as_str()).collect::<Vec<_>>();
+    format!("CREATE TABLE {} ( {} )", quoted(table, qb), definitions.join(", "))
+}
+
+pub(crate) fn quoted<Qb: QueryBuilder>(name: &str, qb: &Qb) -> String {
+    let mut quoted = String::new();
+    Alias::new(name).prepare(&mut quoted, qb.quote());
+    quoted
+}
+
+/// Add a column with the `backfill` value as its default, which is dropped once the existing records have the value
+pub(crate) fn add_column_with_backfill<Qb: QueryBuilder>(
+    qb: &Qb,
+    table: &str,
+    column: &ColumnSnapshot,
+    backfill: Option<&str>,
+) -> Vec<String> {
+    let table = quoted(table, qb);
+    match backfill {
+        Some(value) => vec![
+            format!(
+                "ALTER TABLE {} ADD COLUMN {} DEFAULT {}",
+                table, column.definition, value
+            ),
+            format!(
+                "ALTER TABLE {} ALTER COLUMN {} DROP DEFAULT",
+                table,
+                quoted(&column.name, qb)
+            ),
+        ],
+        None => vec![format!("ALTER TABLE {} ADD COLUMN {}", table, column.definition)],
+    }
+}
@@ -0,0 +1,516 @@
+use std::fmt::Display;
+use std::ops::DerefMut;
+
+use sea_query::{ColumnSpec, ColumnType, DynIden, TableBuilder, TableCreateStatement, TableRef};
+use sqlx::{Acquire, Database, Executor, FromRow, IntoArguments};
+
+use crate::db::{DbCtx, Dialect};
+use crate::{TableEntity, outbox};
+
+/// Snapshots of entity tables and migrations between them
+pub mod migration;
+
+/// The `CREATE TABLE` statement of an entity table.
+///
+/// The [`Entity`](crate::macros::Entity) macro derives the table from the struct:
+///
+/// - Column types are inferred from the Rust types by [`SqlType`], or set by `#[lazybe(sql_type = "...")]`.
+///   A type without [`SqlType`] (e.g. `rust_decimal::Decimal`) is declared by its name (e.g. `Decimal`),
+///   so set `sql_type` when the name is not a type of the database.
+/// - `Option` fields are nullable and other fields are `NOT NULL`
+/// - The primary key is auto-incremented unless it is generated by `generate_with`
+/// - `json` fields are stored as [`ColumnType::JsonBinary`], which is `jsonb` on Postgres, `json` on MySQL
+///   and `jsonb_text` (a text column) on SQLite
+/// - `created_at` and `updated_at` fields default to the current timestamp
+///
+/// The statement can be rendered by any [`sea_query`] schema builder.
+///
+/// # Example
+///
+/// ```
+/// # use lazybe::macros::Entity;
+/// # use lazybe::schema::TableSchema;
+/// # use sea_query::{PostgresQueryBuilder, SqliteQueryBuilder};
+/// #[derive(Entity)]
+/// #[lazybe(table = "book")]
+/// pub struct Book {
+///     #[lazybe(primary_key)]
+///     pub id: i32,
+///     pub title: String,
+///     pub pages: Option<i32>,
+/// }
+///
+/// assert_eq!(
+///     Book::create_table().to_string(SqliteQueryBuilder),
+///     r#"CREATE TABLE "book" ( "id" integer NOT NULL PRIMARY KEY AUTOINCREMENT, "title" text NOT NULL, "pages" integer NULL )"#
+/// );
+/// assert_eq!(
+///     Book::create_table().to_string(PostgresQueryBuilder),
+///     r#"CREATE TABLE "book" ( "id" serial NOT NULL PRIMARY KEY, "title" text NOT NULL, "pages" integer NULL )"#
+/// );
+/// ```
+pub trait TableSchema: TableEntity {
+    fn create_table() -> TableCreateStatement;
+
+    /// Columns that are renamed from a previous name with `#[lazybe(renamed_from = "...")]` as `(previous, current)`
+    fn renamed_columns() -> Vec<(&'static str, &'static str)> {
+        Vec::new()
+    }
+}
+
+/// A Rust type that is stored in a column of [`ColumnType`]
+pub trait SqlType {
+    fn sql_type() -> ColumnType;
+}
+
+macro_rules! impl_sql_type {
+    ($($ty:ty => $column_type:expr),* $(,)?) => {
+        $(
+            impl SqlType for $ty {
+                fn sql_type() -> ColumnType {
+                    $column_type
+                }
+            }
+        )*
+    };
+}
+
+impl_sql_type! {
+    bool => ColumnType::Boolean,
+    i8 => ColumnType::TinyInteger,
+    i16 => ColumnType::SmallInteger,
+    i32 => ColumnType::Integer,
+    i64 => ColumnType::BigInteger,
+    u8 => ColumnType::TinyUnsigned,
+    u16 => ColumnType::SmallUnsigned,
+    u32 => ColumnType::Unsigned,
+    u64 => ColumnType::BigUnsigned,
+    f32 => ColumnType::Float,
+    f64 => ColumnType::Double,
+    String => ColumnType::Text,
+    Vec<u8> => ColumnType::Blob,
+    uuid::Uuid => ColumnType::Uuid,
+    uuid::fmt::Hyphenated => ColumnType::Text,
+    uuid::fmt::Simple => ColumnType::Text,
+    serde_json::Value => ColumnType::JsonBinary,
+    chrono::NaiveDate => ColumnType::Date,
+    chrono::NaiveTime => ColumnType::Time,
+    chrono::NaiveDateTime => ColumnType::DateTime,
+}
+
+impl<Tz: chrono::TimeZone> SqlType for chrono::DateTime<Tz> {
+    fn sql_type() -> ColumnType {
+        ColumnType::TimestampWithTimeZone
+    }
+}
+
+/// Infers the column type of a Rust type by autoref specialization.
+/// Called on `&&Probe`, the method of `&Probe` applies when the type implements [`SqlType`],
+/// otherwise the method of `Probe` declares the column by the name of the type.
+#[doc(hidden)]
+pub mod __private {
+    use std::marker::PhantomData;
+
+    use sea_query::ColumnType;
+
+    use super::SqlType;
+
+    pub struct Probe<T: ?Sized>(PhantomData<fn() -> T>);
+
+    impl<T: ?Sized> Probe<T> {
+        #[allow(clippy::new_without_default)]
+        pub fn new() -> Self {
+            Self(PhantomData)
+        }
+    }
+
+    pub trait ViaSqlType {
+        fn sql_type(&self) -> ColumnType;
+    }
+
+    impl<T: SqlType + ?Sized> ViaSqlType for &Probe<T> {
+        fn sql_type(&self) -> ColumnType {
+            T::sql_type()
+        }
+    }
+
+    pub trait NoSqlType {
+        fn sql_type(&self) -> ColumnType;
+    }
+
+    impl<T: ?Sized> NoSqlType for Probe<T> {
+        fn sql_type(&self) -> ColumnType {
+            // `rust_decimal::decimal::Decimal` is declared as `Decimal`
+            let name = std::any::type_name::<T>();
+            let name = name.split('<').next().unwrap_or(name);
+            let name = name.rsplit("::").next().unwrap_or(name);
+            ColumnType::custom(name)
+        }
+    }
+}
+
+/// Compare the tables of entities with the connected database, e.g. to refuse to start when they have drifted apart.
+///
+/// Every column of an entity must exist in its table with the same nullability and a compatible type.
+/// Types are compared by kind (e.g. text, 32-bit integer or timestamp with time zone) rather than by their exact name.
+/// On SQLite, which stores any value in any column, a type only mismatches when the values cannot be stored in it.
+/// Columns of the table that are not part of the entity are ignored.
+/// The history table of a `#[lazybe(history)]` entity and the outbox table of a `#[lazybe(outbox)]` entity
+/// are checked with the entity.
+///
+/// # Example
+///
+/// ```
+/// # use lazybe::db::sqlite::SqliteDbCtx;
+/// # use lazybe::macros::Entity;
+/// # use lazybe::schema::{SchemaCheck, SchemaMismatch};
+/// # use sqlx::{Executor, SqlitePool};
+/// #[derive(Entity)]
+/// #[lazybe(table = "book")]
+/// pub struct Book {
+///     #[lazybe(primary_key)]
+///     pub id: i32,
+///     pub title: String,
+///     pub pages: Option<i32>,
+/// }
+///
+/// # #[tokio::main]
+/// # async fn main() -> anyhow::Result<()> {
+/// let pool = SqlitePool::connect("sqlite::memory:").await?;
+/// pool.execute("CREATE TABLE book (id INTEGER PRIMARY KEY, title TEXT NOT NULL)").await?;
+///
+/// let report = SchemaCheck::new().entity::<Book>().run(&SqliteDbCtx, &pool).await?;
+/// assert!(matches!(
+///     report.mismatches(),
+///     [SchemaMismatch::MissingColumn { column, .. }] if column == "pages"
+/// ));
+/// assert_eq!(report.to_string(), "Column `book.pages` of entity `Book` does not exist");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct SchemaCheck {
+    tables: Vec<ExpectedTable>,
+}
+
+#[derive(Debug, Clone)]
+struct ExpectedTable {
+    entity: &'static str,
+    table: DynIden,
+    statement: TableCreateStatement,
+}
+
+impl SchemaCheck {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Check the table of the entity, its history table when it is a `#[lazybe(history)]` entity,
+    /// and the outbox table when it is a `#[lazybe(outbox)]` entity
+    pub fn entity<T: TableSchema>(mut self) -> Self {
+        self.tables.push(ExpectedTable {
+            entity: T::entity_name(),
+            table: T::table(),
+            statement: T::create_table(),
+        });
+        if let Some(history) = T::history() {
+            self.tables.push(ExpectedTable {
+                entity: T::entity_name(),
+                table: history.table(),
+                statement: history.create_table(),
+            });
+        }
+        // the outbox table is shared by all entities, so it is checked once
+        let outbox = outbox::table();
+        if T::outbox().is_some() && !self.tables.iter().any(|t| t.table.to_string() == outbox.to_string()) {
+            self.tables.push(ExpectedTable {
+                entity: "OutboxMessage",
+                table: outbox,
+                statement: outbox::create_table(),
+            });
+        }
+        self
+    }
+
+    /// Introspect the tables resolved by [`DbCtx::table_ref`] and report the mismatches
+    pub async fn run<'a, Ctx, Db>(
+        &self,
+        ctx: &Ctx,
+        conn: impl Acquire<'a, Database = Db> + Send + 'a,
+    ) -> Result<SchemaReport, sqlx::Error>
+    where
+        Ctx: DbCtx<Db>,
+        Ctx::Qb: TableBuilder,
+        Db: Database,
+        for<'q> <Db as Database>::Arguments<'q>: IntoArguments<'q, Db>,
+        for<'c> &'c mut <Db as Database>::Connection: Executor<'c, Database = Db>,
+        (String, String, String): for<'r> FromRow<'r, Db::Row>,
+    {
+        let qb = ctx.query_builder();
+        let mut conn = conn.acquire().await?;
+        let mut mismatches = Vec::new();
+        for expected in &self.tables {
+            let (schema, table) = table_name(ctx.table_ref(expected.table.clone()), &expected.table);
+            let query = qb.columns_query(schema.as_deref(), &table);
+            let columns: Vec<(String, String, String)> = sqlx::query_as(&query).fetch_all(conn.deref_mut()).await?;
+            if columns.is_empty() {
+                mismatches.push(SchemaMismatch::MissingTable {
+                    entity: expected.entity,
+                    table,
+                });
+                continue;
+            }
+            for column_def in expected.statement.get_columns() {
+                let column = column_def.get_column_name();
+                let Some((_, actual_type, is_nullable)) = columns.iter().find(|(name, _, _)| *name == column) else {
+                    mismatches.push(SchemaMismatch::MissingColumn {
+                        entity: expected.entity,
+                        table: table.clone(),
+                        column,
+                    });
+                    continue;
+                };
+                let nullable = column_def
+                    .get_column_spec()
+                    .iter()
+                    .any(|spec| matches!(spec, ColumnSpec::Null));
+                if nullable != (is_nullable == "YES") {
+                    mismatches.push(SchemaMismatch::Nullability {
+                        entity: expected.entity,
+                        table: table.clone(),
+                        column: column.clone(),
+                        nullable,
+                    });
+                }
+                let mut expected_type = String::new();
+                if let Some(column_type) = column_def.get_column_type() {
+                    qb.prepare_column_type(column_type, &mut expected_type);
+                }
+                if !is_compatible(&expected_type, actual_type, qb.is_dynamically_typed()) {
+                    mismatches.push(SchemaMismatch::Type {
+                        entity: expected.entity,
+                        table: table.clone(),
+                        column,
+                        expected: expected_type,
+                        actual: actual_type.clone(),
+                    });
+                }
+            }
+        }
+        Ok(SchemaReport { mismatches })
+    }
+}
+
+/// The result of a [`SchemaCheck`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaReport {
+    mismatches: Vec<SchemaMismatch>,
+}
+
+impl SchemaReport {
+    /// Whether the database matches all entities
+    pub fn is_ok(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+
+    pub fn mismatches(&self) -> &[SchemaMismatch] {
+        &self.mismatches
+    }
+}
+
+impl Display for SchemaReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_ok() {
+            return write!(f, "The database schema matches all entities");
+        }
+        let lines = self.mismatches.iter().map(|m| m.to_string()).collect::<Vec<_>>();
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+impl std::error::Error for SchemaReport {}
+
+/// A difference between an entity and its table in the database
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SchemaMismatch {
+    /// The table does not exist
+    MissingTable { entity: &'static str, table: String },
+    /// The column of a field does not exist
+    MissingColumn {
+        entity: &'static str,
+        table: String,
+        column: String,
+    },
+    /// The column is nullable while the field is not, or the other way around
+    Nullability {
+        entity: &'static str,
+        table: String,
+        column: String,
+        /// Whether the field is nullable
+        nullable: bool,
+    },
+    /// The column type cannot store the values of the field
+    Type {
+        entity: &'static str,
+        table: String,
+        column: String,
+        /// The column type of the field
+        expected: String,
+        /// The column type in the database
+        actual: String,
+    },
+}
+
+impl Display for SchemaMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SchemaMismatch::MissingTable { entity, table } => {
+                write!(f, "Table `{}` of entity `{}` does not exist", table, entity)
+            }
+            SchemaMismatch::MissingColumn { entity, table, column } => {
+                write!(f, "Column `{}.{}` of entity `{}` does not exist", table, column, entity)
+            }
+            SchemaMismatch::Nullability {
+                entity,
+                table,
+                column,
+                nullable,
+            } => {
+                let (column_nullability, field_nullability) = match nullable {
+                    true => ("NOT NULL", "an `Option`"),
+                    false => ("nullable", "not an `Option`"),
+                };
+                write!(
+                    f,
+                    "Column `{}.{}` of entity `{}` is {} but the field is {}",
+                    table, column, entity, column_nullability, field_nullability
+                )
+            }
+            SchemaMismatch::Type {
+                entity,
+                table,
+                column,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "Column `{}.{}` of entity `{}` is `{}` but the field is `{}`",
+                table, column, entity, actual, expected
+            ),
+        }
+    }
+}
+
+/// The schema and the name of a table as it is queried at runtime
+fn table_name(table_ref: TableRef, table: &DynIden) -> (Option<String>, String) {
+    match table_ref {
+        TableRef::Table(t) | TableRef::TableAlias(t, _) => (None, t.to_string()),
+        TableRef::SchemaTable(s, t) | TableRef::SchemaTableAlias(s, t, _) => (Some(s.to_string()), t.to_string()),
+        TableRef::DatabaseSchemaTable(_, s, t) | TableRef::DatabaseSchemaTableAlias(_, s, t, _) => {
+            (Some(s.to_string()), t.to_string())
+        }
+        _ => (None, table.to_string()),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TypeKind {
+    Boolean,
+    SmallInt,
+    Int,
+    BigInt,
+    Float,
+    Double,
+    Decimal,
+    Text,
+    Blob,
+    Json,
+    Uuid,
+    Date,
+    Time,
+    /// A timestamp without a time zone, e.g. `timestamp` on Postgres or `datetime` on MySQL
+    DateTime,
+    /// A timestamp with a time zone, e.g. `timestamptz` on Postgres
+    DateTimeTz,
+}
+
+impl TypeKind {
+    /// The kind of a type name, or `None` if it is not known
+    fn parse(type_name: &str) -> Option<Self> {
+        let name = type_name.trim().to_lowercase();
+        let kind = match name.as_str() {
+            "tinyint(1)" => TypeKind::Boolean,
+            n if n.starts_with("bool") => TypeKind::Boolean,
+            n if n.contains("json") => TypeKind::Json,
+            n if n.contains("uuid") => TypeKind::Uuid,
+            n if n == "timestamptz"
+                || n.starts_with("timestamp with time zone")
+                || n.starts_with("timestamp_with_timezone") =>
+            {
+                TypeKind::DateTimeTz
+            }
+            n if n.starts_with("timestamp") || n.starts_with("datetime") => TypeKind::DateTime,
+            n if n.starts_with("date") => TypeKind::Date,
+            n if n.starts_with("time") => TypeKind::Time,
+            n if n.starts_with("interval") => return None,
+            n if ["tinyint", "smallint", "int2", "smallserial"]
+                .iter()
+                .any(|p| n.starts_with(p)) =>
+            {
+                TypeKind::SmallInt
+            }
+            n if ["bigint", "int8", "bigserial"].iter().any(|p| n.starts_with(p)) => TypeKind::BigInt,
+            n if ["int", "mediumint", "serial"].iter().any(|p| n.starts_with(p)) => TypeKind::Int,
+            n if n.starts_with("real") || n.starts_with("float4") || n == "float" => TypeKind::Float,
+            n if n.starts_with("double") || n.starts_with("float") => TypeKind::Double,
+            n if n.starts_with("numeric") || n.starts_with("decimal") => TypeKind::Decimal,
+            n if n.contains("char") || n.contains("text") || n.contains("clob") => TypeKind::Text,
+            n if n.contains("blob") || n.contains("binary") || n == "bytea" => TypeKind::Blob,
+            _ => return None,
+        };
+        Some(kind)
+    }
+
+    /// The SQLite storage classes that can hold the values of this kind
+    fn storage_classes(&self) -> &'static [&'static str] {
+        match self {
+            TypeKind::Boolean | TypeKind::SmallInt | TypeKind::Int | TypeKind::BigInt => &["integer"],
+            TypeKind::Float | TypeKind::Double | TypeKind::Decimal => &["integer", "real"],
+            TypeKind::Text | TypeKind::Json => &["text"],
+            TypeKind::Blob => &["blob"],
+            TypeKind::Uuid => &["text", "blob"],
+            TypeKind::Date | TypeKind::Time | TypeKind::DateTime | TypeKind::DateTimeTz => &["text", "integer", "real"],
+        }
+    }
+
+    /// A constant of this kind that is valid in every dialect, or `None` if there is no such value,
+    /// e.g. a UUID is stored as text or as bytes depending on the database
+    fn zero_value(&self) -> Option<&'static str> {
+        let value = match self {
+            TypeKind::Boolean => "FALSE",
+            TypeKind::SmallInt | TypeKind::Int | TypeKind::BigInt => "0",
+            TypeKind::Float | TypeKind::Double | TypeKind::Decimal => "0",
+            TypeKind::Text => "''",
+            TypeKind::Json => "'{}'",
+            TypeKind::Date => "'1970-01-01'",
+            TypeKind::Time => "'00:00:00'",
+            TypeKind::DateTime => "'1970-01-01 00:00:00'",
+            TypeKind::DateTimeTz => "'1970-01-01 00:00:00+00:00'",
+            TypeKind::Blob | TypeKind::Uuid => return None,
+        };
+        Some(value)
+    }
+}
+
+/// Whether a column of the actual type can store the values of a column of the expected type.
+/// Unknown types are assumed to be compatible.
+fn is_compatible(expected: &str, actual: &str, dynamically_typed: bool) -> bool {
+    let (Some(expected), Some(actual)) = (TypeKind::parse(expected), TypeKind::parse(actual)) else {
+        return true;
+    };
+    if dynamically_typed {
+        let actual_classes = actual.storage_classes();
+        return expected.storage_classes().iter().any(|c| actual_classes.contains(c));
+    }
+    expected == actual
+}
@@ -0,0 +1,120 @@
+use sea_query::{Alias, DynIden, IntoIden, TableRef};
+
+use crate::TableEntity;
+
+/// An entity with one or more fields marked as `searchable`.
+///
+/// This trait is derived by the [`Entity`](crate::macros::Entity) macro when
+/// at least one field has the `#[lazybe(searchable)]` attribute.
+///
+/// # Backend requirements
+///
+/// - Postgres - No setup is required. The searchable columns are concatenated and matched with
+///   `to_tsvector(..) @@ plainto_tsquery(..)` using the database default text search configuration.
+///   An expression index on the same `to_tsvector(..)` expression is recommended for large tables.
+/// - SQLite - An [FTS5](https://www.sqlite.org/fts5.html) table named `{table}_fts` must exist.
+///   It must contain the primary key column (as `UNINDEXED`) followed by all searchable columns
+///   and be kept in sync with the entity table (e.g. with triggers).
+///
+/// ```sql
+/// CREATE VIRTUAL TABLE book_fts USING fts5(id UNINDEXED, title, description);
+/// ```
+///
+/// - MySQL - A `FULLTEXT` index on exactly the searchable columns must exist.
+///   The search term is matched in boolean mode where every word is required.
+///
+/// ```sql
+/// CREATE FULLTEXT INDEX book_search ON book (title, description);
+/// ```
+pub trait SearchableEntity: TableEntity {
+    /// The table where the entity is stored
+    fn search_table() -> DynIden;
+
+    /// The primary key column used to correlate the entity with the full-text index
+    fn search_key() -> DynIden;
+
+    /// The columns that are matched against a search term
+    fn search_columns() -> Vec<DynIden>;
+}
+
+/// A full-text search term bound to the searchable columns of an entity.
+///
+/// The SQL for a search depends on the database, so it is only rendered
+/// when the query is executed via [`Dialect`](crate::db::Dialect).
+#[derive(Debug, Clone)]
+pub struct Search {
+    term: String,
+    table: DynIden,
+    key: DynIden,
+    columns: Vec<DynIden>,
+    fts_table: Option<Box<TableRef>>,
+}
+
+impl Search {
+    pub fn new<T: SearchableEntity>(term: impl Into<String>) -> Self {
+        Self {
+            term: term.into(),
+            table: T::search_table(),
+            key: T::search_key(),
+            columns: T::search_columns(),
+            fts_table: None,
+        }
+    }
+
+    /// The raw search term as provided by the user
+    pub fn term(&self) -> &str {
+        &self.term
+    }
+
+    pub fn table(&self) -> DynIden {
+        self.table.clone()
+    }
+
+    pub fn key(&self) -> DynIden {
+        self.key.clone()
+    }
+
+    pub fn columns(&self) -> &[DynIden] {
+        &self.columns
+    }
+
+    /// The name of the SQLite FTS5 table that indexes the searchable columns
+    pub fn fts_table(&self) -> DynIden {
+        match self.fts_table.as_deref() {
+            Some(
+                TableRef::Table(table) | TableRef::SchemaTable(_, table) | TableRef::DatabaseSchemaTable(_, _, table),
+            ) => table.clone(),
+            _ => Alias::new(format!("{}_fts", self.table.to_string())).into_iden(),
+        }
+    }
+
+    /// A reference to the SQLite FTS5 table, possibly qualified by [`DbCtx::table_ref`](crate::db::DbCtx::table_ref)
+    pub fn fts_table_ref(&self) -> TableRef {
+        match self.fts_table.as_deref() {
+            Some(table_ref) => table_ref.clone(),
+            None => TableRef::Table(self.fts_table()),
+        }
+    }
+
+    /// Resolve the FTS5 table with the given function. A table reference with an alias is ignored.
+    pub(crate) fn resolve_fts_table(self, resolve: impl Fn(DynIden) -> TableRef) -> Self {
+        let fts_table = match resolve(self.fts_table()) {
+            table_ref @ (TableRef::Table(_) | TableRef::SchemaTable(..) | TableRef::DatabaseSchemaTable(..)) => {
+                Some(Box::new(table_ref))
+            }
+            _ => None,
+        };
+        Self { fts_table, ..self }
+    }
+
+    /// The search term as a FTS5 query where every word must match.
+    ///
+    /// Each word is quoted so that FTS5 syntax in user input is treated as plain text.
+    pub fn fts_match_query(&self) -> String {
+        self.term
+            .split_whitespace()
+            .map(|word| format!("\"{}\"", word.replace('"', "\"\"")))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
@@ -1,6 +1,10 @@
 use std::marker::PhantomData;
 
-use sea_query::{ColumnRef, IntoColumnRef, Order};
+use sea_query::{Expr, Func, IntoColumnRef, NullOrdering, Order, SimpleExpr};
+
+use crate::db::Dialect;
+use crate::relation::{Join, merge_joins};
+use crate::search::{Search, SearchableEntity};
 
 #[derive(Debug, Clone)]
 pub struct Sort<Entity> {
@@ -26,45 +30,162 @@ impl<Entity> Sort<Entity> {
         }
     }
 
-    pub fn into_order_exprs(self) -> Vec<(ColumnRef, Order)> {
-        self.exprs.into_iter().map(|s| (s.col, s.order)).collect()
+    /// The joins required by the columns of related entities used in this sort
+    pub fn joins(&self) -> Vec<Join> {
+        let mut joins = Vec::new();
+        for expr in &self.exprs {
+            merge_joins(&mut joins, expr.joins.iter().cloned());
+        }
+        joins
+    }
+
+    /// Render the sort as `ORDER BY` expressions with their order and optional null ordering
+    pub fn into_order_exprs<D: Dialect>(self, dialect: &D) -> Vec<(SimpleExpr, Order, Option<NullOrdering>)> {
+        self.into_order_exprs_with(dialect, &|search| search)
+    }
+
+    /// Like [`Sort::into_order_exprs`] but every search is adjusted by `resolve` before it is rendered
+    pub(crate) fn into_order_exprs_with<D: Dialect>(
+        self,
+        dialect: &D,
+        resolve: &impl Fn(Search) -> Search,
+    ) -> Vec<(SimpleExpr, Order, Option<NullOrdering>)> {
+        self.exprs
+            .into_iter()
+            .map(|s| (s.target.into_expr(dialect, resolve), s.order, s.nulls))
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone)]
+enum SortTarget {
+    Expr(SimpleExpr),
+    SearchRank(Search),
+    Lower(Box<SortTarget>),
+    Coalesce(Vec<SortTarget>),
+}
+
+impl SortTarget {
+    fn into_expr<D: Dialect>(self, dialect: &D, resolve: &impl Fn(Search) -> Search) -> SimpleExpr {
+        match self {
+            SortTarget::Expr(expr) => expr,
+            SortTarget::SearchRank(search) => dialect.search_rank(&resolve(search)),
+            SortTarget::Lower(target) => Func::lower(target.into_expr(dialect, resolve)).into(),
+            SortTarget::Coalesce(targets) => {
+                Func::coalesce(targets.into_iter().map(|t| t.into_expr(dialect, resolve))).into()
+            }
+        }
     }
 }
 
 #[derive(Debug, Clone)]
 pub struct SortCol<Entity> {
-    col: ColumnRef,
+    target: SortTarget,
+    joins: Vec<Join>,
     entity: PhantomData<Entity>,
 }
 
 impl<Entity> SortCol<Entity> {
     pub fn new<C: IntoColumnRef>(col: C) -> Self {
+        Self::expr(Expr::col(col.into_column_ref()))
+    }
+
+    /// Sort by an arbitrary SQL expression, e.g. `Expr::cust("length(title)")`
+    pub fn expr(expr: impl Into<SimpleExpr>) -> Self {
         Self {
-            col: col.into_column_ref(),
+            target: SortTarget::Expr(expr.into()),
+            joins: Vec::new(),
             entity: PhantomData,
         }
     }
 
-    pub fn asc(self) -> SortExpr<Entity> {
-        SortExpr {
-            col: self.col,
+    /// A column of a related entity that is reachable through the given joins
+    pub fn with_joins(self, joins: impl IntoIterator<Item = Join>) -> Self {
+        Self {
+            joins: joins.into_iter().collect(),
+            ..self
+        }
+    }
+
+    /// Sort case-insensitively by `LOWER(..)` of this column
+    pub fn lower(self) -> Self {
+        Self {
+            target: SortTarget::Lower(Box::new(self.target)),
+            ..self
+        }
+    }
+
+    /// Sort by the first non-null value of this column and the given columns, i.e. `COALESCE(..)`
+    pub fn coalesce<I>(self, others: I) -> Self
+    where
+        I: IntoIterator<Item = SortCol<Entity>>,
+    {
+        let mut targets = vec![self.target];
+        let mut joins = self.joins;
+        for other in others {
+            targets.push(other.target);
+            merge_joins(&mut joins, other.joins);
+        }
+        Self {
+            target: SortTarget::Coalesce(targets),
+            joins,
             entity: PhantomData,
-            order: Order::Asc,
         }
     }
 
+    pub fn asc(self) -> SortExpr<Entity> {
+        self.order(Order::Asc)
+    }
+
     pub fn desc(self) -> SortExpr<Entity> {
+        self.order(Order::Desc)
+    }
+
+    fn order(self, order: Order) -> SortExpr<Entity> {
         SortExpr {
-            col: self.col,
+            target: self.target,
+            joins: self.joins,
+            entity: PhantomData,
+            order,
+            nulls: None,
+        }
+    }
+}
+
+impl<Entity: SearchableEntity> SortCol<Entity> {
+    /// Sort by relevance to a full-text search term. Use `desc()` to get the most relevant records first.
+    pub fn search_rank(term: impl Into<String>) -> Self {
+        Self {
+            target: SortTarget::SearchRank(Search::new::<Entity>(term)),
+            joins: Vec::new(),
             entity: PhantomData,
-            order: Order::Desc,
         }
     }
 }
 
 #[derive(Debug, Clone)]
 pub struct SortExpr<Entity> {
-    col: ColumnRef,
+    target: SortTarget,
+    joins: Vec<Join>,
     entity: PhantomData<Entity>,
     order: Order,
+    nulls: Option<NullOrdering>,
+}
+
+impl<Entity> SortExpr<Entity> {
+    /// Place `NULL` values before non-null values (`NULLS FIRST`)
+    pub fn nulls_first(self) -> Self {
+        Self {
+            nulls: Some(NullOrdering::First),
+            ..self
+        }
+    }
+
+    /// Place `NULL` values after non-null values (`NULLS LAST`)
+    pub fn nulls_last(self) -> Self {
+        Self {
+            nulls: Some(NullOrdering::Last),
+            ..self
+        }
+    }
 }
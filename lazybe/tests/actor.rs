@@ -0,0 +1,108 @@
+use axum::Router;
+use axum::body::Body;
+use axum::http::{Extensions, Method, Request, StatusCode};
+use lazybe::db::sqlite::SqliteDbCtx;
+use lazybe::macros::Entity;
+use lazybe::router::{CreateRouter, RouteConfig, UpdateRouter};
+use lazybe::schema::TableSchema;
+use sea_query::SqliteQueryBuilder;
+use serde::{Deserialize, Serialize};
+use sqlx::{Executor, Pool, Sqlite, SqlitePool};
+
+mod common;
+
+use common::send;
+
+/// Inserted into the request extensions by an authentication middleware
+#[derive(Clone)]
+struct User(String);
+
+/// A route config that acts for the user of a request, if any
+#[derive(Clone)]
+struct ActorState {
+    pool: SqlitePool,
+}
+
+impl RouteConfig for ActorState {
+    type Ctx = SqliteDbCtx;
+    type Db = Sqlite;
+
+    fn db_ctx(&self) -> (Self::Ctx, Pool<Self::Db>) {
+        (SqliteDbCtx, self.pool.clone())
+    }
+
+    fn actor(&self, extensions: &Extensions) -> Option<sea_query::Value> {
+        extensions.get::<User>().map(|User(name)| name.clone().into())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Entity)]
+#[lazybe(table = "review", endpoint = "/reviews")]
+pub struct Review {
+    #[lazybe(primary_key)]
+    pub id: i32,
+    pub text: String,
+    #[lazybe(created_by)]
+    pub created_by: String,
+    #[lazybe(updated_by)]
+    pub updated_by: String,
+}
+
+async fn setup() -> anyhow::Result<(SqlitePool, Router)> {
+    let pool = SqlitePool::connect("sqlite::memory:").await?;
+    pool.execute(Review::create_table().to_string(SqliteQueryBuilder).as_str())
+        .await?;
+    let router = Router::new()
+        .merge(Review::create_endpoint())
+        .merge(Review::update_endpoint())
+        .with_state(ActorState { pool: pool.clone() });
+    Ok((pool, router))
+}
+
+async fn request(router: &Router, method: Method, uri: &str, user: Option<&str>, body: &str) -> (StatusCode, String) {
+    let mut request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header("content-type", "application/json");
+    if let Some(user) = user {
+        request = request.extension(User(user.to_string()));
+    }
+    send(router, request.body(Body::from(body.to_string())).unwrap()).await
+}
+
+async fn reviews(pool: &SqlitePool) -> anyhow::Result<Vec<(String, String, String)>> {
+    Ok(
+        sqlx::query_as("SELECT text, created_by, updated_by FROM review ORDER BY id")
+            .fetch_all(pool)
+            .await?,
+    )
+}
+
+#[tokio::test]
+async fn required_actor_is_missing() -> anyhow::Result<()> {
+    let (pool, router) = setup().await?;
+
+    // a misconfigured server, not an invalid input
+    let (status, _) = request(&router, Method::POST, "/reviews", None, r#"{"text": "anonymous"}"#).await;
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+    assert!(reviews(&pool).await?.is_empty());
+
+    let (status, _) = request(&router, Method::POST, "/reviews", Some("alice"), r#"{"text": "hello"}"#).await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    // an update without an actor fails and keeps the last actor
+    let (status, _) = request(&router, Method::PATCH, "/reviews/1", None, r#"{"text": "anonymous"}"#).await;
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(
+        reviews(&pool).await?,
+        [("hello".to_string(), "alice".to_string(), "alice".to_string())]
+    );
+
+    let (status, _) = request(&router, Method::PATCH, "/reviews/1", Some("bob"), r#"{"text": "bye"}"#).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        reviews(&pool).await?,
+        [("bye".to_string(), "alice".to_string(), "bob".to_string())]
+    );
+    Ok(())
+}
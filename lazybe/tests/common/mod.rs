@@ -0,0 +1,153 @@
+#![allow(dead_code)]
+
+use std::borrow::Cow;
+use std::error::Error as StdError;
+use std::fmt::Display;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+use axum::body::Body;
+use axum::http::{Method, Request, StatusCode};
+use lazybe::db::sqlite::SqliteDbCtx;
+use lazybe::router::RouteConfig;
+use serde::Serializer;
+use serde::ser::Error as _;
+use sqlx::error::{DatabaseError, ErrorKind};
+use sqlx::{Pool, Sqlite, SqlitePool};
+use tower::ServiceExt;
+use tracing_subscriber::fmt::MakeWriter;
+
+/// A route config of the endpoints on a SQLite pool
+#[derive(Clone)]
+pub struct AppState {
+    pub pool: SqlitePool,
+}
+
+impl RouteConfig for AppState {
+    type Ctx = SqliteDbCtx;
+    type Db = Sqlite;
+
+    fn db_ctx(&self) -> (Self::Ctx, Pool<Self::Db>) {
+        (SqliteDbCtx, self.pool.clone())
+    }
+}
+
+/// Fail to serialize an empty string, so that the JSON of a record, e.g. a snapshot or an event payload, fails
+pub fn non_empty<S: Serializer>(value: &str, serializer: S) -> Result<S::Ok, S::Error> {
+    match value {
+        "" => Err(S::Error::custom("the value is empty")),
+        value => serializer.serialize_str(value),
+    }
+}
+
+/// Send a request with a JSON body to a router and read the status and the body of the response
+pub async fn request(router: &axum::Router, method: Method, uri: &str, body: &str) -> (StatusCode, String) {
+    let request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header("content-type", "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap();
+    send(router, request).await
+}
+
+/// Send a request to a router and read the status and the body of the response
+pub async fn send(router: &axum::Router, request: Request<Body>) -> (StatusCode, String) {
+    let response = router.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, String::from_utf8(body.to_vec()).unwrap())
+}
+
+/// The lines logged to a `tracing` subscriber
+#[derive(Clone, Default)]
+pub struct Logs(Arc<Mutex<Vec<u8>>>);
+
+impl Logs {
+    /// A subscriber that logs plain lines to these logs
+    pub fn subscriber(&self) -> impl tracing::Subscriber + Send + Sync + 'static {
+        tracing_subscriber::fmt()
+            .with_writer(self.clone())
+            .with_ansi(false)
+            .without_time()
+            .finish()
+    }
+
+    pub fn lines(&self) -> Vec<String> {
+        let buf = self.0.lock().unwrap();
+        String::from_utf8_lossy(&buf).lines().map(str::to_string).collect()
+    }
+}
+
+impl Write for Logs {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> MakeWriter<'a> for Logs {
+    type Writer = Logs;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        self.clone()
+    }
+}
+
+/// A database error with a given kind, SQLSTATE code and message, as reported by a database
+#[derive(Debug)]
+pub struct FakeDbError {
+    pub kind: ErrorKind,
+    pub code: Option<&'static str>,
+    pub message: &'static str,
+}
+
+impl FakeDbError {
+    pub fn into_error(self) -> sqlx::Error {
+        sqlx::Error::Database(Box::new(self))
+    }
+}
+
+impl Display for FakeDbError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.message)
+    }
+}
+
+impl StdError for FakeDbError {}
+
+impl DatabaseError for FakeDbError {
+    fn message(&self) -> &str {
+        self.message
+    }
+
+    fn code(&self) -> Option<Cow<'_, str>> {
+        self.code.map(Cow::Borrowed)
+    }
+
+    fn as_error(&self) -> &(dyn StdError + Send + Sync + 'static) {
+        self
+    }
+
+    fn as_error_mut(&mut self) -> &mut (dyn StdError + Send + Sync + 'static) {
+        self
+    }
+
+    fn into_error(self: Box<Self>) -> Box<dyn StdError + Send + Sync + 'static> {
+        self
+    }
+
+    fn kind(&self) -> ErrorKind {
+        match self.kind {
+            ErrorKind::UniqueViolation => ErrorKind::UniqueViolation,
+            ErrorKind::ForeignKeyViolation => ErrorKind::ForeignKeyViolation,
+            ErrorKind::NotNullViolation => ErrorKind::NotNullViolation,
+            ErrorKind::CheckViolation => ErrorKind::CheckViolation,
+            _ => ErrorKind::Other,
+        }
+    }
+}
@@ -0,0 +1,75 @@
+use lazybe::Error;
+use sqlx::error::ErrorKind;
+
+mod common;
+
+use common::FakeDbError;
+
+/// Classify an error with a SQLite message, which does not report the constraint separately
+fn classify(kind: ErrorKind, message: &'static str) -> Error {
+    Error::from(
+        FakeDbError {
+            kind,
+            code: None,
+            message,
+        }
+        .into_error(),
+    )
+}
+
+#[test]
+fn sqlite_unique_violation() {
+    let error = classify(ErrorKind::UniqueViolation, "UNIQUE constraint failed: book.isbn");
+    assert!(matches!(error, Error::UniqueViolation(_)));
+    let violation = error.violation().unwrap();
+    assert_eq!(violation.constraint(), None);
+    assert_eq!(violation.columns(), ["isbn"]);
+    assert_eq!(error.to_string(), "Unique constraint violation on `isbn`");
+
+    let error = classify(
+        ErrorKind::UniqueViolation,
+        "UNIQUE constraint failed: book.author_id, book.title",
+    );
+    assert_eq!(error.violation().unwrap().columns(), ["author_id", "title"]);
+    assert_eq!(error.to_string(), "Unique constraint violation on `author_id, title`");
+}
+
+#[test]
+fn sqlite_not_null_violation() {
+    let error = classify(ErrorKind::NotNullViolation, "NOT NULL constraint failed: book.title");
+    assert!(matches!(error, Error::NotNullViolation(_)));
+    assert_eq!(error.violation().unwrap().columns(), ["title"]);
+    assert_eq!(error.to_string(), "Not null constraint violation on `title`");
+}
+
+#[test]
+fn sqlite_foreign_key_violation() {
+    let error = classify(ErrorKind::ForeignKeyViolation, "FOREIGN KEY constraint failed");
+    assert!(matches!(error, Error::ForeignKeyViolation(_)));
+    let violation = error.violation().unwrap();
+    assert_eq!(violation.constraint(), None);
+    assert!(violation.columns().is_empty());
+    assert_eq!(error.to_string(), "Foreign key constraint violation");
+}
+
+#[test]
+fn sqlite_check_violation() {
+    let error = classify(ErrorKind::CheckViolation, "CHECK constraint failed: positive_pages");
+    assert!(matches!(error, Error::CheckViolation(_)));
+    let violation = error.violation().unwrap();
+    assert_eq!(violation.constraint(), Some("positive_pages"));
+    assert!(violation.columns().is_empty());
+    assert_eq!(error.to_string(), "Check constraint violation on `positive_pages`");
+
+    // an unnamed check constraint is reported by its expression
+    let error = classify(ErrorKind::CheckViolation, "CHECK constraint failed: pages > 0");
+    assert_eq!(error.violation().unwrap().constraint(), Some("pages > 0"));
+}
+
+#[test]
+fn other_error() {
+    let error = classify(ErrorKind::Other, "database is locked");
+    assert!(matches!(error, Error::Database(_)));
+    assert!(error.violation().is_none());
+    assert_eq!(error.to_string(), "error returned from database: database is locked");
+}
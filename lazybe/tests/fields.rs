@@ -0,0 +1,74 @@
+use axum::Json;
+use axum::http::{Method, StatusCode};
+use axum::response::{IntoResponse, Response};
+use lazybe::fields::{Fields, Partial};
+use lazybe::filter::Filter;
+use lazybe::macros::Entity;
+use lazybe::page::{Page, PaginationInput};
+use lazybe::router::{EmptyQuery, EntityCollectionApi, ListRouter};
+use lazybe::schema::TableSchema;
+use lazybe::sort::Sort;
+use sea_query::SqliteQueryBuilder;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use sqlx::{Executor, SqlitePool};
+
+mod common;
+
+use common::{AppState, request};
+
+#[derive(Debug, Clone, Serialize, Deserialize, Entity)]
+#[lazybe(table = "book", endpoint = "/books", collection_api = "manual")]
+pub struct Book {
+    #[lazybe(primary_key)]
+    pub id: i32,
+    pub title: String,
+}
+
+impl EntityCollectionApi for Book {
+    type Resp = Page<Self>;
+    type Query = EmptyQuery;
+
+    fn page_response(page: Page<Self>) -> Self::Resp {
+        page
+    }
+
+    fn partial_page_response(
+        page: Page<Partial<Self>>,
+        _input: &Self::Query,
+        _fields: &Fields<Self>,
+        _path: &str,
+    ) -> Response {
+        Json(page).into_response()
+    }
+
+    fn page_input(_input: &Self::Query) -> Option<PaginationInput> {
+        Some(PaginationInput { page: 0, limit: 1 })
+    }
+
+    fn filter_input(_input: &Self::Query) -> Filter<Self> {
+        Filter::empty()
+    }
+
+    fn sort_input(_input: &Self::Query) -> Sort<Self> {
+        Sort::new([BookSort::id().asc()])
+    }
+}
+
+#[tokio::test]
+async fn partial_page_keeps_page_info() -> anyhow::Result<()> {
+    let pool = SqlitePool::connect("sqlite::memory:").await?;
+    pool.execute(Book::create_table().to_string(SqliteQueryBuilder).as_str())
+        .await?;
+    pool.execute("INSERT INTO book (title) VALUES ('Dune'), ('Dune Messiah')")
+        .await?;
+
+    let router = Book::list_endpoint().with_state(AppState { pool });
+    let (status, body) = request(&router, Method::GET, "/books?fields=title", "").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        serde_json::from_str::<Value>(&body)?,
+        json!({"page": 0, "page_size": 1, "total_records": 2, "data": [{"title": "Dune"}]})
+    );
+    Ok(())
+}
@@ -0,0 +1,88 @@
+use lazybe::db::DbSql;
+use lazybe::db::sqlite::SqliteDbCtx;
+use lazybe::filter::Filter;
+use lazybe::macros::Entity;
+use lazybe::sort::Sort;
+use sea_query::{Alias, Asterisk, Cond, Query, SqliteQueryBuilder};
+
+#[derive(Debug, Clone, Entity)]
+#[lazybe(table = "book")]
+pub struct Book {
+    #[lazybe(primary_key)]
+    pub id: i32,
+    #[lazybe(searchable)]
+    pub title: String,
+    pub pages: i32,
+}
+
+fn list_sql(filter: Filter<Book>) -> String {
+    SqliteDbCtx.list_sql::<Book>(filter, Sort::empty(), None).remove(1)
+}
+
+#[test]
+fn search_in_all() {
+    let filter = Filter::all([
+        BookFilter::search("dune"),
+        BookFilter::pages().gt(100).into(),
+        BookFilter::search("messiah"),
+    ]);
+    assert_eq!(
+        list_sql(filter),
+        r#"SELECT COUNT(*) AS "count" FROM "book" WHERE "book"."id" IN (SELECT "id" FROM "book_fts" WHERE "book_fts" MATCH '"dune"') AND "book"."pages" > 100 AND "book"."id" IN (SELECT "id" FROM "book_fts" WHERE "book_fts" MATCH '"messiah"')"#
+    );
+}
+
+#[test]
+fn blank_search_in_any() {
+    // a blank search term matches every record
+    let filter = Filter::any([BookFilter::search(" "), BookFilter::pages().gt(100).into()]);
+    assert_eq!(
+        list_sql(filter),
+        r#"SELECT COUNT(*) AS "count" FROM "book" WHERE TRUE OR "book"."pages" > 100"#
+    );
+}
+
+#[test]
+fn search_in_any() {
+    let filter = Filter::any([BookFilter::search("dune"), BookFilter::pages().gt(100).into()]);
+    assert_eq!(
+        list_sql(filter),
+        r#"SELECT COUNT(*) AS "count" FROM "book" WHERE "book"."id" IN (SELECT "id" FROM "book_fts" WHERE "book_fts" MATCH '"dune"') OR "book"."pages" > 100"#
+    );
+}
+
+#[test]
+fn nested_search_in_any() {
+    let filter = Filter::any([
+        Filter::all([BookFilter::search("dune"), BookFilter::pages().gt(100).into()]),
+        BookFilter::pages().lt(10).into(),
+    ]);
+    assert_eq!(
+        list_sql(filter),
+        r#"SELECT COUNT(*) AS "count" FROM "book" WHERE ("book"."id" IN (SELECT "id" FROM "book_fts" WHERE "book_fts" MATCH '"dune"') AND "book"."pages" > 100) OR "book"."pages" < 10"#
+    );
+}
+
+#[test]
+fn search_in_not() {
+    let filter = BookFilter::search("dune messiah").not();
+    assert_eq!(
+        list_sql(filter),
+        r#"SELECT COUNT(*) AS "count" FROM "book" WHERE NOT "book"."id" IN (SELECT "id" FROM "book_fts" WHERE "book_fts" MATCH '"dune" "messiah"')"#
+    );
+}
+
+#[test]
+fn unrendered_search() {
+    // a filter converted to a condition without a dialect matches the search with `LIKE`
+    let filter = Filter::all([BookFilter::search("dune 50%"), BookFilter::pages().gt(100).into()]);
+    let query = Query::select()
+        .column(Asterisk)
+        .from(Alias::new("book"))
+        .cond_where(Cond::all().add(filter))
+        .to_owned();
+    assert_eq!(
+        query.to_string(SqliteQueryBuilder),
+        r#"SELECT * FROM "book" WHERE "book"."title" LIKE '%dune%' ESCAPE '\' AND "book"."title" LIKE '%50\%%' ESCAPE '\' AND "book"."pages" > 100"#
+    );
+}
@@ -0,0 +1,111 @@
+use axum::http::{Method, StatusCode};
+use lazybe::db::DbOps;
+use lazybe::db::sqlite::SqliteDbCtx;
+use lazybe::history::{HistoryEntity, HistoryOperation, HistoryRecord};
+use lazybe::macros::Entity;
+use lazybe::openapi::HistoryRouterDoc;
+use lazybe::router::HistoryRouter;
+use lazybe::schema::TableSchema;
+use sea_query::SqliteQueryBuilder;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::{Executor, SqlitePool};
+use utoipa::ToSchema;
+
+mod common;
+
+use common::{AppState, request};
+
+#[derive(Debug, Clone, Serialize, Deserialize, Entity, ToSchema)]
+#[lazybe(table = "article", endpoint = "/articles", derive_to_schema, history)]
+pub struct Article {
+    #[lazybe(primary_key)]
+    pub id: i32,
+    /// The snapshot of an article without a title fails
+    #[serde(serialize_with = "common::non_empty")]
+    pub title: String,
+}
+
+async fn history(pool: &SqlitePool, id: i32) -> anyhow::Result<Vec<HistoryRecord>> {
+    let router = Article::history_endpoint().with_state(AppState { pool: pool.clone() });
+    let (status, body) = request(&router, Method::GET, &format!("/articles/{}/history", id), "").await;
+    assert_eq!(status, StatusCode::OK);
+    Ok(serde_json::from_str(&body)?)
+}
+
+#[tokio::test]
+async fn record_history() -> anyhow::Result<()> {
+    let pool = SqlitePool::connect("sqlite::memory:").await?;
+    pool.execute(Article::create_table().to_string(SqliteQueryBuilder).as_str())
+        .await?;
+    pool.execute(Article::create_history_table().to_string(SqliteQueryBuilder).as_str())
+        .await?;
+    let ctx = SqliteDbCtx;
+
+    let create = CreateArticle {
+        title: "Dune".to_string(),
+    };
+    ctx.create_as::<Article>(&pool, create, "alice").await?;
+    let update = UpdateArticle {
+        title: Some("Dune Messiah".to_string()),
+    };
+    ctx.update::<Article>(&pool, 1, update.clone()).await?;
+    ctx.delete::<Article>(&pool, 1).await?;
+
+    // missing records are not recorded
+    assert!(ctx.update::<Article>(&pool, 1, update).await?.is_none());
+    ctx.delete::<Article>(&pool, 1).await?;
+
+    let records = history(&pool, 1).await?;
+    let changes = records
+        .iter()
+        .map(|r| (r.operation, r.actor.as_deref(), r.before.clone(), r.after.clone()))
+        .collect::<Vec<_>>();
+    let dune = json!({"id": 1, "title": "Dune"});
+    let messiah = json!({"id": 1, "title": "Dune Messiah"});
+    assert_eq!(
+        changes,
+        [
+            (HistoryOperation::Create, Some("alice"), None, Some(dune.clone())),
+            (HistoryOperation::Update, None, Some(dune), Some(messiah.clone())),
+            (HistoryOperation::Delete, None, Some(messiah), None),
+        ]
+    );
+    assert!(history(&pool, 2).await?.is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn failed_snapshot_fails_the_change() -> anyhow::Result<()> {
+    let pool = SqlitePool::connect("sqlite::memory:").await?;
+    pool.execute(Article::create_table().to_string(SqliteQueryBuilder).as_str())
+        .await?;
+    pool.execute(Article::create_history_table().to_string(SqliteQueryBuilder).as_str())
+        .await?;
+    let ctx = SqliteDbCtx;
+
+    let create = CreateArticle { title: String::new() };
+    let error = ctx.create::<Article>(&pool, create).await.unwrap_err();
+    assert!(matches!(error, sqlx::Error::Encode(_)), "{}", error);
+    let create = CreateArticle {
+        title: "Dune".to_string(),
+    };
+    ctx.create::<Article>(&pool, create).await?;
+    let update = UpdateArticle {
+        title: Some(String::new()),
+    };
+    assert!(ctx.update::<Article>(&pool, 1, update).await.is_err());
+
+    // the changes without a history record are rolled back
+    let articles: Vec<(i32, String)> = sqlx::query_as("SELECT id, title FROM article").fetch_all(&pool).await?;
+    assert_eq!(articles, [(1, "Dune".to_string())]);
+    assert_eq!(history(&pool, 1).await?.len(), 1);
+    Ok(())
+}
+
+#[test]
+fn history_doc() {
+    let openapi = Article::history_endpoint_doc(Some("Article"));
+    let operation = openapi.paths.paths["/articles/{id}/history"].get.as_ref().unwrap();
+    assert_eq!(operation.tags, Some(vec!["Article".to_string()]));
+}
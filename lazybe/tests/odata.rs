@@ -0,0 +1,181 @@
+use lazybe::db::DbSql;
+use lazybe::db::sqlite::SqliteDbCtx;
+use lazybe::macros::Entity;
+use lazybe::odata::{ODataPage, ODataQuery, ODataQueryParams};
+use lazybe::page::Page;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Entity)]
+#[lazybe(table = "book", endpoint = "/books", collection_api = "odata")]
+pub struct Book {
+    #[lazybe(primary_key)]
+    pub id: i32,
+    pub title: String,
+    pub pages: Option<i32>,
+}
+
+fn parse(params: ODataQueryParams) -> Result<ODataQuery<Book>, String> {
+    ODataQuery::parse(params).map_err(|e| e.to_string())
+}
+
+fn parse_filter(filter: &str) -> Result<ODataQuery<Book>, String> {
+    parse(ODataQueryParams {
+        filter: Some(filter.to_string()),
+        ..Default::default()
+    })
+}
+
+/// The data query of the list operation with the query options
+fn list_sql(query: ODataQuery<Book>) -> String {
+    let sort = query.sort().unwrap_or_else(lazybe::sort::Sort::empty);
+    SqliteDbCtx
+        .list_sql::<Book>(query.filter(), sort, query.page_input())
+        .remove(0)
+}
+
+fn where_clause(filter: &str) -> String {
+    let sql = list_sql(parse_filter(filter).unwrap());
+    sql.split_once(" WHERE ").unwrap().1.to_string()
+}
+
+#[test]
+fn filter_precedence() {
+    assert_eq!(
+        where_clause("id eq 1 or title eq 'Dune' and pages gt 300"),
+        r#""book"."id" = 1 OR ("book"."title" = 'Dune' AND "book"."pages" > 300)"#
+    );
+    assert_eq!(
+        where_clause("(id eq 1 or title eq 'Dune') and pages gt 300"),
+        r#"("book"."id" = 1 OR "book"."title" = 'Dune') AND "book"."pages" > 300"#
+    );
+    assert_eq!(
+        where_clause("not pages eq null and id in (1, 2)"),
+        r#"(NOT "book"."pages" IS NULL) AND "book"."id" IN (1, 2)"#
+    );
+    assert_eq!(
+        where_clause("startswith(title,'50%') or pages ne null"),
+        r#""book"."title" LIKE '50\%%' ESCAPE '\' OR "book"."pages" IS NOT NULL"#
+    );
+}
+
+#[test]
+fn filter_errors() {
+    let cases = [
+        ("title eq 'Dune", "Unterminated string literal"),
+        ("pages gt", "Unexpected end of $filter"),
+        ("(pages gt 300", "Unexpected end of $filter"),
+        ("pages gt 300)", "Unexpected `)` in $filter"),
+        ("pages gt 300 and", "Unexpected end of $filter"),
+        ("pages gt 300 pages", "Unexpected `pages` in $filter"),
+        ("title has 'Dune'", "Unsupported operator `has`"),
+        ("pages gt null", "Operator `gt` cannot be used with null"),
+        ("substringof(title,'Dune')", "Unsupported function `substringof`"),
+        ("contains(title)", "Unexpected `)` in $filter"),
+        ("author eq 'Frank Herbert'", "Unknown property `author`"),
+        ("contains(author,'Frank')", "Unknown property `author`"),
+    ];
+    for (filter, error) in cases {
+        assert_eq!(parse_filter(filter).unwrap_err(), error, "{}", filter);
+    }
+    assert!(
+        parse_filter("pages eq 'many'")
+            .unwrap_err()
+            .starts_with("Invalid value for property `pages`")
+    );
+}
+
+#[test]
+fn orderby() {
+    let query = parse(ODataQueryParams {
+        orderby: Some("title desc, id".to_string()),
+        ..Default::default()
+    });
+    assert!(list_sql(query.unwrap()).ends_with(r#"ORDER BY "book"."title" DESC, "book"."id" ASC"#));
+
+    let error = |orderby: &str| {
+        parse(ODataQueryParams {
+            orderby: Some(orderby.to_string()),
+            ..Default::default()
+        })
+        .unwrap_err()
+    };
+    assert_eq!(error("title up"), "Invalid $orderby item `title up`");
+    assert_eq!(error("title,"), "Invalid $orderby item ``");
+    assert_eq!(error("author"), "Unknown property `author`");
+}
+
+#[test]
+fn select() {
+    let page = || Page {
+        page: 0,
+        page_size: 1,
+        total_records: 1,
+        data: vec![Book {
+            id: 1,
+            title: "Dune".to_string(),
+            pages: None,
+        }],
+    };
+    let response = |select: &str| {
+        let query = parse(ODataQueryParams {
+            select: Some(select.to_string()),
+            ..Default::default()
+        })?;
+        Ok::<_, String>(serde_json::to_value(query.response(page(), "/books")).unwrap())
+    };
+    assert_eq!(
+        response("title, id"),
+        Ok(json!({"value": [{"id": 1, "title": "Dune"}]}))
+    );
+    assert_eq!(
+        response("title,*"),
+        Ok(json!({"value": [{"id": 1, "title": "Dune", "pages": null}]}))
+    );
+    assert_eq!(response("title,author"), Err("Unknown property `author`".to_string()));
+}
+
+#[test]
+fn top_and_skip() {
+    let query = |top: Option<u32>, skip: Option<u32>| {
+        parse(ODataQueryParams {
+            top,
+            skip,
+            ..Default::default()
+        })
+    };
+    assert!(list_sql(query(Some(10), Some(20)).unwrap()).ends_with("LIMIT 10 OFFSET 20"));
+    assert!(list_sql(query(Some(10), None).unwrap()).ends_with("LIMIT 10 OFFSET 0"));
+    // a page that contains the records from 5 to 15 and from 15 to 25
+    assert!(list_sql(query(Some(10), Some(5)).unwrap()).ends_with("LIMIT 15 OFFSET 0"));
+    assert!(list_sql(query(Some(10), Some(15)).unwrap()).ends_with("LIMIT 13 OFFSET 13"));
+    // without `$top`, all records are listed
+    assert!(!list_sql(query(None, Some(5)).unwrap()).contains("LIMIT"));
+    assert_eq!(query(Some(0), None).unwrap_err(), "$top must be greater than 0");
+
+    let books = |ids: std::ops::Range<i32>, total_records: u32| Page {
+        page: 0,
+        page_size: 0,
+        total_records,
+        data: ids
+            .map(|id| Book {
+                id,
+                title: format!("Book {}", id),
+                pages: None,
+            })
+            .collect::<Vec<_>>(),
+    };
+    let ids = |response: &ODataPage<Book>| response.value.iter().map(|b| b.id).collect::<Vec<_>>();
+
+    let response = query(Some(10), Some(5)).unwrap().response(books(0..15, 30), "/books");
+    assert_eq!(ids(&response), (5..15).collect::<Vec<_>>());
+    assert_eq!(response.next_link.as_deref(), Some("/books?%24top=10&%24skip=15"));
+
+    let response = query(Some(10), Some(15)).unwrap().response(books(13..26, 30), "/books");
+    assert_eq!(ids(&response), (15..25).collect::<Vec<_>>());
+    assert_eq!(response.next_link.as_deref(), Some("/books?%24top=10&%24skip=25"));
+
+    let response = query(None, Some(25)).unwrap().response(books(0..30, 30), "/books");
+    assert_eq!(ids(&response), (25..30).collect::<Vec<_>>());
+    assert_eq!(response.next_link, None);
+}
@@ -0,0 +1,221 @@
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use lazybe::db::DbOps;
+use lazybe::db::sqlite::SqliteDbCtx;
+use lazybe::macros::Entity;
+use lazybe::outbox::{self, EntityEvent, OutboxDispatcher, OutboxMessage, OutboxWorker};
+use lazybe::schema::TableSchema;
+use sea_query::SqliteQueryBuilder;
+use serde::{Deserialize, Serialize};
+use sqlx::{Executor, SqlitePool};
+
+mod common;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Entity)]
+#[lazybe(table = "book", outbox)]
+pub struct Book {
+    #[lazybe(primary_key)]
+    pub id: i32,
+    /// The payload of a book without a title fails
+    #[serde(serialize_with = "common::non_empty")]
+    pub title: String,
+}
+
+async fn setup() -> anyhow::Result<SqlitePool> {
+    let pool = SqlitePool::connect("sqlite::memory:").await?;
+    pool.execute(Book::create_table().to_string(SqliteQueryBuilder).as_str())
+        .await?;
+    pool.execute(outbox::create_table().to_string(SqliteQueryBuilder).as_str())
+        .await?;
+    Ok(pool)
+}
+
+async fn create_books(pool: &SqlitePool, titles: &[&str]) -> anyhow::Result<()> {
+    for title in titles {
+        let create = CreateBook {
+            title: title.to_string(),
+        };
+        SqliteDbCtx.create::<Book>(pool, create).await?;
+    }
+    Ok(())
+}
+
+#[derive(Default)]
+struct CollectDispatcher {
+    unavailable: AtomicBool,
+    messages: Mutex<Vec<OutboxMessage>>,
+}
+
+impl OutboxDispatcher for &CollectDispatcher {
+    type Error = String;
+
+    async fn dispatch(&self, message: &OutboxMessage) -> Result<(), Self::Error> {
+        if self.unavailable.load(Ordering::SeqCst) {
+            return Err("broker is unavailable".to_string());
+        }
+        self.messages.lock().unwrap().push(message.clone());
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn dispatch_events() -> anyhow::Result<()> {
+    let pool = setup().await?;
+    let ctx = SqliteDbCtx;
+
+    let create = CreateBook {
+        title: "Dune".to_string(),
+    };
+    ctx.create::<Book>(&pool, create.clone()).await?;
+    let update = UpdateBook {
+        title: Some("Dune Messiah".to_string()),
+    };
+    ctx.update::<Book>(&pool, 1, update.clone()).await?;
+    ctx.delete::<Book>(&pool, 1).await?;
+
+    // no events for missing records and rolled back changes
+    ctx.update::<Book>(&pool, 1, update).await?;
+    let mut tx = pool.begin().await?;
+    ctx.create::<Book>(&mut *tx, create).await?;
+    tx.rollback().await?;
+
+    let dispatcher = CollectDispatcher::default();
+    let worker = OutboxWorker::new(ctx, pool.clone(), &dispatcher)
+        .batch_size(2)
+        .backoff(Duration::ZERO, Duration::ZERO);
+
+    // failed deliveries are retried
+    dispatcher.unavailable.store(true, Ordering::SeqCst);
+    assert_eq!(worker.dispatch_pending().await?, 0);
+    dispatcher.unavailable.store(false, Ordering::SeqCst);
+    assert_eq!(worker.dispatch_pending().await?, 2);
+    assert_eq!(worker.dispatch_pending().await?, 1);
+    assert_eq!(worker.dispatch_pending().await?, 0);
+
+    let messages = dispatcher.messages.into_inner().unwrap();
+    assert_eq!(messages.iter().map(|m| m.attempts).collect::<Vec<_>>(), [1, 1, 0]);
+    assert_eq!(messages[0].last_error.as_deref(), Some("broker is unavailable"));
+    assert!(messages.iter().all(|m| m.is_of::<Book>()));
+    let events = messages
+        .iter()
+        .map(|m| m.decode::<Book>())
+        .collect::<Result<Vec<_>, _>>()?;
+    let dune = Book {
+        id: 1,
+        title: "Dune".to_string(),
+    };
+    let messiah = Book {
+        id: 1,
+        title: "Dune Messiah".to_string(),
+    };
+    assert_eq!(
+        events,
+        [
+            EntityEvent::Created(dune),
+            EntityEvent::Updated(messiah.clone()),
+            EntityEvent::Deleted(messiah),
+        ]
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn back_off_and_park_failed_events() -> anyhow::Result<()> {
+    let pool = setup().await?;
+    create_books(&pool, &["Dune", "Dune Messiah"]).await?;
+    let dispatcher = CollectDispatcher::default();
+    let worker = OutboxWorker::new(SqliteDbCtx, pool.clone(), &dispatcher)
+        .max_attempts(2)
+        .backoff(Duration::from_secs(60 * 60), Duration::from_secs(60 * 60));
+
+    // the failed events are not retried before their backoff
+    dispatcher.unavailable.store(true, Ordering::SeqCst);
+    assert_eq!(worker.dispatch_pending().await?, 0);
+    dispatcher.unavailable.store(false, Ordering::SeqCst);
+    assert_eq!(worker.dispatch_pending().await?, 0);
+    create_books(&pool, &["Children of Dune"]).await?;
+    assert_eq!(worker.dispatch_pending().await?, 1);
+
+    // the events failing too many times are parked until they are requeued, once the backoff has elapsed
+    pool.execute("UPDATE lazybe_outbox SET next_attempt_at = NULL").await?;
+    dispatcher.unavailable.store(true, Ordering::SeqCst);
+    assert_eq!(worker.dispatch_pending().await?, 0);
+    dispatcher.unavailable.store(false, Ordering::SeqCst);
+    pool.execute("UPDATE lazybe_outbox SET next_attempt_at = NULL").await?;
+    assert_eq!(worker.dispatch_pending().await?, 0);
+    let parked: Vec<(i64, i32)> = sqlx::query_as("SELECT id, attempts FROM lazybe_outbox WHERE parked_at IS NOT NULL")
+        .fetch_all(&pool)
+        .await?;
+    assert_eq!(parked, [(1, 2), (2, 2)]);
+
+    worker.requeue_parked().await?;
+    assert_eq!(worker.dispatch_pending().await?, 2);
+    let messages = dispatcher.messages.into_inner().unwrap();
+    let delivered = messages.iter().map(|m| (m.id, m.attempts)).collect::<Vec<_>>();
+    assert_eq!(delivered, [(3, 0), (1, 0), (2, 0)]);
+    assert!(messages[1].last_error.is_some());
+    Ok(())
+}
+
+/// Reads the state of a message in the database while delivering it
+struct ClaimDispatcher {
+    pool: SqlitePool,
+    claimed: Mutex<Vec<bool>>,
+}
+
+impl OutboxDispatcher for &ClaimDispatcher {
+    type Error = sqlx::Error;
+
+    async fn dispatch(&self, message: &OutboxMessage) -> Result<(), Self::Error> {
+        let (claimed,): (bool,) = sqlx::query_as("SELECT next_attempt_at IS NOT NULL FROM lazybe_outbox WHERE id = ?")
+            .bind(message.id)
+            .fetch_one(&self.pool)
+            .await?;
+        self.claimed.lock().unwrap().push(claimed);
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn claim_events_before_delivery() -> anyhow::Result<()> {
+    let pool = setup().await?;
+    create_books(&pool, &["Dune", "Dune Messiah"]).await?;
+    let dispatcher = ClaimDispatcher {
+        pool: pool.clone(),
+        claimed: Mutex::default(),
+    };
+    let worker = OutboxWorker::new(SqliteDbCtx, pool.clone(), &dispatcher);
+
+    // the claim is committed, so it is seen by other connections during the delivery
+    assert_eq!(worker.dispatch_pending().await?, 2);
+    assert_eq!(dispatcher.claimed.into_inner().unwrap(), [true, true]);
+
+    // the events of a poll that stopped before marking them are claimed again after the timeout
+    pool.execute("UPDATE lazybe_outbox SET dispatched_at = NULL").await?;
+    let dispatcher = CollectDispatcher::default();
+    let worker = OutboxWorker::new(SqliteDbCtx, pool.clone(), &dispatcher);
+    assert_eq!(worker.dispatch_pending().await?, 0);
+    pool.execute("UPDATE lazybe_outbox SET next_attempt_at = '2000-01-01 00:00:00.000000 +00:00'")
+        .await?;
+    assert_eq!(worker.dispatch_pending().await?, 2);
+    Ok(())
+}
+
+#[tokio::test]
+async fn failed_payload_fails_the_change() -> anyhow::Result<()> {
+    let pool = setup().await?;
+
+    let create = CreateBook { title: String::new() };
+    let error = SqliteDbCtx.create::<Book>(&pool, create).await.unwrap_err();
+    assert!(matches!(error, sqlx::Error::Encode(_)), "{}", error);
+
+    // the change without an event is rolled back
+    let (books,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM book").fetch_one(&pool).await?;
+    let (events,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM lazybe_outbox")
+        .fetch_one(&pool)
+        .await?;
+    assert_eq!((books, events), (0, 0));
+    Ok(())
+}
@@ -0,0 +1,185 @@
+use axum::http::{Method, StatusCode};
+use lazybe::db::sqlite::SqliteDbCtx;
+use lazybe::db::{DbCtx, DbOps};
+use lazybe::entity::ops::CreateEntity;
+use lazybe::macros::{Entity, EntityEndpoint};
+use lazybe::openapi::CrudRouterDoc;
+use lazybe::registry;
+use lazybe::router::{CreateRouter, CrudEndpoints, CrudOperation, CrudOptions, CrudRouter, RouteConfig};
+use serde::{Deserialize, Serialize};
+use sqlx::{Acquire, Executor, Pool, Sqlite, SqlitePool};
+use utoipa::ToSchema;
+
+mod common;
+
+use common::{AppState, Logs, request};
+
+#[derive(Clone)]
+struct AdminState {
+    pool: SqlitePool,
+}
+
+impl RouteConfig for AdminState {
+    type Ctx = SqliteDbCtx;
+    type Db = Sqlite;
+
+    fn db_ctx(&self) -> (Self::Ctx, Pool<Self::Db>) {
+        (SqliteDbCtx, self.pool.clone())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Entity, ToSchema)]
+#[lazybe(table = "book", endpoint = "/books", derive_to_schema, register = "AppState")]
+pub struct Book {
+    #[lazybe(primary_key)]
+    pub id: i32,
+    pub title: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, EntityEndpoint, ToSchema)]
+#[lazybe(endpoint = "/book-batches", create_ty = "Vec<CreateBook>", register = "AppState")]
+pub struct BookBatch {
+    books: Vec<Book>,
+}
+
+impl CreateEntity<Sqlite> for BookBatch {
+    async fn create<'a, Ctx>(
+        ctx: &Ctx,
+        conn: impl Acquire<'a, Database = Sqlite> + Send + 'a,
+        input: Self::Create,
+    ) -> Result<Self, sqlx::Error>
+    where
+        Ctx: DbCtx<Sqlite> + Sync,
+    {
+        let mut tx = conn.begin().await?;
+        let mut books = Vec::with_capacity(input.len());
+        for create_book in input {
+            books.push(ctx.create::<Book>(&mut tx, create_book).await?);
+        }
+        tx.commit().await?;
+        Ok(BookBatch { books })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Entity)]
+#[lazybe(table = "audit_log", endpoint = "/audit-logs", register = "AdminState")]
+pub struct AuditLog {
+    #[lazybe(primary_key)]
+    pub id: i32,
+    pub message: String,
+}
+
+#[tokio::test]
+async fn registered_endpoints() -> anyhow::Result<()> {
+    let pool = SqlitePool::connect("sqlite::memory:").await?;
+    pool.execute("CREATE TABLE book (id INTEGER PRIMARY KEY AUTOINCREMENT, title TEXT NOT NULL)")
+        .await?;
+    let router = registry::router::<AppState>().with_state(AppState { pool });
+
+    let (status, _) = request(&router, Method::POST, "/book-batches", r#"[{"title": "Dune"}]"#).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let (status, body) = request(&router, Method::GET, "/books/1", "").await;
+    assert_eq!((status, body.as_str()), (StatusCode::OK, r#"{"id":1,"title":"Dune"}"#));
+    let (status, _) = request(&router, Method::PATCH, "/books/1", r#"{"title": "Dune Messiah"}"#).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // the batch only supports create and the audit log is registered for another state
+    let (status, _) = request(&router, Method::GET, "/book-batches/1", "").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = request(&router, Method::GET, "/audit-logs", "").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    Ok(())
+}
+
+/// Only the create operation, which is the only one supported by the batch
+struct CreateOnly;
+
+impl<T, S, Db> CrudEndpoints<T, S, Db> for CreateOnly
+where
+    T: CreateRouter<S, Db>,
+{
+    fn endpoint(operation: CrudOperation) -> Option<axum::Router<S>> {
+        match operation {
+            CrudOperation::Create => Some(T::create_endpoint()),
+            _ => None,
+        }
+    }
+}
+
+#[tokio::test]
+async fn crud_router_of_supported_operations() -> anyhow::Result<()> {
+    let pool = SqlitePool::connect("sqlite::memory:").await?;
+    pool.execute("CREATE TABLE book (id INTEGER PRIMARY KEY AUTOINCREMENT, title TEXT NOT NULL)")
+        .await?;
+    let router = axum::Router::new()
+        .merge(BookBatch::crud_router(&CrudOptions::<CreateOnly>::default()))
+        .merge(Book::crud_router(&CrudOptions::read_only().prefix("/read")))
+        .with_state(AppState { pool });
+
+    let (status, _) = request(&router, Method::POST, "/book-batches", r#"[{"title": "Dune"}]"#).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let (status, body) = request(&router, Method::GET, "/read/books/1", "").await;
+    assert_eq!((status, body.as_str()), (StatusCode::OK, r#"{"id":1,"title":"Dune"}"#));
+    let (status, _) = request(&router, Method::GET, "/read/books", "").await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = request(&router, Method::PATCH, "/read/books/1", r#"{"title": "Dune Messiah"}"#).await;
+    assert_eq!(status, StatusCode::METHOD_NOT_ALLOWED);
+    let (status, _) = request(&router, Method::POST, "/read/books", r#"{"title": "Dune Messiah"}"#).await;
+    assert_eq!(status, StatusCode::METHOD_NOT_ALLOWED);
+
+    let openapi = Book::crud_doc(&CrudOptions::read_only());
+    let paths = openapi.paths.paths.keys().collect::<Vec<_>>();
+    assert_eq!(paths, ["/books", "/books/{id}"]);
+    let book = &openapi.paths.paths["/books/{id}"];
+    assert!(book.get.is_some() && book.patch.is_none() && book.delete.is_none());
+    assert!(openapi.paths.paths["/books"].post.is_none());
+    Ok(())
+}
+
+#[tokio::test]
+async fn prefixed_error_url() -> anyhow::Result<()> {
+    // the table is missing, so the request fails with an error that is logged
+    let pool = SqlitePool::connect("sqlite::memory:").await?;
+    let router = axum::Router::new()
+        .merge(Book::crud_router(&CrudOptions::new().prefix("/api/v1")))
+        .with_state(AppState { pool });
+
+    let logs = Logs::default();
+    let _guard = tracing::subscriber::set_default(logs.subscriber());
+    let (status, _) = request(&router, Method::GET, "/api/v1/books/1", "").await;
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+    let (status, _) = request(&router, Method::GET, "/api/v1/books", "").await;
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+
+    let lines = logs.lines();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].contains(r#"url="/api/v1/books/{id}""#), "{}", lines[0]);
+    assert!(lines[1].contains(r#"url="/api/v1/books""#), "{}", lines[1]);
+    Ok(())
+}
+
+#[test]
+fn registered_docs() {
+    let openapi = registry::openapi::<AppState>();
+    let paths = openapi.paths.paths.keys().collect::<Vec<_>>();
+    assert_eq!(paths, ["/book-batches", "/books", "/books/{id}"]);
+
+    let batches = &openapi.paths.paths["/book-batches"];
+    assert!(batches.post.is_some() && batches.get.is_none());
+    let tags = batches.post.as_ref().and_then(|op| op.tags.clone());
+    assert_eq!(tags, Some(vec!["BookBatch".to_string()]));
+
+    // the endpoints of the audit log cannot be documented without `ToSchema`, which is warned about
+    let warnings = Logs::default();
+    let openapi = tracing::subscriber::with_default(warnings.subscriber(), registry::openapi::<AdminState>);
+    assert!(openapi.paths.paths.is_empty());
+    let warnings = warnings.lines();
+    assert_eq!(warnings.len(), 5);
+    assert!(warnings[0].contains("The endpoints are not documented since the entity does not implement GetRouterDoc"));
+    assert!(warnings.iter().all(|w| w.contains("WARN") && w.contains("AuditLog")));
+
+    let entities = registry::entities()
+        .map(|e| (e.name(), e.is_registered_for::<AppState>()))
+        .collect::<Vec<_>>();
+    assert_eq!(entities, [("AuditLog", false), ("Book", true), ("BookBatch", true)]);
+}
@@ -0,0 +1,422 @@
+use lazybe::db::DbSql;
+use lazybe::filter::Filter;
+use lazybe::macros::Entity;
+use lazybe::page::PaginationInput;
+use lazybe::sort::Sort;
+
+#[derive(Debug, Clone, Entity)]
+#[lazybe(table = "author")]
+pub struct Author {
+    #[lazybe(primary_key)]
+    pub id: i32,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Entity)]
+#[lazybe(table = "book")]
+pub struct Book {
+    #[lazybe(primary_key)]
+    pub id: i32,
+    #[lazybe(searchable)]
+    pub title: String,
+    pub pages: Option<i32>,
+    #[lazybe(references = "Author")]
+    pub author_id: i32,
+}
+
+#[derive(Debug, Clone, Entity)]
+#[lazybe(table = "note")]
+pub struct Note {
+    #[lazybe(primary_key)]
+    pub id: i32,
+    #[lazybe(tenant)]
+    pub tenant_id: i32,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Entity)]
+#[lazybe(table = "task")]
+pub struct Task {
+    #[lazybe(primary_key)]
+    pub id: i32,
+    #[lazybe(tenant)]
+    pub tenant_id: i32,
+    #[lazybe(references = "Note")]
+    pub note_id: i32,
+}
+
+#[derive(Debug, Clone, Entity)]
+#[lazybe(table = "comment")]
+pub struct Comment {
+    #[lazybe(primary_key)]
+    pub id: i32,
+    pub text: String,
+    #[lazybe(created_by)]
+    pub created_by: Option<String>,
+    #[lazybe(updated_by)]
+    pub updated_by: Option<String>,
+}
+
+#[derive(Debug, Clone, Entity)]
+#[lazybe(table = "review")]
+pub struct Review {
+    #[lazybe(primary_key)]
+    pub id: i32,
+    pub text: String,
+    #[lazybe(created_by)]
+    pub created_by: String,
+    #[lazybe(updated_by)]
+    pub updated_by: String,
+}
+
+fn list_input() -> (Filter<Book>, Sort<Book>, Option<PaginationInput>) {
+    let filter = Filter::all([
+        BookFilter::title().like("%Dune%".to_string()),
+        BookFilter::author().name().eq("Frank Herbert".to_string()),
+    ]);
+    let sort = Sort::new([BookSort::author().name().asc(), BookSort::id().desc()]);
+    let pagination = Some(PaginationInput { page: 2, limit: 10 });
+    (filter, sort, pagination)
+}
+
+fn create_input() -> CreateBook {
+    CreateBook {
+        title: "Dune".to_string(),
+        pages: None,
+        author_id: 1,
+    }
+}
+
+fn update_input() -> UpdateBook {
+    UpdateBook {
+        title: Some("Dune Messiah".to_string()),
+        pages: Some(Some(256)),
+        ..Default::default()
+    }
+}
+
+#[test]
+#[cfg(feature = "sqlite")]
+fn sqlite_sql() {
+    let ctx = lazybe::db::sqlite::SqliteDbCtx;
+    let (filter, sort, pagination) = list_input();
+
+    assert_eq!(
+        ctx.get_sql::<Book>(1),
+        [r#"SELECT "id", "title", "pages", "author_id" FROM "book" WHERE "id" = 1"#]
+    );
+    assert_eq!(
+        ctx.list_sql::<Book>(filter, sort, pagination),
+        [
+            r#"SELECT "book"."id", "book"."title", "book"."pages", "book"."author_id" FROM "book" LEFT JOIN "author" AS "book__author" ON "book"."author_id" = "book__author"."id" WHERE "book"."title" LIKE '%Dune%' AND "book__author"."name" = 'Frank Herbert' ORDER BY "book__author"."name" ASC, "book"."id" DESC LIMIT 10 OFFSET 20"#,
+            r#"SELECT COUNT(*) AS "count" FROM "book" LEFT JOIN "author" AS "book__author" ON "book"."author_id" = "book__author"."id" WHERE "book"."title" LIKE '%Dune%' AND "book__author"."name" = 'Frank Herbert'"#,
+        ]
+    );
+    assert_eq!(
+        ctx.create_sql::<Book>(create_input()).unwrap(),
+        [r#"INSERT INTO "book" ("title", "pages", "author_id") VALUES ('Dune', NULL, 1) RETURNING *"#]
+    );
+    assert_eq!(
+        ctx.update_sql::<Book>(1, update_input()).unwrap(),
+        [r#"UPDATE "book" SET "title" = 'Dune Messiah', "pages" = 256 WHERE "id" = 1 RETURNING *"#]
+    );
+    assert_eq!(ctx.delete_sql::<Book>(1), [r#"DELETE FROM "book" WHERE "id" = 1"#]);
+}
+
+#[test]
+#[cfg(feature = "postgres")]
+fn postgres_sql() {
+    let ctx = lazybe::db::postgres::PostgresDbCtx;
+    let (filter, sort, pagination) = list_input();
+
+    assert_eq!(
+        ctx.get_sql::<Book>(1),
+        [r#"SELECT "id", "title", "pages", "author_id" FROM "book" WHERE "id" = 1"#]
+    );
+    assert_eq!(
+        ctx.list_sql::<Book>(filter, sort, pagination),
+        [
+            r#"SELECT "book"."id", "book"."title", "book"."pages", "book"."author_id" FROM "book" LEFT JOIN "author" AS "book__author" ON "book"."author_id" = "book__author"."id" WHERE "book"."title" LIKE '%Dune%' AND "book__author"."name" = 'Frank Herbert' ORDER BY "book__author"."name" ASC, "book"."id" DESC LIMIT 10 OFFSET 20"#,
+            r#"SELECT COUNT(*) AS "count" FROM "book" LEFT JOIN "author" AS "book__author" ON "book"."author_id" = "book__author"."id" WHERE "book"."title" LIKE '%Dune%' AND "book__author"."name" = 'Frank Herbert'"#,
+        ]
+    );
+    assert_eq!(
+        ctx.create_sql::<Book>(create_input()).unwrap(),
+        [r#"INSERT INTO "book" ("title", "pages", "author_id") VALUES ('Dune', NULL, 1) RETURNING *"#]
+    );
+    assert_eq!(
+        ctx.update_sql::<Book>(1, update_input()).unwrap(),
+        [r#"UPDATE "book" SET "title" = 'Dune Messiah', "pages" = 256 WHERE "id" = 1 RETURNING *"#]
+    );
+    assert_eq!(ctx.delete_sql::<Book>(1), [r#"DELETE FROM "book" WHERE "id" = 1"#]);
+}
+
+#[test]
+#[cfg(feature = "mysql")]
+fn mysql_sql() {
+    let ctx = lazybe::db::mysql::MySqlDbCtx;
+    let (filter, sort, pagination) = list_input();
+
+    assert_eq!(
+        ctx.get_sql::<Book>(1),
+        [r#"SELECT `id`, `title`, `pages`, `author_id` FROM `book` WHERE `id` = 1"#]
+    );
+    assert_eq!(
+        ctx.list_sql::<Book>(filter, sort, pagination),
+        [
+            r#"SELECT `book`.`id`, `book`.`title`, `book`.`pages`, `book`.`author_id` FROM `book` LEFT JOIN `author` AS `book__author` ON `book`.`author_id` = `book__author`.`id` WHERE `book`.`title` LIKE '%Dune%' AND `book__author`.`name` = 'Frank Herbert' ORDER BY `book__author`.`name` ASC, `book`.`id` DESC LIMIT 10 OFFSET 20"#,
+            r#"SELECT COUNT(*) AS `count` FROM `book` LEFT JOIN `author` AS `book__author` ON `book`.`author_id` = `book__author`.`id` WHERE `book`.`title` LIKE '%Dune%' AND `book__author`.`name` = 'Frank Herbert'"#,
+        ]
+    );
+    assert_eq!(
+        ctx.create_sql::<Book>(create_input()).unwrap(),
+        [
+            r#"INSERT INTO `book` (`title`, `pages`, `author_id`) VALUES ('Dune', NULL, 1)"#,
+            r#"SELECT `id`, `title`, `pages`, `author_id` FROM `book` WHERE `id` = (LAST_INSERT_ID())"#,
+        ]
+    );
+    assert_eq!(
+        ctx.update_sql::<Book>(1, update_input()).unwrap(),
+        [
+            r#"UPDATE `book` SET `title` = 'Dune Messiah', `pages` = 256 WHERE `id` = 1"#,
+            r#"SELECT `id`, `title`, `pages`, `author_id` FROM `book` WHERE `id` = 1"#,
+        ]
+    );
+    assert_eq!(ctx.delete_sql::<Book>(1), [r#"DELETE FROM `book` WHERE `id` = 1"#]);
+}
+
+#[test]
+#[cfg(feature = "sqlite")]
+fn tenant_sql() {
+    use lazybe::db::TenantDbCtx;
+    use lazybe::db::sqlite::SqliteDbCtx;
+
+    let ctx = TenantDbCtx::new(SqliteDbCtx, Some(7.into()));
+    let create = CreateNote {
+        text: "hello".to_string(),
+    };
+    let update = UpdateNote {
+        text: Some("bye".to_string()),
+    };
+
+    assert_eq!(
+        ctx.list_sql::<Note>(Filter::empty(), Sort::empty(), None),
+        [
+            r#"SELECT "note"."id", "note"."tenant_id", "note"."text" FROM "note" WHERE TRUE AND "note"."tenant_id" = 7"#,
+            r#"SELECT COUNT(*) AS "count" FROM "note" WHERE TRUE AND "note"."tenant_id" = 7"#,
+        ]
+    );
+    assert_eq!(
+        ctx.create_sql::<Note>(create.clone()).unwrap(),
+        [r#"INSERT INTO "note" ("text", "tenant_id") VALUES ('hello', 7) RETURNING *"#]
+    );
+    assert_eq!(
+        ctx.update_sql::<Note>(1, update).unwrap(),
+        [r#"UPDATE "note" SET "text" = 'bye' WHERE "id" = 1 AND "tenant_id" = 7 RETURNING *"#]
+    );
+    assert_eq!(
+        ctx.delete_sql::<Note>(1),
+        [r#"DELETE FROM "note" WHERE "id" = 1 AND "tenant_id" = 7"#]
+    );
+
+    // the joined records are scoped to the tenant too
+    assert_eq!(
+        ctx.list_sql::<Task>(
+            Filter::all([TaskFilter::note().text().eq("hello".to_string())]),
+            Sort::empty(),
+            None
+        ),
+        [
+            r#"SELECT "task"."id", "task"."tenant_id", "task"."note_id" FROM "task" LEFT JOIN "note" AS "task__note" ON "task"."note_id" = "task__note"."id" AND "task__note"."tenant_id" = 7 WHERE "task__note"."text" = 'hello' AND "task"."tenant_id" = 7"#,
+            r#"SELECT COUNT(*) AS "count" FROM "task" LEFT JOIN "note" AS "task__note" ON "task"."note_id" = "task__note"."id" AND "task__note"."tenant_id" = 7 WHERE "task__note"."text" = 'hello' AND "task"."tenant_id" = 7"#,
+        ]
+    );
+
+    // without a tenant, no records are matched and none can be created
+    assert_eq!(
+        SqliteDbCtx.get_sql::<Note>(1),
+        [r#"SELECT "id", "tenant_id", "text" FROM "note" WHERE "id" = 1 AND 1 = 0"#]
+    );
+    let error = SqliteDbCtx.create_sql::<Note>(create).unwrap_err();
+    assert!(matches!(error, sqlx::Error::Configuration(_)));
+}
+
+#[tokio::test]
+#[cfg(feature = "sqlite")]
+async fn create_without_tenant() {
+    use lazybe::db::sqlite::SqliteDbCtx;
+    use lazybe::db::{DbOps, TenantDbCtx};
+    use sqlx::SqlitePool;
+
+    let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
+    sqlx::query("CREATE TABLE note (id INTEGER PRIMARY KEY, tenant_id INTEGER NOT NULL, text TEXT NOT NULL)")
+        .execute(&pool)
+        .await
+        .unwrap();
+    let create = CreateNote {
+        text: "hello".to_string(),
+    };
+
+    let error = SqliteDbCtx.create::<Note>(&pool, create.clone()).await.unwrap_err();
+    assert!(matches!(error, sqlx::Error::Configuration(_)));
+    assert_eq!(
+        error.to_string(),
+        "error with configuration: `Note` cannot be created without a tenant, see `DbCtx::tenant`"
+    );
+
+    let ctx = TenantDbCtx::new(SqliteDbCtx, Some(7.into()));
+    let note = ctx.create::<Note>(&pool, create).await.unwrap();
+    assert_eq!((note.tenant_id, note.text.as_str()), (7, "hello"));
+}
+
+#[test]
+#[cfg(feature = "sqlite")]
+fn actor_sql() {
+    use lazybe::db::ActorDbCtx;
+    use lazybe::db::sqlite::SqliteDbCtx;
+
+    let ctx = ActorDbCtx::new(SqliteDbCtx, Some("alice".into()));
+    let create = CreateComment {
+        text: "hello".to_string(),
+    };
+    let update = UpdateComment {
+        text: Some("bye".to_string()),
+    };
+
+    assert_eq!(
+        ctx.create_sql::<Comment>(create.clone()).unwrap(),
+        [
+            r#"INSERT INTO "comment" ("text", "created_by", "updated_by") VALUES ('hello', 'alice', 'alice') RETURNING *"#
+        ]
+    );
+    assert_eq!(
+        ctx.update_sql::<Comment>(1, update.clone()).unwrap(),
+        [r#"UPDATE "comment" SET "text" = 'bye', "updated_by" = 'alice' WHERE "id" = 1 RETURNING *"#]
+    );
+
+    // without an actor, the fields are set to NULL
+    assert_eq!(
+        SqliteDbCtx.create_sql::<Comment>(create).unwrap(),
+        [r#"INSERT INTO "comment" ("text", "created_by", "updated_by") VALUES ('hello', NULL, NULL) RETURNING *"#]
+    );
+    assert_eq!(
+        SqliteDbCtx.update_sql::<Comment>(1, update).unwrap(),
+        [r#"UPDATE "comment" SET "text" = 'bye', "updated_by" = NULL WHERE "id" = 1 RETURNING *"#]
+    );
+}
+
+#[test]
+#[cfg(feature = "sqlite")]
+fn required_actor_sql() {
+    use lazybe::db::ActorDbCtx;
+    use lazybe::db::sqlite::SqliteDbCtx;
+
+    let ctx = ActorDbCtx::new(SqliteDbCtx, Some("alice".into()));
+    let create = CreateReview {
+        text: "hello".to_string(),
+    };
+    let update = UpdateReview {
+        text: Some("bye".to_string()),
+    };
+
+    assert_eq!(
+        ctx.create_sql::<Review>(create.clone()).unwrap(),
+        [r#"INSERT INTO "review" ("text", "created_by", "updated_by") VALUES ('hello', 'alice', 'alice') RETURNING *"#]
+    );
+    assert_eq!(
+        ctx.update_sql::<Review>(1, update.clone()).unwrap(),
+        [r#"UPDATE "review" SET "text" = 'bye', "updated_by" = 'alice' WHERE "id" = 1 RETURNING *"#]
+    );
+
+    // without an actor, fields that are not optional cannot be set
+    assert_eq!(
+        SqliteDbCtx.create_sql::<Review>(create).unwrap_err().to_string(),
+        "error with configuration: `Review` cannot be created without an actor, see `DbCtx::actor`"
+    );
+    assert_eq!(
+        SqliteDbCtx.update_sql::<Review>(1, update).unwrap_err().to_string(),
+        "error with configuration: `Review` cannot be updated without an actor, see `DbCtx::actor`"
+    );
+}
+
+/// Stores the tables in a schema
+#[cfg(feature = "sqlite")]
+struct SchemaDbCtx;
+
+#[cfg(feature = "sqlite")]
+impl lazybe::db::DbCtx<sqlx::Sqlite> for SchemaDbCtx {
+    type Qb = sea_query::SqliteQueryBuilder;
+
+    fn query_builder(&self) -> Self::Qb {
+        sea_query::SqliteQueryBuilder
+    }
+
+    fn table_ref(&self, table: sea_query::DynIden) -> sea_query::TableRef {
+        sea_query::TableRef::SchemaTable(sea_query::IntoIden::into_iden(sea_query::Alias::new("library")), table)
+    }
+}
+
+/// Stores the tables with a prefix
+#[cfg(feature = "sqlite")]
+struct PrefixDbCtx;
+
+#[cfg(feature = "sqlite")]
+impl lazybe::db::DbCtx<sqlx::Sqlite> for PrefixDbCtx {
+    type Qb = sea_query::SqliteQueryBuilder;
+
+    fn query_builder(&self) -> Self::Qb {
+        sea_query::SqliteQueryBuilder
+    }
+
+    fn table_ref(&self, table: sea_query::DynIden) -> sea_query::TableRef {
+        let table = sea_query::Alias::new(format!("t1_{}", table.to_string()));
+        sea_query::TableRef::Table(sea_query::IntoIden::into_iden(table))
+    }
+}
+
+/// The statements of every operation with the tables resolved by a context
+#[cfg(feature = "sqlite")]
+fn resolved_sql<Ctx: lazybe::db::DbCtx<sqlx::Sqlite>>(ctx: &Ctx) -> Vec<String> {
+    let (filter, sort, pagination) = list_input();
+    let filter = Filter::all([filter, BookFilter::search("dune")]);
+    let mut sql = ctx.get_sql::<Book>(1);
+    sql.extend(ctx.list_sql::<Book>(filter, sort, pagination));
+    sql.extend(ctx.create_sql::<Book>(create_input()).unwrap());
+    sql.extend(ctx.update_sql::<Book>(1, update_input()).unwrap());
+    sql.extend(ctx.delete_sql::<Book>(1));
+    sql
+}
+
+#[test]
+#[cfg(feature = "sqlite")]
+fn schema_table_sql() {
+    // the selected tables are aliased with the entity table, the FTS table is in the same schema
+    assert_eq!(
+        resolved_sql(&SchemaDbCtx),
+        [
+            r#"SELECT "id", "title", "pages", "author_id" FROM "library"."book" AS "book" WHERE "id" = 1"#,
+            r#"SELECT "book"."id", "book"."title", "book"."pages", "book"."author_id" FROM "library"."book" AS "book" LEFT JOIN "library"."author" AS "book__author" ON "book"."author_id" = "book__author"."id" WHERE "book"."title" LIKE '%Dune%' AND "book__author"."name" = 'Frank Herbert' AND "book"."id" IN (SELECT "id" FROM "library"."book_fts" WHERE "book_fts" MATCH '"dune"') ORDER BY "book__author"."name" ASC, "book"."id" DESC LIMIT 10 OFFSET 20"#,
+            r#"SELECT COUNT(*) AS "count" FROM "library"."book" AS "book" LEFT JOIN "library"."author" AS "book__author" ON "book"."author_id" = "book__author"."id" WHERE "book"."title" LIKE '%Dune%' AND "book__author"."name" = 'Frank Herbert' AND "book"."id" IN (SELECT "id" FROM "library"."book_fts" WHERE "book_fts" MATCH '"dune"')"#,
+            r#"INSERT INTO "library"."book" ("title", "pages", "author_id") VALUES ('Dune', NULL, 1) RETURNING *"#,
+            r#"UPDATE "library"."book" SET "title" = 'Dune Messiah', "pages" = 256 WHERE "id" = 1 RETURNING *"#,
+            r#"DELETE FROM "library"."book" WHERE "id" = 1"#,
+        ]
+    );
+}
+
+#[test]
+#[cfg(feature = "sqlite")]
+fn prefix_table_sql() {
+    // the FTS table is prefixed like the entity table
+    assert_eq!(
+        resolved_sql(&PrefixDbCtx),
+        [
+            r#"SELECT "id", "title", "pages", "author_id" FROM "t1_book" AS "book" WHERE "id" = 1"#,
+            r#"SELECT "book"."id", "book"."title", "book"."pages", "book"."author_id" FROM "t1_book" AS "book" LEFT JOIN "t1_author" AS "book__author" ON "book"."author_id" = "book__author"."id" WHERE "book"."title" LIKE '%Dune%' AND "book__author"."name" = 'Frank Herbert' AND "book"."id" IN (SELECT "id" FROM "t1_book_fts" WHERE "t1_book_fts" MATCH '"dune"') ORDER BY "book__author"."name" ASC, "book"."id" DESC LIMIT 10 OFFSET 20"#,
+            r#"SELECT COUNT(*) AS "count" FROM "t1_book" AS "book" LEFT JOIN "t1_author" AS "book__author" ON "book"."author_id" = "book__author"."id" WHERE "book"."title" LIKE '%Dune%' AND "book__author"."name" = 'Frank Herbert' AND "book"."id" IN (SELECT "id" FROM "t1_book_fts" WHERE "t1_book_fts" MATCH '"dune"')"#,
+            r#"INSERT INTO "t1_book" ("title", "pages", "author_id") VALUES ('Dune', NULL, 1) RETURNING *"#,
+            r#"UPDATE "t1_book" SET "title" = 'Dune Messiah', "pages" = 256 WHERE "id" = 1 RETURNING *"#,
+            r#"DELETE FROM "t1_book" WHERE "id" = 1"#,
+        ]
+    );
+}
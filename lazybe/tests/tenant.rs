@@ -0,0 +1,128 @@
+use axum::Router;
+use axum::body::Body;
+use axum::http::request::Parts;
+use axum::http::{Method, Request, StatusCode};
+use axum::response::Response;
+use lazybe::db::sqlite::SqliteDbCtx;
+use lazybe::macros::Entity;
+use lazybe::router::{CreateRouter, DeleteRouter, GetRouter, RouteConfig, UpdateRouter};
+use lazybe::schema::TableSchema;
+use sea_query::SqliteQueryBuilder;
+use serde::{Deserialize, Serialize};
+use sqlx::{Executor, Pool, Sqlite, SqlitePool};
+
+mod common;
+
+use common::send;
+
+/// A route config that reads the tenant from the `x-tenant-id` header, if any
+#[derive(Clone)]
+struct TenantState {
+    pool: SqlitePool,
+}
+
+impl RouteConfig for TenantState {
+    type Ctx = SqliteDbCtx;
+    type Db = Sqlite;
+
+    fn db_ctx(&self) -> (Self::Ctx, Pool<Self::Db>) {
+        (SqliteDbCtx, self.pool.clone())
+    }
+
+    async fn tenant(&self, parts: &mut Parts) -> Result<Option<sea_query::Value>, Response> {
+        let tenant_id = parts
+            .headers
+            .get("x-tenant-id")
+            .and_then(|value| value.to_str().ok()?.parse::<i32>().ok());
+        Ok(tenant_id.map(Into::into))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Entity)]
+#[lazybe(table = "note", endpoint = "/notes")]
+pub struct Note {
+    #[lazybe(primary_key)]
+    pub id: i32,
+    #[lazybe(tenant)]
+    pub tenant_id: i32,
+    pub text: String,
+}
+
+async fn setup() -> anyhow::Result<(SqlitePool, Router)> {
+    let pool = SqlitePool::connect("sqlite::memory:").await?;
+    pool.execute(Note::create_table().to_string(SqliteQueryBuilder).as_str())
+        .await?;
+    pool.execute("INSERT INTO note (tenant_id, text) VALUES (1, 'mine'), (2, 'theirs')")
+        .await?;
+    let router = Router::new()
+        .merge(Note::get_endpoint())
+        .merge(Note::create_endpoint())
+        .merge(Note::update_endpoint())
+        .merge(Note::delete_endpoint())
+        .with_state(TenantState { pool: pool.clone() });
+    Ok((pool, router))
+}
+
+async fn request(router: &Router, method: Method, uri: &str, tenant: Option<&str>, body: &str) -> (StatusCode, String) {
+    let mut request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header("content-type", "application/json");
+    if let Some(tenant) = tenant {
+        request = request.header("x-tenant-id", tenant);
+    }
+    send(router, request.body(Body::from(body.to_string())).unwrap()).await
+}
+
+async fn texts(pool: &SqlitePool) -> anyhow::Result<Vec<(i32, String)>> {
+    Ok(sqlx::query_as("SELECT tenant_id, text FROM note ORDER BY id")
+        .fetch_all(pool)
+        .await?)
+}
+
+#[tokio::test]
+async fn another_tenant_is_not_found() -> anyhow::Result<()> {
+    let (pool, router) = setup().await?;
+
+    let (status, body) = request(&router, Method::GET, "/notes/1", Some("1"), "").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(serde_json::from_str::<Note>(&body)?.text, "mine");
+
+    // the note 2 belongs to the tenant 2
+    let (status, _) = request(&router, Method::GET, "/notes/2", Some("1"), "").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = request(&router, Method::PATCH, "/notes/2", Some("1"), r#"{"text": "stolen"}"#).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = request(&router, Method::DELETE, "/notes/2", Some("1"), "").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    assert_eq!(
+        texts(&pool).await?,
+        [(1, "mine".to_string()), (2, "theirs".to_string())]
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn missing_tenant_is_rejected() -> anyhow::Result<()> {
+    let (pool, router) = setup().await?;
+
+    let (status, _) = request(&router, Method::GET, "/notes/1", None, "").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = request(&router, Method::POST, "/notes", None, r#"{"text": "orphan"}"#).await;
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+
+    let (status, body) = request(&router, Method::POST, "/notes", Some("2"), r#"{"text": "new"}"#).await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(serde_json::from_str::<Note>(&body)?.tenant_id, 2);
+
+    assert_eq!(
+        texts(&pool).await?,
+        [
+            (1, "mine".to_string()),
+            (2, "theirs".to_string()),
+            (2, "new".to_string())
+        ]
+    );
+    Ok(())
+}
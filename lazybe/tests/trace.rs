@@ -0,0 +1,73 @@
+use std::time::Duration;
+
+use lazybe::db::{DbCtx, DbOps};
+use lazybe::macros::Entity;
+use lazybe::schema::TableSchema;
+use sea_query::SqliteQueryBuilder;
+use sqlx::{Executor, Sqlite, SqlitePool};
+
+mod common;
+
+use common::Logs;
+
+/// Reports every operation as a slow one
+struct SlowDbCtx;
+
+impl DbCtx<Sqlite> for SlowDbCtx {
+    type Qb = SqliteQueryBuilder;
+
+    fn query_builder(&self) -> Self::Qb {
+        SqliteQueryBuilder
+    }
+
+    fn slow_query_threshold(&self) -> Option<Duration> {
+        Some(Duration::ZERO)
+    }
+}
+
+#[derive(Debug, Clone, Entity)]
+#[lazybe(table = "book")]
+pub struct Book {
+    #[lazybe(primary_key)]
+    pub id: i32,
+    pub title: String,
+}
+
+#[tokio::test]
+async fn trace_operations() -> anyhow::Result<()> {
+    let pool = SqlitePool::connect("sqlite::memory:").await?;
+    pool.execute(Book::create_table().to_string(SqliteQueryBuilder).as_str())
+        .await?;
+    pool.execute("INSERT INTO book (title) VALUES ('Dune'), ('Dune Messiah')")
+        .await?;
+
+    let logs = Logs::default();
+    let _guard = tracing::subscriber::set_default(logs.subscriber());
+    SlowDbCtx.get::<Book>(&pool, 1).await?;
+    assert!(SlowDbCtx.delete::<Book>(&pool, 2).await?);
+    assert!(!SlowDbCtx.delete::<Book>(&pool, 2).await?);
+
+    // every operation reaches the threshold of zero and is reported within its span
+    let lines = logs.lines();
+    assert_eq!(lines.len(), 3, "{:#?}", lines);
+    for (line, op, sql, rows) in [
+        (
+            &lines[0],
+            "get",
+            r#"SELECT \"id\", \"title\" FROM \"book\" WHERE \"id\" = ?"#,
+            1,
+        ),
+        (&lines[1], "delete", r#"DELETE FROM \"book\" WHERE \"id\" = ?"#, 1),
+        (&lines[2], "delete", r#"DELETE FROM \"book\" WHERE \"id\" = ?"#, 0),
+    ] {
+        let (span, event) = line.split_once("}: ").unwrap();
+        assert!(span.starts_with(r#" WARN db_op{entity="Book""#), "{}", line);
+        assert!(span.contains(&format!(r#"op="{}""#, op)), "{}", line);
+        // the values of the statements are redacted
+        assert!(span.contains(&format!(r#"sql="{}""#, sql)), "{}", line);
+        assert!(span.contains("duration_ms="), "{}", line);
+        assert!(span.ends_with(&format!("rows={}", rows)), "{}", line);
+        assert!(event.contains("Slow database operation"), "{}", line);
+    }
+    Ok(())
+}
@@ -0,0 +1,96 @@
+use std::time::Duration;
+
+use lazybe::db::sqlite::SqliteDbCtx;
+use lazybe::db::{DbOps, IsolationLevel, TransactionOptions, with_transaction};
+use lazybe::filter::Filter;
+use lazybe::macros::Entity;
+use lazybe::schema::TableSchema;
+use lazybe::sort::Sort;
+use sea_query::SqliteQueryBuilder;
+use sqlx::error::ErrorKind;
+use sqlx::{Executor, SqlitePool};
+
+mod common;
+
+use common::FakeDbError;
+
+#[derive(Debug, Clone, Entity)]
+#[lazybe(table = "book")]
+pub struct Book {
+    #[lazybe(primary_key)]
+    pub id: i32,
+    pub title: String,
+}
+
+fn serialization_failure() -> sqlx::Error {
+    FakeDbError {
+        kind: ErrorKind::Other,
+        code: Some("40001"),
+        message: "could not serialize access due to concurrent update",
+    }
+    .into_error()
+}
+
+/// Create a book in a transaction that fails with `error` on the first `failures` attempts
+async fn create_book(
+    pool: &SqlitePool,
+    options: &TransactionOptions,
+    failures: u32,
+    error: fn() -> sqlx::Error,
+) -> (u32, Result<Book, sqlx::Error>) {
+    let mut attempts = 0;
+    let result = with_transaction(&SqliteDbCtx, pool, options, |ctx, tx| {
+        attempts += 1;
+        let attempt = attempts;
+        Box::pin(async move {
+            let input = CreateBook {
+                title: "Dune".to_string(),
+            };
+            let book = ctx.create::<Book>(&mut **tx, input).await?;
+            if attempt <= failures {
+                return Err(error());
+            }
+            Ok(book)
+        })
+    })
+    .await;
+    (attempts, result)
+}
+
+#[tokio::test]
+async fn retry_transaction() -> anyhow::Result<()> {
+    let pool = SqlitePool::connect("sqlite::memory:").await?;
+    pool.execute(Book::create_table().to_string(SqliteQueryBuilder).as_str())
+        .await?;
+    let options = TransactionOptions::new()
+        .isolation_level(IsolationLevel::Serializable)
+        .backoff(Duration::from_millis(1), Duration::from_millis(1));
+
+    // the failed attempts are rolled back
+    let (attempts, result) = create_book(&pool, &options, 2, serialization_failure).await;
+    assert_eq!(attempts, 3);
+    assert_eq!(result?.id, 1);
+
+    // the last error is returned after the max attempts
+    let (attempts, result) = create_book(&pool, &options, 3, serialization_failure).await;
+    assert_eq!(attempts, 3);
+    assert_eq!(
+        result
+            .unwrap_err()
+            .as_database_error()
+            .and_then(|e| e.code())
+            .as_deref(),
+        Some("40001")
+    );
+
+    // other errors are not retried
+    let (attempts, result) = create_book(&pool, &options, 1, || sqlx::Error::RowNotFound).await;
+    assert_eq!(attempts, 1);
+    assert!(matches!(result, Err(sqlx::Error::RowNotFound)));
+
+    let books = SqliteDbCtx
+        .list::<Book>(&pool, Filter::empty(), Sort::empty(), None)
+        .await?;
+    assert_eq!(books.total_records, 1);
+    Ok(())
+}
@@ -0,0 +1,78 @@
+use axum::Router;
+use axum::http::{Method, StatusCode};
+use lazybe::macros::Entity;
+use lazybe::router::{CreateRouter, DeleteRouter, ErrorResponse};
+use serde::{Deserialize, Serialize};
+use sqlx::{Executor, SqlitePool};
+
+mod common;
+
+use common::{AppState, request};
+
+#[derive(Debug, Clone, Serialize, Deserialize, Entity)]
+#[lazybe(table = "author", endpoint = "/authors")]
+pub struct Author {
+    #[lazybe(primary_key)]
+    pub id: i32,
+    pub name: String,
+}
+
+async fn setup() -> anyhow::Result<(SqlitePool, Router)> {
+    // SQLite enforces foreign keys of a pool connected by sqlx
+    let pool = SqlitePool::connect("sqlite::memory:").await?;
+    pool.execute("CREATE TABLE author (id INTEGER PRIMARY KEY AUTOINCREMENT, name TEXT NOT NULL UNIQUE)")
+        .await?;
+    pool.execute(
+        "CREATE TABLE book (id INTEGER PRIMARY KEY AUTOINCREMENT, author_id INTEGER NOT NULL REFERENCES author (id))",
+    )
+    .await?;
+    let router = Router::new()
+        .merge(Author::create_endpoint())
+        .merge(Author::delete_endpoint())
+        .with_state(AppState { pool: pool.clone() });
+    Ok((pool, router))
+}
+
+#[tokio::test]
+async fn unique_violation_is_conflict() -> anyhow::Result<()> {
+    let (pool, router) = setup().await?;
+
+    let (status, _) = request(&router, Method::POST, "/authors", r#"{"name": "Frank Herbert"}"#).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let (status, body) = request(&router, Method::POST, "/authors", r#"{"name": "Frank Herbert"}"#).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    let error = serde_json::from_str::<ErrorResponse>(&body)?;
+    assert_eq!(error.title, "Conflict");
+    assert_eq!(
+        error.detail.as_deref(),
+        Some("An entity with the same value already exists (column `name`).")
+    );
+
+    let (authors,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM author").fetch_one(&pool).await?;
+    assert_eq!(authors, 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn foreign_key_violation_on_delete_is_conflict() -> anyhow::Result<()> {
+    let (pool, router) = setup().await?;
+    pool.execute("INSERT INTO author (name) VALUES ('Frank Herbert'), ('Ursula K. Le Guin')")
+        .await?;
+    pool.execute("INSERT INTO book (author_id) VALUES (1)").await?;
+
+    let (status, body) = request(&router, Method::DELETE, "/authors/1", "").await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    let error = serde_json::from_str::<ErrorResponse>(&body)?;
+    assert_eq!(error.title, "Conflict");
+    assert_eq!(
+        error.detail.as_deref(),
+        Some("The entity is still referenced by other entities.")
+    );
+
+    // an author without books is deleted
+    let (status, _) = request(&router, Method::DELETE, "/authors/2", "").await;
+    assert_eq!(status, StatusCode::OK);
+    let authors: Vec<(i32,)> = sqlx::query_as("SELECT id FROM author").fetch_all(&pool).await?;
+    assert_eq!(authors, [(1,)]);
+    Ok(())
+}